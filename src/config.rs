@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+/// The directory that persistent settings (recent colors, etc.)
+/// are stored in: `$XDG_CONFIG_HOME/rs-paint` (or the platform equivalent)
+pub fn config_dir() -> PathBuf {
+    gtk::glib::user_config_dir().join("rs-paint")
+}
+
+pub fn config_path(file_name: &str) -> PathBuf {
+    config_dir().join(file_name)
+}
+
+/// Reads the given config file, returning `None` if it
+/// doesn't exist (or can't be read)
+pub fn read_config_file(file_name: &str) -> Option<String> {
+    std::fs::read_to_string(config_path(file_name)).ok()
+}
+
+/// Like `read_config_file`, for binary files
+pub fn read_config_bytes(file_name: &str) -> Option<Vec<u8>> {
+    std::fs::read(config_path(file_name)).ok()
+}
+
+/// Writes the given config file, creating the config directory if necessary
+pub fn write_config_file(file_name: &str, contents: impl AsRef<[u8]>) -> Result<(), String> {
+    std::fs::create_dir_all(config_dir())
+        .map_err(|e| e.to_string())?;
+    std::fs::write(config_path(file_name), contents)
+        .map_err(|e| e.to_string())
+}
@@ -77,3 +77,12 @@ pub fn vec_plus((x0, y0): (f64, f64), (x1, y1): (f64, f64)) -> (f64, f64) {
 pub fn vec_scale(s: f64, (x, y): (f64, f64)) -> (f64, f64) {
     (s * x, s * y)
 }
+
+/// `point`, rotated about `anchor` to the nearest multiple of `step` (radians)
+pub fn snap_to_angle(anchor: (f64, f64), point: (f64, f64), step: f64) -> (f64, f64) {
+    let (dx, dy) = (point.0 - anchor.0, point.1 - anchor.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    let angle = (dy.atan2(dx) / step).round() * step;
+
+    (anchor.0 + length * angle.cos(), anchor.1 + length * angle.sin())
+}
@@ -5,13 +5,20 @@ pub mod blend;
 pub mod transform;
 pub mod resize;
 pub mod bitmask;
+pub mod filter;
+pub mod quantize;
+pub mod color;
 pub mod io;
+pub mod vector_path;
+pub mod gradient;
 
 use std::collections::HashMap;
 
 use gtk::cairo;
 use gtk::gdk::RGBA;
 
+use vector_path::VectorPath;
+
 use serde_derive::{Serialize, Deserialize};
 
 /// The ubiquitous (r, g, b, a) pixel type, used for
@@ -55,7 +62,7 @@ impl Pixel {
         DrawablePixel::from_rgba(self.r, self.g, self.b, self.a)
     }
 
-    fn scale_alpha(&self, amount: f64) -> Pixel {
+    pub fn scale_alpha(&self, amount: f64) -> Pixel {
         Pixel::from_rgba(self.r, self.g, self.b, (self.a as f64 * amount) as u8)
     }
 
@@ -585,6 +592,9 @@ pub struct FusedLayeredImage {
 
     pix_modified_since_draw: HashMap<usize, Pixel>,
     pix_modified_since_save: HashMap<usize, (Pixel, Pixel)>,
+
+    /// The pen tool's paths (edits are recorded with `ImageDiff::Paths`)
+    paths: Vec<VectorPath>,
 }
 
 impl FusedLayeredImage {
@@ -596,9 +606,18 @@ impl FusedLayeredImage {
             active_layer_index: LayerIndex::BaseLayer,
             pix_modified_since_draw: HashMap::new(),
             pix_modified_since_save: HashMap::new(),
+            paths: Vec::new(),
         }
     }
 
+    pub fn paths(&self) -> &Vec<VectorPath> {
+        &self.paths
+    }
+
+    pub fn paths_mut(&mut self) -> &mut Vec<VectorPath> {
+        &mut self.paths
+    }
+
     #[inline]
     fn active_fused_layer(&self) -> &FusedLayer {
         match self.active_layer_index {
@@ -716,6 +735,28 @@ impl FusedLayeredImage {
             })
     }
 
+    /// The visible color of the given pixel (blended across all
+    /// visible layers), or `None` if it's out of bounds
+    pub fn try_composite_pix_at(&self, r: i32, c: i32) -> Option<Pixel> {
+        if r < 0 || c < 0 || r >= self.height() || c >= self.width() {
+            return None;
+        }
+
+        let i = r as usize * self.width() as usize + c as usize;
+        Some(self.get_blended_pixel_at(i).to_pixel_lossy())
+    }
+
+    /// The visible image (like `gen_entire_blended_image`, but
+    /// leaving out hidden layers)
+    pub fn composite_image(&self) -> Image {
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        let pixels = (0..width * height)
+            .map(|i| self.get_blended_pixel_at(i).to_pixel_lossy())
+            .collect::<Vec<_>>();
+
+        Image::new(pixels, width, height)
+    }
+
     /// Updates the size of every drawable to
     /// match the image sizes (which should all match)
     fn update_drawable_sizes(&mut self) {
@@ -768,6 +809,16 @@ impl FusedLayeredImage {
         (mod_pix, self.active_layer_index)
     }
 
+    /// The active layer's pixel at (`r`, `c`) as of the last commit
+    /// (before any of the changes since, e.g. the current stroke's)
+    pub fn committed_pix_at(&self, r: usize, c: usize) -> &Pixel {
+        let i = r * self.active_image().width + c;
+
+        self.pix_modified_since_save.get(&i).map(|(before, _after)| before)
+            .or_else(|| self.pix_modified_since_draw.get(&i))
+            .unwrap_or(&self.active_image().pixels[i])
+    }
+
     pub fn has_unsaved_changes(&self) -> bool {
         (!self.pix_modified_since_save.is_empty()) ||
         (!self.pix_modified_since_draw.is_empty())
@@ -44,13 +44,15 @@ impl ImageBitmask {
     }
 
     /// Generic function to flood-fill a `Canvas`'s `Image` to obtain
-    /// a bitmask; used for both magic wand and fill
+    /// a bitmask; used for both magic wand and fill. The fill spreads
+    /// diagonally too iff `diagonal` (8-way rather than 4-way connectivity).
     pub fn from_flood_fill(
         image: &impl ImageLike,
         tolerance: f64,
         or: usize,
         oc: usize,
         tolerance_reference: Option<&Pixel>, // pixel to use in tolerance computation
+        diagonal: bool,
     ) -> Self {
         let w = image.width();
         let h = image.height();
@@ -64,7 +66,9 @@ impl ImageBitmask {
             // the pixel which we're computing the tolerance test with
             let reference = tolerance_reference.unwrap_or(image.try_pix_at(r, c).unwrap());
 
-            for (nr, nc) in in_bounds_4d_neighbors(r, c, w, h).into_iter() {
+            let offsets: &[(isize, isize)] = if diagonal { &NEIGHBORS_8D } else { &NEIGHBORS_4D };
+
+            for (nr, nc) in in_bounds_neighbors(r, c, w, h, offsets).into_iter() {
                 if *res.flat_index(nr, nc) {
                     continue; // already visited, continue
                 }
@@ -83,6 +87,18 @@ impl ImageBitmask {
         res
     }
 
+    /// Selects every pixel of `image` that tolerates `reference`,
+    /// whether or not it's connected to the others (so there's no
+    /// relative tolerance or diagonal connectivity, unlike `from_flood_fill`)
+    pub fn from_color_match(image: &impl ImageLike, tolerance: f64, reference: &Pixel) -> Self {
+        let (w, h) = (image.width(), image.height());
+        let bits = (0..w * h)
+            .map(|i| fulfills_tolerance(reference, image.try_pix_at(i / w, i % w).unwrap(), tolerance))
+            .collect::<Vec<_>>();
+
+        ImageBitmask::from_flat_bits(h, w, bits)
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -101,9 +117,9 @@ impl ImageBitmask {
         )
     }
 
-    /// Creates a `cairo::Path` from all of the set-pixel-to-unset-pixel
-    /// boundaries
-    fn gen_edge_path(&self, cr: &cairo::Context) -> cairo::Path {
+    /// Traces all of the set-pixel-to-unset-pixel boundaries
+    /// into chains of (x, y) grid points
+    pub fn edge_loops(&self) -> Vec<Vec<(usize, usize)>> {
         #[inline]
         fn is_active(bitmask: &ImageBitmask, x: i32, y: i32) -> bool {
             x >= 0 && y >= 0 &&
@@ -164,8 +180,7 @@ impl ImageBitmask {
 
         // trace each "strongly-connected-component"
 
-        cr.new_path();
-
+        let mut loops = vec![];
         let mut vis = vec![false; segments.len()];
 
         for i in 0..vis.len() {
@@ -174,14 +189,14 @@ impl ImageBitmask {
             }
 
             let (p0, p1) = segments[i];
-            cr.move_to(p0.0 as f64, p0.1 as f64);
+            let mut points = vec![p0];
 
             let mut curr = i;
             let mut end_point = p1;
 
             loop {
                 vis[curr] = true;
-                cr.line_to(end_point.0 as f64, end_point.1 as f64);
+                points.push(end_point);
 
                 let neigh_idxs = &endpoints_to_seg_idxs[&end_point];
                 if let Some(other_idx) = neigh_idxs.iter()
@@ -199,6 +214,25 @@ impl ImageBitmask {
                     break;
                 }
             }
+
+            loops.push(points);
+        }
+
+        loops
+    }
+
+    /// Creates a `cairo::Path` from all of the set-pixel-to-unset-pixel
+    /// boundaries
+    fn gen_edge_path(&self, cr: &cairo::Context) -> cairo::Path {
+        cr.new_path();
+
+        for points in self.edge_loops() {
+            let (x, y) = points[0];
+            cr.move_to(x as f64, y as f64);
+
+            for &(x, y) in points[1..].iter() {
+                cr.line_to(x as f64, y as f64);
+            }
         }
 
         cr.copy_path().unwrap()
@@ -335,6 +369,40 @@ impl ImageBitmask {
     pub fn bit_at(&self, i: usize) -> bool {
         self.bits[i]
     }
+
+    /// The mask grown outwards by `radius` pixels (setting every
+    /// bit within a circle of that radius of a set bit)
+    pub fn expanded(self, radius: usize) -> Self {
+        if radius == 0 {
+            return self;
+        }
+
+        let mut res = ImageBitmask::from_flat_bits(self.height, self.width, self.bits.clone());
+        let r_i = radius as i32;
+        let offsets = (-r_i..=r_i)
+            .flat_map(|dr| (-r_i..=r_i).map(move |dc| (dr, dc)))
+            .filter(|(dr, dc)| dr * dr + dc * dc <= r_i * r_i)
+            .collect::<Vec<_>>();
+
+        // only bits on the edge of the mask can reach past it
+        let edge_bits = self.coords_of_active_bits()
+            .filter(|&(r, c)| {
+                let neighbors = in_bounds_neighbors(r, c, self.width, self.height, &NEIGHBORS_4D);
+                neighbors.len() < NEIGHBORS_4D.len() || neighbors.into_iter().any(|(nr, nc)| !self.bits[nr * self.width + nc])
+            })
+            .collect::<Vec<_>>();
+
+        for (r, c) in edge_bits.into_iter() {
+            for (dr, dc) in offsets.iter() {
+                let (nr, nc) = (r as i32 + dr, c as i32 + dc);
+                if nr >= 0 && nc >= 0 && (nr as usize) < self.height && (nc as usize) < self.width {
+                    *res.flat_index(nr as usize, nc as usize) = true;
+                }
+            }
+        }
+
+        res
+    }
 }
 
 /// Returns `true` iff `a` "tolerates" (is close to) `b`
@@ -351,25 +419,23 @@ fn fulfills_tolerance(a: &Pixel, b: &Pixel, tolerance: f64) -> bool {
     <= tolerance.powi(2)
 }
 
-/// Looks in 4 directions from (r, c), returning the coordinates
-/// of any in-bounds cells
+/// Offsets to the 4 orthogonal neighbors of a cell
+const NEIGHBORS_4D: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+/// Offsets to all 8 neighbors of a cell (including diagonals)
+const NEIGHBORS_8D: [(isize, isize); 8] = [
+    (1, 0), (1, 1), (0, 1), (-1, 1),
+    (-1, 0), (-1, -1), (0, -1), (1, -1),
+];
+
+/// Looks from (r, c) by each of the (row, column) `offsets`,
+/// returning the coordinates of any in-bounds cells
 #[inline]
-fn in_bounds_4d_neighbors(r: usize, c: usize, w: usize, h: usize) -> Vec<(usize, usize)> {
-    let r = r as i32;
-    let c = c as i32;
-    vec![
-        (r + 1, c),
-        (r, c + 1),
-        (r - 1, c),
-        (r, c - 1),
-    ]
-    .into_iter()
-    .map(|(rp, cp)| (rp as usize, cp as usize))
-    .filter(|(rp, cp)| {
-        *rp < h && *rp != usize::MAX &&
-        *cp < w && *cp != usize::MAX
-    })
-    .collect::<Vec<_>>()
+fn in_bounds_neighbors(r: usize, c: usize, w: usize, h: usize, offsets: &[(isize, isize)]) -> Vec<(usize, usize)> {
+    offsets.iter()
+        .filter_map(|&(dr, dc)| Some((r.checked_add_signed(dr)?, c.checked_add_signed(dc)?)))
+        .filter(|&(rp, cp)| rp < h && cp < w)
+        .collect::<Vec<_>>()
 }
 
 pub struct DeletePix<I>
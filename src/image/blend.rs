@@ -1,14 +1,70 @@
 use super::Pixel;
 
+use serde_derive::{Serialize, Deserialize};
+
 // defines a way to average two pixels
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum BlendingMode {
     Overwrite,
     Average,
     Paint,
+    Multiply,
+    Screen,
+    Darken,
+    Lighten,
+    /// Reduces the alpha of the pixel below by the alpha of the pixel above
+    Erase,
+    /// Paints underneath the pixel below (so only its
+    /// transparent parts are affected)
+    Behind,
+}
+
+/// Composites `above` over `below` (source-over), with the color of
+/// overlapping regions determined by `blend_channel` (which
+/// takes and returns channels in [0, 1], below first)
+fn blend_separable(above: &Pixel, below: &Pixel, blend_channel: fn(f64, f64) -> f64) -> Pixel {
+    let a_above = above.a as f64 / 255.0;
+    let a_below = below.a as f64 / 255.0;
+    let a_out = a_above + a_below * (1.0 - a_above);
+
+    if a_out == 0.0 {
+        return Pixel::from_rgba(0, 0, 0, 0);
+    }
+
+    let channel = |c_above: u8, c_below: u8| {
+        let c_above = c_above as f64 / 255.0;
+        let c_below = c_below as f64 / 255.0;
+        let mixed = (1.0 - a_below) * c_above + a_below * blend_channel(c_below, c_above);
+        let premultiplied = a_above * mixed + (1.0 - a_above) * a_below * c_below;
+        ((premultiplied / a_out) * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+
+    Pixel::from_rgba(channel(above.r, below.r),
+                     channel(above.g, below.g),
+                     channel(above.b, below.b),
+                     (a_out * 255.0).round() as u8)
+}
+
+/// Composites `above` over `below` (plain source-over)
+pub fn composite_over(above: &Pixel, below: &Pixel) -> Pixel {
+    blend_separable(above, below, |_c_below, c_above| c_above)
 }
 
 impl BlendingMode {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Overwrite", BlendingMode::Overwrite),
+            ("Paint", BlendingMode::Paint),
+            ("Average", BlendingMode::Average),
+            ("Multiply", BlendingMode::Multiply),
+            ("Screen", BlendingMode::Screen),
+            ("Darken", BlendingMode::Darken),
+            ("Lighten", BlendingMode::Lighten),
+            ("Erase", BlendingMode::Erase),
+            ("Behind", BlendingMode::Behind),
+        ].iter().map(|x| x.clone())
+    }
+
     pub fn blend(&self, above: &Pixel, below: &Pixel) -> Pixel {
         match self {
             BlendingMode::Overwrite => above.clone(),
@@ -25,7 +81,16 @@ impl BlendingMode {
                                  (above.g as f64 * o + below.g as f64 * t) as u8,
                                  (above.b as f64 * o + below.b as f64 * t) as u8,
                                  std::cmp::max(above.a, below.a))
-            }
+            },
+            BlendingMode::Multiply => blend_separable(above, below, |b, a| b * a),
+            BlendingMode::Screen => blend_separable(above, below, |b, a| b + a - b * a),
+            BlendingMode::Darken => blend_separable(above, below, f64::min),
+            BlendingMode::Lighten => blend_separable(above, below, f64::max),
+            BlendingMode::Erase => {
+                let t = 1.0 - above.a as f64 / 255.0;
+                Pixel::from_rgba(below.r, below.g, below.b, (below.a as f64 * t).round() as u8)
+            },
+            BlendingMode::Behind => BlendingMode::Paint.blend(below, above),
         }
     }
-}
\ No newline at end of file
+}
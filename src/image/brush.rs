@@ -1,23 +1,131 @@
+mod dynamics;
+
 use super::bitmask::ImageBitmask;
-use super::{ImageLike, Pixel};
+use super::{Image, ImageLike, Pixel};
+pub use dynamics::{BrushDynamics, StrokeRng, VelocityCurve, VelocityDynamics};
 
 use gtk::gdk::RGBA;
 use gtk::prelude::*;
+use serde_derive::{Serialize, Deserialize};
+use std::rc::Rc;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum BrushType {
     Square,
     Round,
     Dither,
     Caligraphy,
+    /// Pixelates what's beneath it rather than drawing
+    Pixelate,
+    /// Uses a user-supplied `BrushTip` (falls back to `Round` without one)
+    Custom,
+}
+
+/// Which channel of an image becomes a `BrushTip`'s mask
+#[derive(Clone, Copy, PartialEq)]
+pub enum MaskSource {
+    Alpha,
+    /// Dark (opaque) pixels are painted, light ones aren't
+    Luminance,
 }
 
-#[derive(PartialEq)]
+impl MaskSource {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Alpha", MaskSource::Alpha),
+            ("Luminance", MaskSource::Luminance),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+/// A user-supplied brush shape: a coverage mask (0 => not painted,
+/// 255 => fully painted) that's tinted with the brush's color
+#[derive(PartialEq, Eq)]
+pub struct BrushTip {
+    name: String,
+    mask: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+impl BrushTip {
+    pub fn from_image(name: &str, image: &Image, source: MaskSource) -> Self {
+        let mask = image.pixels().iter()
+            .map(|p| match source {
+                MaskSource::Alpha => p.a,
+                MaskSource::Luminance => {
+                    let luminance = 0.299 * p.r as f64 + 0.587 * p.g as f64 + 0.114 * p.b as f64;
+                    ((255.0 - luminance) * p.a as f64 / 255.0).round() as u8
+                },
+            })
+            .collect::<Vec<_>>();
+
+        BrushTip {
+            name: String::from(name),
+            mask,
+            width: image.width(),
+            height: image.height(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The mask, as the alpha channel of a black image
+    /// (the format that tips are saved in)
+    pub fn to_image(&self) -> Image {
+        let pixels = self.mask.iter()
+            .map(|a| Pixel::from_rgba(0, 0, 0, *a))
+            .collect::<Vec<_>>();
+
+        Image::new(pixels, self.width, self.height)
+    }
+
+    /// The average coverage of the source region that maps onto
+    /// target pixel (`i`, `j`) when the tip is scaled by `scale`
+    fn scaled_coverage_at(&self, i: usize, j: usize, scale: f64) -> f64 {
+        let r0 = (i as f64 / scale).floor() as usize;
+        let c0 = (j as f64 / scale).floor() as usize;
+        let r1 = (((i + 1) as f64 / scale).ceil() as usize).clamp(r0 + 1, self.height);
+        let c1 = (((j + 1) as f64 / scale).ceil() as usize).clamp(c0 + 1, self.width);
+
+        let mut total = 0.0;
+        for r in r0..r1 {
+            for c in c0..c1 {
+                total += self.mask[r * self.width + c] as f64;
+            }
+        }
+
+        total / ((r1 - r0) * (c1 - c0)) as f64
+    }
+}
+
+#[derive(Clone)]
 struct BrushProperties {
     brush_type: BrushType,
     radius: u8,
     primary_color: RGBA,
     secondary_color: RGBA,
+    tip: Option<Rc<BrushTip>>,
+}
+
+impl PartialEq for BrushProperties {
+    fn eq(&self, other: &Self) -> bool {
+        // tips are shared from the brush library, so
+        // comparing pointers spares comparing whole masks
+        let same_tip = match (&self.tip, &other.tip) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+
+        self.brush_type == other.brush_type
+            && self.radius == other.radius
+            && self.primary_color == other.primary_color
+            && self.secondary_color == other.secondary_color
+            && same_tip
+    }
 }
 
 pub struct BrushImage {
@@ -38,6 +146,56 @@ impl BrushImage {
             pixel_options,
         }
     }
+
+    /// A copy rotated by `angle` radians (clockwise on-screen) about
+    /// its center, grown so that no corners are clipped
+    pub fn rotated(&self, angle: f64) -> Self {
+        let size = ((self.width.pow(2) + self.height.pow(2)) as f64).sqrt().ceil() as usize;
+        // keep the same parity, so the center stays on the same pixel
+        let size = size + (size + self.width) % 2;
+        let (sin, cos) = angle.sin_cos();
+        let src_center = ((self.height as f64 - 1.0) / 2.0, (self.width as f64 - 1.0) / 2.0);
+        let dst_center = (size as f64 - 1.0) / 2.0;
+
+        let pixel_options = (0..size * size)
+            .map(|k| {
+                let (y, x) = ((k / size) as f64 - dst_center, (k % size) as f64 - dst_center);
+                // inverse rotation, to find the source pixel
+                let r = (-sin * x + cos * y + src_center.0).round();
+                let c = (cos * x + sin * y + src_center.1).round();
+
+                if r < 0.0 || c < 0.0 || r >= self.height as f64 || c >= self.width as f64 {
+                    None
+                } else {
+                    self.pixel_options[r as usize * self.width + c as usize].clone()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            width: size,
+            height: size,
+            pixel_options,
+        }
+    }
+
+    /// The image mirrored left-to-right (`horizontal`) and/or top-to-bottom (`vertical`)
+    pub fn flipped(&self, horizontal: bool, vertical: bool) -> Self {
+        let pixel_options = (0..self.width * self.height)
+            .map(|k| {
+                let (r, c) = (k / self.width, k % self.width);
+                let r = if vertical { self.height - 1 - r } else { r };
+                let c = if horizontal { self.width - 1 - c } else { c };
+                self.pixel_options[r * self.width + c].clone()
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            width: self.width,
+            height: self.height,
+            pixel_options,
+        }
+    }
 }
 
 impl ImageLike for BrushImage {
@@ -68,6 +226,36 @@ fn mk_square_brush_image(n: u8, primary_color: RGBA, _secondary_color: RGBA) ->
     BrushImage::from_pixels_options(vec![vec![Some(p); n as usize]; n as usize])
 }
 
+/// The tip, scaled (preserving its aspect ratio) to fit
+/// in (and centered in) an `n` by `n` square
+fn mk_custom_brush_image(n: u8, tip: &BrushTip, primary_color: RGBA) -> BrushImage {
+    let p = Pixel::from_rgba_struct(primary_color);
+    let n = n as usize;
+    let mut pix = vec![vec![None; n]; n];
+
+    if tip.width == 0 || tip.height == 0 {
+        return BrushImage::from_pixels_options(pix);
+    }
+
+    let scale = n as f64 / tip.width.max(tip.height) as f64;
+    let scaled_width = ((tip.width as f64 * scale).round() as usize).clamp(1, n);
+    let scaled_height = ((tip.height as f64 * scale).round() as usize).clamp(1, n);
+    let row_offset = (n - scaled_height) / 2;
+    let col_offset = (n - scaled_width) / 2;
+
+    for i in 0..scaled_height {
+        for j in 0..scaled_width {
+            let coverage = tip.scaled_coverage_at(i, j, scale) / 255.0;
+
+            if coverage > 0.0 {
+                pix[i + row_offset][j + col_offset] = Some(p.scale_alpha(coverage));
+            }
+        }
+    }
+
+    BrushImage::from_pixels_options(pix)
+}
+
 fn mk_round_brush_image(n: u8, fade: bool, dither: bool, primary_color: RGBA, secondary_color: RGBA) -> BrushImage {
     let p = Pixel::from_rgba_struct(primary_color);
     let p2 = Pixel::from_rgba_struct(secondary_color);
@@ -140,15 +328,33 @@ impl Brush {
             BrushType::Round => mk_round_brush_image(r, false, false, props.primary_color, props.secondary_color),
             BrushType::Dither => mk_round_brush_image(r, false, true, props.primary_color, props.secondary_color),
             BrushType::Caligraphy => mk_caligraphy_brush_image(r, props.primary_color, props.secondary_color),
+            BrushType::Pixelate => mk_square_brush_image(r, props.primary_color, props.secondary_color),
+            BrushType::Custom => match props.tip {
+                Some(ref tip) => mk_custom_brush_image(r, tip, props.primary_color),
+                None => mk_round_brush_image(r, false, false, props.primary_color, props.secondary_color),
+            },
         }
     }
 
     pub fn modify(&mut self, primary_color: RGBA, secondary_color: RGBA, brush_type: BrushType, radius: u8) {
+        self.modify_with_tip(primary_color, secondary_color, brush_type, radius, None)
+    }
+
+    /// Like `modify`, but with the tip to use for `BrushType::Custom`
+    pub fn modify_with_tip(
+        &mut self,
+        primary_color: RGBA,
+        secondary_color: RGBA,
+        brush_type: BrushType,
+        radius: u8,
+        tip: Option<Rc<BrushTip>>,
+    ) {
         let new_props = BrushProperties {
             primary_color,
             secondary_color,
             brush_type,
             radius,
+            tip,
         };
 
         if self.props == new_props {
@@ -164,6 +370,7 @@ impl Brush {
             secondary_color,
             brush_type,
             radius,
+            tip: None,
         };
 
         Self::from_props(props)
@@ -186,6 +393,47 @@ impl Brush {
         }
     }
 
+    /// For round brushes, an anti-aliased image of the brush (with edge
+    /// pixels' alpha scaled by their coverage) scaled to `diameter` and
+    /// centered at the (real-number) pixel coordinates `center`, along
+    /// with the coordinates of the image's top-left pixel
+    pub fn antialiased_image(&self, diameter: f64, (cx, cy): (f64, f64)) -> Option<(BrushImage, i32, i32)> {
+        if self.props.brush_type != BrushType::Round {
+            return None;
+        }
+
+        let p = Pixel::from_rgba_struct(self.props.primary_color);
+        let r = (diameter / 2.0).max(0.5);
+        let x0 = (cx - r - 0.5).floor();
+        let y0 = (cy - r - 0.5).floor();
+        let n = (2.0 * r + 2.0).ceil() as usize + 1;
+
+        let pix = (0..n).map(|i| {
+            (0..n).map(|j| {
+                // distance from the center of pixel (i, j)
+                let dx = x0 + j as f64 + 0.5 - cx;
+                let dy = y0 + i as f64 + 0.5 - cy;
+                let coverage = (r + 0.5 - (dx * dx + dy * dy).sqrt()).clamp(0.0, 1.0);
+
+                Some(p.scale_alpha(coverage)).filter(|_| coverage > 0.0)
+            }).collect::<Vec<_>>()
+        }).collect::<Vec<_>>();
+
+        Some((BrushImage::from_pixels_options(pix), x0 as i32, y0 as i32))
+    }
+
+    /// A copy of the brush with a different radius
+    pub fn resized(&self, radius: u8) -> Self {
+        Self::from_props(BrushProperties {
+            radius: radius.max(1),
+            ..self.props.clone()
+        })
+    }
+
+    pub fn brush_type(&self) -> BrushType {
+        self.props.brush_type
+    }
+
     pub fn radius(&self) -> usize {
         self.props.radius as usize
     }
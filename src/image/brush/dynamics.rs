@@ -0,0 +1,178 @@
+use serde_derive::{Serialize, Deserialize};
+
+/// Per-stamp variation applied to a brush as it's dragged
+/// along a stroke. Fractional fields are in [0, 1] unless
+/// otherwise noted.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub struct BrushDynamics {
+    /// Distance between stamps, as a fraction of the radius
+    pub spacing: f64,
+    /// Max fraction of the radius to shrink each stamp by
+    pub size_jitter: f64,
+    /// Max fraction of the flow to reduce each stamp by
+    pub opacity_jitter: f64,
+    /// Max distance (in any direction) to move each stamp, as a fraction of the radius
+    pub position_jitter: f64,
+    /// Max distance (perpendicular to the stroke) to move each stamp,
+    /// as a fraction of the radius (may exceed 1)
+    pub scatter: f64,
+    /// Rotate the brush to match the stroke's direction
+    pub follow_angle: bool,
+    /// How much each stamp contributes (overlapping stamps build up)
+    pub flow: f64,
+    /// The most coverage a single stroke can reach
+    pub opacity: f64,
+    pub velocity: VelocityDynamics,
+}
+
+/// How the cursor's speed maps onto the range between the
+/// slow and fast values of `VelocityDynamics`
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub enum VelocityCurve {
+    Linear,
+    /// Changes slowly at low speeds
+    EaseIn,
+    /// Changes quickly at low speeds
+    EaseOut,
+}
+
+impl VelocityCurve {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Linear", VelocityCurve::Linear),
+            ("Ease In", VelocityCurve::EaseIn),
+            ("Ease Out", VelocityCurve::EaseOut),
+        ].iter().map(|x| x.clone())
+    }
+
+    fn apply(&self, t: f64) -> f64 {
+        match self {
+            VelocityCurve::Linear => t,
+            VelocityCurve::EaseIn => t * t,
+            VelocityCurve::EaseOut => t.sqrt(),
+        }
+    }
+}
+
+/// Variation of a stroke's size and opacity with the cursor's speed,
+/// and tapering at its ends. Speed is measured in pixels per input event
+/// (rather than per unit time), so replaying the same points always
+/// produces the same stroke.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub struct VelocityDynamics {
+    /// Fraction of the radius to use when the cursor is still
+    pub slow_size: f64,
+    /// Fraction of the radius to use at (or above) `full_speed`
+    pub fast_size: f64,
+    /// Fraction of the opacity to use when the cursor is still
+    pub slow_opacity: f64,
+    /// Fraction of the opacity to use at (or above) `full_speed`
+    pub fast_opacity: f64,
+    /// Speed (pixels per input event) at which the fast values are reached
+    pub full_speed: f64,
+    pub curve: VelocityCurve,
+    /// Length (in pixels) of the taper at the start of the stroke
+    pub fade_in: f64,
+    /// Length (in pixels) of the taper at the end of the stroke
+    pub fade_out: f64,
+}
+
+impl Default for VelocityDynamics {
+    fn default() -> Self {
+        VelocityDynamics {
+            slow_size: 1.0,
+            fast_size: 1.0,
+            slow_opacity: 1.0,
+            fast_opacity: 1.0,
+            full_speed: 40.0,
+            curve: VelocityCurve::Linear,
+            fade_in: 0.0,
+            fade_out: 0.0,
+        }
+    }
+}
+
+impl VelocityDynamics {
+    fn speed_fraction(&self, speed: f64) -> f64 {
+        self.curve.apply((speed / self.full_speed.max(1.0)).clamp(0.0, 1.0))
+    }
+
+    /// Factor to scale the radius by at the given speed
+    pub fn size_at(&self, speed: f64) -> f64 {
+        let t = self.speed_fraction(speed);
+        self.slow_size + (self.fast_size - self.slow_size) * t
+    }
+
+    /// Factor to scale the opacity by at the given speed
+    pub fn opacity_at(&self, speed: f64) -> f64 {
+        let t = self.speed_fraction(speed);
+        self.slow_opacity + (self.fast_opacity - self.slow_opacity) * t
+    }
+
+    /// Factor to scale the size and opacity by at a point `from_start`
+    /// pixels into the stroke (and `from_end` pixels from its end, if known)
+    pub fn taper(&self, from_start: f64, from_end: Option<f64>) -> f64 {
+        let fade = |dist: f64, len: f64| {
+            if len > 0.0 {
+                (dist / len).clamp(0.0, 1.0)
+            } else {
+                1.0
+            }
+        };
+
+        fade(from_start, self.fade_in) * from_end.map(|d| fade(d, self.fade_out)).unwrap_or(1.0)
+    }
+}
+
+impl Default for BrushDynamics {
+    fn default() -> Self {
+        BrushDynamics {
+            spacing: 0.1,
+            size_jitter: 0.0,
+            opacity_jitter: 0.0,
+            position_jitter: 0.0,
+            scatter: 0.0,
+            follow_angle: false,
+            flow: 1.0,
+            opacity: 1.0,
+            velocity: VelocityDynamics::default(),
+        }
+    }
+}
+
+impl BrushDynamics {
+    /// Distance (in pixels) between stamps of a brush with the given radius
+    pub fn sample_distance(&self, radius: usize) -> f64 {
+        (radius as f64).powf(1.05) * self.spacing.max(0.01)
+    }
+}
+
+/// Small, seedable pseudo-random number generator (xorshift64*),
+/// so that jittered strokes can be reproduced
+#[derive(Clone, Copy)]
+pub struct StrokeRng {
+    state: u64,
+}
+
+impl StrokeRng {
+    pub fn new(seed: u64) -> Self {
+        StrokeRng {
+            // the state must be nonzero
+            state: (seed ^ 0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    /// A value in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let x = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A value in [-1, 1)
+    pub fn next_signed(&mut self) -> f64 {
+        self.next_f64() * 2.0 - 1.0
+    }
+}
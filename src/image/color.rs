@@ -0,0 +1,54 @@
+use super::Pixel;
+
+/// `h` in [0, 360), `s` and `v` in [0, 1]
+pub fn hsv_to_rgb(h: f64, s: f64, v: f64) -> (f64, f64, f64) {
+    let c = v * s;
+    let h_prime = (h.rem_euclid(360.0)) / 60.0;
+    let x = c * (1.0 - (h_prime % 2.0 - 1.0).abs());
+    let (r, g, b) = match h_prime as u8 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+
+    (r + m, g + m, b + m)
+}
+
+/// Inverse of `hsv_to_rgb`; the hue (and saturation) are `None`
+/// when they're undefined (for grays (and black))
+pub fn rgb_to_hsv(r: f64, g: f64, b: f64) -> (Option<f64>, Option<f64>, f64) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let h = if delta == 0.0 {
+        None
+    } else if max == r {
+        Some((60.0 * ((g - b) / delta)).rem_euclid(360.0))
+    } else if max == g {
+        Some(60.0 * ((b - r) / delta) + 120.0)
+    } else {
+        Some(60.0 * ((r - g) / delta) + 240.0)
+    };
+
+    let s = if max == 0.0 {
+        None
+    } else {
+        Some(delta / max)
+    };
+
+    (h, s, max)
+}
+
+/// `#rrggbb`, or `#rrggbbaa` if the pixel isn't opaque
+pub fn pixel_to_hex(pix: &Pixel) -> String {
+    if pix.a == 255 {
+        format!("#{:02x}{:02x}{:02x}", pix.r, pix.g, pix.b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", pix.r, pix.g, pix.b, pix.a)
+    }
+}
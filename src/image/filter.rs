@@ -0,0 +1,208 @@
+use super::undo::action::{ActionName, AutoDiffAction};
+use super::{FusedLayeredImage, Pixel, TrackedLayeredImage};
+
+use std::collections::HashMap;
+
+/// Averages the given pixels, weighting the color
+/// channels by alpha (so fully-transparent pixels don't
+/// drag the average color towards black)
+pub fn average_pixels<'a>(pixels: impl Iterator<Item = &'a Pixel>) -> Pixel {
+    let mut n = 0.0;
+    let (mut r, mut g, mut b, mut a) = (0.0, 0.0, 0.0, 0.0);
+
+    for p in pixels {
+        let alpha = p.a as f64;
+        r += p.r as f64 * alpha;
+        g += p.g as f64 * alpha;
+        b += p.b as f64 * alpha;
+        a += alpha;
+        n += 1.0;
+    }
+
+    if a == 0.0 {
+        return Pixel::from_rgba(0, 0, 0, 0);
+    }
+
+    Pixel::from_rgba(
+        (r / a).round() as u8,
+        (g / a).round() as u8,
+        (b / a).round() as u8,
+        (a / n).round() as u8,
+    )
+}
+
+/// The average pixel of the `block_size` by `block_size` block at
+/// (`block_r`, `block_c`) in the grid of blocks (clipped to the image's bounds)
+pub fn block_average(
+    image: &impl TrackedLayeredImage,
+    block_size: usize,
+    (block_r, block_c): (usize, usize),
+) -> Pixel {
+    let r0 = block_r * block_size;
+    let c0 = block_c * block_size;
+    let r1 = (r0 + block_size).min(image.height() as usize);
+    let c1 = (c0 + block_size).min(image.width() as usize);
+
+    let pixels = (r0..r1)
+        .flat_map(|r| (c0..c1).map(move |c| (r, c)))
+        .map(|(r, c)| image.pix_at(r as i32, c as i32));
+
+    average_pixels(pixels)
+}
+
+/// The average of the 3x3 neighborhood around (`r`, `c`) as of
+/// the last commit (clipped to the image's bounds)
+pub fn neighborhood_average(image: &FusedLayeredImage, r: usize, c: usize) -> Pixel {
+    let r0 = r.saturating_sub(1);
+    let c0 = c.saturating_sub(1);
+    let r1 = (r + 2).min(image.height() as usize);
+    let c1 = (c + 2).min(image.width() as usize);
+
+    let pixels = (r0..r1)
+        .flat_map(|r| (c0..c1).map(move |c| (r, c)))
+        .map(|(r, c)| image.committed_pix_at(r, c));
+
+    average_pixels(pixels)
+}
+
+/// `p` with its difference from `blurred` (its neighborhood's average)
+/// exaggerated by `amount` (an unsharp mask); alpha is left unchanged
+pub fn sharpen_pixel(p: &Pixel, blurred: &Pixel, amount: f64) -> Pixel {
+    let sharpen = |x: u8, blurred: u8| {
+        (x as f64 + (x as f64 - blurred as f64) * amount).round().clamp(0.0, 255.0) as u8
+    };
+
+    Pixel::from_rgba(
+        sharpen(p.r, blurred.r),
+        sharpen(p.g, blurred.g),
+        sharpen(p.b, blurred.b),
+        p.a,
+    )
+}
+
+/// `p`, with its color shifted by the difference between `from` and `to`
+/// (e.g. to match a copied texture's average color to its new surroundings)
+pub fn shift_pixel(p: &Pixel, from: &Pixel, to: &Pixel) -> Pixel {
+    let shift = |x: u8, from: u8, to: u8| (x as i32 + to as i32 - from as i32).clamp(0, 255) as u8;
+
+    Pixel::from_rgba(
+        shift(p.r, from.r, to.r),
+        shift(p.g, from.g, to.g),
+        shift(p.b, from.b, to.b),
+        p.a,
+    )
+}
+
+/// Which tones the dodge and burn tools affect most
+#[derive(Clone, Copy, PartialEq)]
+pub enum ToneRange {
+    Shadows,
+    Midtones,
+    Highlights,
+}
+
+impl ToneRange {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Shadows", ToneRange::Shadows),
+            ("Midtones", ToneRange::Midtones),
+            ("Highlights", ToneRange::Highlights),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+/// `p`, lightened (or, if `burn`, darkened) within `range` by `exposure`
+/// (from 0 to 1); alpha is left unchanged
+pub fn dodge_burn_pixel(p: &Pixel, range: ToneRange, exposure: f64, burn: bool) -> Pixel {
+    let adjust = |x: u8| {
+        let v = x as f64 / 255.0;
+
+        let v = match (range, burn) {
+            (ToneRange::Shadows, false) => {
+                let factor = 1.0 - exposure / 3.0;
+                factor * v + 1.0 - factor
+            },
+            (ToneRange::Midtones, false) => v.powf(1.0 / (1.0 + exposure)),
+            (ToneRange::Highlights, false) => v * (1.0 + exposure / 3.0),
+            (ToneRange::Shadows, true) => {
+                let factor = exposure / 3.0;
+                ((v - factor) / (1.0 - factor)).max(0.0)
+            },
+            (ToneRange::Midtones, true) => v.powf(1.0 + exposure),
+            (ToneRange::Highlights, true) => v * (1.0 - exposure / 3.0),
+        };
+
+        (v * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+
+    Pixel::from_rgba(adjust(p.r), adjust(p.g), adjust(p.b), p.a)
+}
+
+/// Interpolates from `a` (at `t` = 0) to `b` (at `t` = 1), weighting
+/// the color channels by alpha (like `average_pixels`)
+pub fn mix_pixels(a: &Pixel, b: &Pixel, t: f64) -> Pixel {
+    let (wa, wb) = (a.a as f64 * (1.0 - t), b.a as f64 * t);
+    let alpha = wa + wb;
+
+    if alpha == 0.0 {
+        return Pixel::from_rgba(0, 0, 0, 0);
+    }
+
+    let mix = |x: u8, y: u8| ((x as f64 * wa + y as f64 * wb) / alpha).round() as u8;
+
+    Pixel::from_rgba(
+        mix(a.r, b.r),
+        mix(a.g, b.g),
+        mix(a.b, b.b),
+        alpha.round() as u8,
+    )
+}
+
+/// Replaces each of the given pixels with the average of the
+/// `block_size` by `block_size` block that contains it (blocks
+/// are aligned to the image's origin, so overlapping regions
+/// pixelate consistently)
+pub struct Pixelate<I>
+where
+    I: Iterator<Item = (usize, usize)>
+{
+    pix_iter: I,
+    block_size: usize,
+}
+
+impl<I> Pixelate<I>
+where
+    I: Iterator<Item = (usize, usize)>
+{
+    pub fn new(pix_iter: I, block_size: usize) -> Self {
+        Self {
+            pix_iter,
+            block_size: block_size.max(1),
+        }
+    }
+}
+
+impl<I> AutoDiffAction for Pixelate<I>
+where
+    I: Iterator<Item = (usize, usize)>
+{
+    fn exec(self, image: &mut impl TrackedLayeredImage) {
+        let block_size = self.block_size;
+        // a block's average is always computed before any of its
+        // pixels are overwritten, so caching them is safe
+        let mut block_averages = HashMap::new();
+
+        for (r, c) in self.pix_iter {
+            let block = (r / block_size, c / block_size);
+            let average = block_averages.entry(block)
+                .or_insert_with(|| block_average(&*image, block_size, block))
+                .clone();
+
+            *image.pix_at_mut(r as i32, c as i32) = average;
+        }
+    }
+
+    fn name(&self) -> ActionName {
+        ActionName::Pixelate
+    }
+}
@@ -0,0 +1,178 @@
+use super::undo::action::{ActionName, AutoDiffAction};
+use super::blend::composite_over;
+use super::quantize::BAYER_4X4;
+use super::{Pixel, TrackedLayeredImage};
+
+use std::f64::consts::TAU;
+
+/// How a gradient's colors are laid out around its start and end points
+#[derive(Clone, Copy, PartialEq)]
+pub enum GradientShape {
+    /// Bands perpendicular to the line from start to end
+    Linear,
+    /// Circles around the start (reaching the end's colors at the end)
+    Radial,
+    /// Sweeps once around the start, beginning in the end's direction
+    Conic,
+    /// Diamonds around the start, with a corner at the end
+    Diamond,
+    /// Linear, mirrored about the start
+    Reflected,
+}
+
+impl GradientShape {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Linear", GradientShape::Linear),
+            ("Radial", GradientShape::Radial),
+            ("Conic", GradientShape::Conic),
+            ("Diamond", GradientShape::Diamond),
+            ("Reflected", GradientShape::Reflected),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+/// A gradient between two points of the image
+#[derive(Clone)]
+pub struct Gradient {
+    shape: GradientShape,
+    /// (position from 0 to 1, color), sorted by position
+    stops: Vec<(f64, Pixel)>,
+    start: (f64, f64),
+    end: (f64, f64),
+    /// Whether to break up banding with ordered dithering
+    dither: bool,
+}
+
+impl Gradient {
+    pub fn new(
+        shape: GradientShape,
+        mut stops: Vec<(f64, Pixel)>,
+        start: (f64, f64),
+        end: (f64, f64),
+        dither: bool,
+    ) -> Self {
+        stops.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+
+        Gradient {
+            shape,
+            stops,
+            start,
+            end,
+            dither,
+        }
+    }
+
+    /// How far (from 0 to 1) the point (`x`, `y`) is through the gradient
+    fn t_at(&self, x: f64, y: f64) -> f64 {
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let (px, py) = (x - self.start.0, y - self.start.1);
+        let length_sq = dx * dx + dy * dy;
+
+        if length_sq == 0.0 {
+            return 0.0;
+        }
+
+        // coordinates along, and perpendicular to, the start-to-end line
+        // (in units of its length)
+        let along = (px * dx + py * dy) / length_sq;
+        let across = (px * dy - py * dx) / length_sq;
+
+        let t = match self.shape {
+            GradientShape::Linear => along,
+            GradientShape::Radial => (px * px + py * py).sqrt() / length_sq.sqrt(),
+            GradientShape::Conic => ((py.atan2(px) - dy.atan2(dx)) / TAU).rem_euclid(1.0),
+            GradientShape::Diamond => along.abs() + across.abs(),
+            GradientShape::Reflected => along.abs(),
+        };
+
+        t.clamp(0.0, 1.0)
+    }
+
+    /// The (unrounded, 0 to 255) channels of the gradient at `t`,
+    /// interpolated between the neighboring stops (weighting the
+    /// colors by alpha, so transparent stops don't darken their neighbors)
+    fn channels_at(&self, t: f64) -> [f64; 4] {
+        let channels = |p: &Pixel| [p.r as f64, p.g as f64, p.b as f64, p.a as f64];
+
+        let next = self.stops.iter().position(|(position, _)| *position > t);
+        let (a, b, s) = match next {
+            None => match self.stops.last() {
+                Some((_, last)) => (last, last, 0.0),
+                None => return [0.0; 4],
+            },
+            Some(0) => (&self.stops[0].1, &self.stops[0].1, 0.0),
+            Some(i) => {
+                let (t0, a) = &self.stops[i - 1];
+                let (t1, b) = &self.stops[i];
+                (a, b, (t - t0) / (t1 - t0))
+            },
+        };
+
+        let (a, b) = (channels(a), channels(b));
+        let (wa, wb) = (a[3] * (1.0 - s), b[3] * s);
+        let alpha = wa + wb;
+
+        let mix = |i: usize| if alpha == 0.0 {
+            a[i] * (1.0 - s) + b[i] * s
+        } else {
+            (a[i] * wa + b[i] * wb) / alpha
+        };
+
+        [mix(0), mix(1), mix(2), alpha]
+    }
+
+    /// The gradient's pixel at (`r`, `c`)
+    pub fn pixel_at(&self, r: usize, c: usize) -> Pixel {
+        let channels = self.channels_at(self.t_at(c as f64 + 0.5, r as f64 + 0.5));
+
+        // nudging each channel by the threshold map before rounding
+        // swaps the hard edges between bands for a fine pattern
+        let offset = if self.dither {
+            (BAYER_4X4[r % 4][c % 4] as f64 + 0.5) / 16.0 - 0.5
+        } else {
+            0.0
+        };
+        let quantize = |x: f64| (x + offset).round().clamp(0.0, 255.0) as u8;
+
+        Pixel::from_rgba(quantize(channels[0]), quantize(channels[1]), quantize(channels[2]), quantize(channels[3]))
+    }
+}
+
+/// Composites a gradient over each of the given pixels
+pub struct DrawGradient<I>
+where
+    I: Iterator<Item = (usize, usize)>
+{
+    pix_iter: I,
+    gradient: Gradient,
+}
+
+impl<I> DrawGradient<I>
+where
+    I: Iterator<Item = (usize, usize)>
+{
+    pub fn new(pix_iter: I, gradient: Gradient) -> Self {
+        Self {
+            pix_iter,
+            gradient,
+        }
+    }
+}
+
+impl<I> AutoDiffAction for DrawGradient<I>
+where
+    I: Iterator<Item = (usize, usize)>
+{
+    fn exec(self, image: &mut impl TrackedLayeredImage) {
+        for (r, c) in self.pix_iter {
+            let above = self.gradient.pixel_at(r, c);
+            let p = composite_over(&above, image.pix_at(r as i32, c as i32));
+            *image.pix_at_mut(r as i32, c as i32) = p;
+        }
+    }
+
+    fn name(&self) -> ActionName {
+        ActionName::Gradient
+    }
+}
@@ -74,6 +74,8 @@ impl Image {
 pub struct LayeredImage {
     base_layer: Layer,
     other_layers: Vec<Layer>,
+    #[serde(default)]
+    paths: Vec<VectorPath>,
 }
 
 impl LayeredImage {
@@ -107,7 +109,8 @@ impl FusedLayeredImage {
             base_layer: self.base_layer.unfused(),
             other_layers: self.other_layers.iter()
                 .map(|layer| layer.unfused())
-                .collect::<Vec<_>>()
+                .collect::<Vec<_>>(),
+            paths: self.paths.clone(),
         }
     }
 
@@ -121,6 +124,7 @@ impl FusedLayeredImage {
             active_layer_index: LayerIndex::BaseLayer,
             pix_modified_since_draw: HashMap::new(),
             pix_modified_since_save: HashMap::new(),
+            paths: layered_imge.paths,
         }
     }
 }
@@ -0,0 +1,406 @@
+use super::undo::action::{ActionName, AutoDiffAction};
+use super::{Pixel, TrackedLayeredImage};
+
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum QuantizeMethod {
+    MedianCut,
+    KMeans,
+}
+
+impl QuantizeMethod {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Median Cut", QuantizeMethod::MedianCut),
+            ("K-Means", QuantizeMethod::KMeans),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Dithering {
+    None,
+    FloydSteinberg,
+    Bayer,
+}
+
+impl Dithering {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("None", Dithering::None),
+            ("Floyd\u{2013}Steinberg", Dithering::FloydSteinberg),
+            ("Ordered (Bayer)", Dithering::Bayer),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+/// A color as (r, g, b, a) in [0, 255], alongside
+/// the number of pixels that share it
+type WeightedColor = ([f64; 4], f64);
+
+#[inline]
+fn to_channels(p: &Pixel) -> [f64; 4] {
+    [p.r as f64, p.g as f64, p.b as f64, p.a as f64]
+}
+
+#[inline]
+fn from_channels(c: &[f64; 4]) -> Pixel {
+    let ch = |x: f64| x.round().clamp(0.0, 255.0) as u8;
+    Pixel::from_rgba(ch(c[0]), ch(c[1]), ch(c[2]), ch(c[3]))
+}
+
+#[inline]
+fn dist_sq(a: &[f64; 4], b: &[f64; 4]) -> f64 {
+    a.iter().zip(b.iter())
+        .map(|(x, y)| (x - y).powi(2))
+        .sum()
+}
+
+fn nearest_index(palette: &[[f64; 4]], color: &[f64; 4]) -> usize {
+    palette.iter()
+        .enumerate()
+        .map(|(i, p)| (i, dist_sq(p, color)))
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(i, _d)| i)
+        .unwrap_or(0)
+}
+
+fn weighted_mean(colors: &[WeightedColor]) -> [f64; 4] {
+    let mut sum = [0.0; 4];
+    let mut total_weight = 0.0;
+
+    for (color, weight) in colors.iter() {
+        for ch in 0..4 {
+            sum[ch] += color[ch] * weight;
+        }
+        total_weight += weight;
+    }
+
+    sum.map(|x| x / total_weight.max(1.0))
+}
+
+/// Collects the distinct (non fully-transparent) colors, and their counts
+fn color_histogram<'a>(pixels: impl Iterator<Item = &'a Pixel>) -> Vec<WeightedColor> {
+    let mut counts: HashMap<(u8, u8, u8, u8), usize> = HashMap::new();
+
+    for p in pixels.filter(|p| p.a != 0) {
+        *counts.entry((p.r, p.g, p.b, p.a)).or_insert(0) += 1;
+    }
+
+    let mut colors = counts.into_iter()
+        .map(|((r, g, b, a), n)| ([r as f64, g as f64, b as f64, a as f64], n as f64))
+        .collect::<Vec<_>>();
+
+    // HashMap iteration order is random: sort to keep the results deterministic
+    colors.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    colors
+}
+
+/// Returns the channel with the largest range of values
+/// among `colors`, alongside that range
+fn widest_channel(colors: &[WeightedColor]) -> (usize, f64) {
+    (0..4).map(|ch| {
+        let min = colors.iter().map(|c| c.0[ch]).fold(f64::MAX, f64::min);
+        let max = colors.iter().map(|c| c.0[ch]).fold(f64::MIN, f64::max);
+        (ch, max - min)
+    })
+    .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    .unwrap()
+}
+
+fn median_cut(colors: Vec<WeightedColor>, num_colors: usize) -> Vec<[f64; 4]> {
+    let mut boxes = vec![colors];
+
+    while boxes.len() < num_colors {
+        // split the box spanning the widest range of a single channel
+        let widest = boxes.iter()
+            .enumerate()
+            .filter(|(_i, b)| b.len() > 1)
+            .map(|(i, b)| (i, widest_channel(b)))
+            .max_by(|a, b| (a.1).1.partial_cmp(&(b.1).1).unwrap());
+
+        let (i, (ch, _range)) = if let Some(widest) = widest {
+            widest
+        } else {
+            break; // every box holds a single color
+        };
+
+        let mut lower = boxes.swap_remove(i);
+        lower.sort_by(|a, b| a.0[ch].partial_cmp(&b.0[ch]).unwrap());
+
+        // split at the (pixel-count weighted) median
+        let half_weight = lower.iter().map(|c| c.1).sum::<f64>() / 2.0;
+        let mut acc = 0.0;
+        let split_idx = lower.iter()
+            .position(|c| {
+                acc += c.1;
+                acc >= half_weight
+            })
+            .map(|idx| idx + 1)
+            .unwrap_or(1)
+            .max(1)
+            .min(lower.len() - 1);
+
+        let upper = lower.split_off(split_idx);
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+
+    boxes.iter()
+        .filter(|b| !b.is_empty())
+        .map(|b| weighted_mean(b))
+        .collect::<Vec<_>>()
+}
+
+fn k_means(colors: Vec<WeightedColor>, num_colors: usize) -> Vec<[f64; 4]> {
+    const MAX_ITERATIONS: usize = 16;
+
+    // seeding with median cut (rather than randomly) keeps it deterministic
+    let mut centroids = median_cut(colors.clone(), num_colors);
+    let mut assignments = vec![usize::MAX; colors.len()];
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+
+        for (color, assignment) in colors.iter().zip(assignments.iter_mut()) {
+            let nearest = nearest_index(&centroids, &color.0);
+            if nearest != *assignment {
+                *assignment = nearest;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+
+        for (k, centroid) in centroids.iter_mut().enumerate() {
+            let members = colors.iter()
+                .zip(assignments.iter())
+                .filter(|(_c, a)| **a == k)
+                .map(|(c, _a)| *c)
+                .collect::<Vec<_>>();
+
+            // empty clusters keep their old centroid
+            if !members.is_empty() {
+                *centroid = weighted_mean(&members);
+            }
+        }
+    }
+
+    centroids
+}
+
+/// Generates a palette of (at most) `num_colors` colors from the
+/// histogram `colors`, alongside the pixel count nearest each color
+fn quantize_histogram(colors: Vec<WeightedColor>, num_colors: usize, method: QuantizeMethod) -> Vec<WeightedColor> {
+    if colors.is_empty() || num_colors == 0 {
+        return vec![];
+    }
+
+    let palette = match method {
+        QuantizeMethod::MedianCut => median_cut(colors.clone(), num_colors),
+        QuantizeMethod::KMeans => k_means(colors.clone(), num_colors),
+    };
+
+    let mut frequencies = vec![0.0; palette.len()];
+    for (color, weight) in colors.iter() {
+        frequencies[nearest_index(&palette, color)] += weight;
+    }
+
+    palette.into_iter()
+        .zip(frequencies)
+        .collect::<Vec<_>>()
+}
+
+/// Generates a palette of (at most) `num_colors` colors
+/// that represent the given pixels. Fully transparent
+/// pixels are ignored.
+pub fn quantize<'a>(pixels: impl Iterator<Item = &'a Pixel>, num_colors: usize, method: QuantizeMethod) -> Vec<Pixel> {
+    quantize_histogram(color_histogram(pixels), num_colors, method).iter()
+        .map(|(color, _frequency)| from_channels(color))
+        .collect::<Vec<_>>()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorOrder {
+    Frequency,
+    Hue,
+    Luminance,
+}
+
+impl ColorOrder {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Frequency", ColorOrder::Frequency),
+            ("Hue", ColorOrder::Hue),
+            ("Luminance", ColorOrder::Luminance),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+#[inline]
+fn luminance(c: &[f64; 4]) -> f64 {
+    0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2]
+}
+
+/// Hue in [0, 360), or `None` for grays
+fn hue(c: &[f64; 4]) -> Option<f64> {
+    let (r, g, b) = (c[0], c[1], c[2]);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    if delta < 1.0 {
+        return None;
+    }
+
+    let h = if max == r {
+        60.0 * ((g - b) / delta)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    Some(h.rem_euclid(360.0))
+}
+
+/// Like `quantize`, but the resulting colors are sorted by `order`
+/// (most frequent first, by hue with grays first, or darkest first)
+pub fn quantize_ordered<'a>(
+    pixels: impl Iterator<Item = &'a Pixel>,
+    num_colors: usize,
+    method: QuantizeMethod,
+    order: ColorOrder,
+) -> Vec<Pixel> {
+    let mut palette = quantize_histogram(color_histogram(pixels), num_colors, method);
+
+    match order {
+        ColorOrder::Frequency => palette.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap()),
+        ColorOrder::Hue => palette.sort_by(|a, b| {
+            let hue_a = hue(&a.0).unwrap_or(-1.0);
+            let hue_b = hue(&b.0).unwrap_or(-1.0);
+            hue_a.partial_cmp(&hue_b).unwrap()
+                .then(luminance(&a.0).partial_cmp(&luminance(&b.0)).unwrap())
+        }),
+        ColorOrder::Luminance => palette.sort_by(|a, b| luminance(&a.0).partial_cmp(&luminance(&b.0)).unwrap()),
+    }
+
+    palette.iter()
+        .map(|(color, _frequency)| from_channels(color))
+        .collect::<Vec<_>>()
+}
+
+/// Where `ReduceColors` gets its colors from
+#[derive(Clone)]
+pub enum ReducedPalette {
+    /// Quantize the image itself to N colors
+    Generated(QuantizeMethod, usize),
+    /// Map onto the given colors
+    Fixed(Vec<Pixel>),
+}
+
+pub const BAYER_4X4: [[u8; 4]; 4] = [
+    [ 0,  8,  2, 10],
+    [12,  4, 14,  6],
+    [ 3, 11,  1,  9],
+    [15,  7, 13,  5],
+];
+
+/// Maps every (non fully-transparent) pixel of the
+/// active layer onto a reduced palette
+pub struct ReduceColors {
+    palette: ReducedPalette,
+    dithering: Dithering,
+}
+
+impl ReduceColors {
+    pub fn new(palette: ReducedPalette, dithering: Dithering) -> Self {
+        ReduceColors {
+            palette,
+            dithering,
+        }
+    }
+}
+
+impl AutoDiffAction for ReduceColors {
+    fn name(&self) -> ActionName {
+        ActionName::ReduceColors
+    }
+
+    fn exec(self, image: &mut impl TrackedLayeredImage) {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+
+        let palette = match self.palette {
+            ReducedPalette::Generated(method, num_colors) => {
+                let image_ref = &*image;
+                let pixels = (0..height)
+                    .flat_map(|r| (0..width).map(move |c| (r, c)))
+                    .map(|(r, c)| image_ref.pix_at(r as i32, c as i32));
+                quantize(pixels, num_colors, method)
+            },
+            ReducedPalette::Fixed(colors) => colors,
+        };
+
+        if palette.is_empty() {
+            return;
+        }
+
+        let palette = palette.iter().map(to_channels).collect::<Vec<_>>();
+        // the expected distance between palette colors (per channel),
+        // used to scale the ordered dithering's threshold map
+        let spread = 255.0 / (palette.len() as f64).cbrt();
+        let mut errors = vec![[0.0; 4]; width * height];
+
+        for r in 0..height {
+            for c in 0..width {
+                let p = image.pix_at(r as i32, c as i32);
+                if p.a == 0 {
+                    continue;
+                }
+
+                let mut target = to_channels(p);
+
+                match self.dithering {
+                    Dithering::None => (),
+                    Dithering::FloydSteinberg => {
+                        let error = errors[r * width + c];
+                        for (x, e) in target[..3].iter_mut().zip(error) {
+                            *x = (*x + e).clamp(0.0, 255.0);
+                        }
+                    },
+                    Dithering::Bayer => {
+                        let offset = (BAYER_4X4[r % 4][c % 4] as f64 + 0.5) / 16.0 - 0.5;
+                        for x in target[..3].iter_mut() {
+                            *x = (*x + offset * spread).clamp(0.0, 255.0);
+                        }
+                    },
+                }
+
+                let nearest = palette[nearest_index(&palette, &target)];
+
+                if let Dithering::FloydSteinberg = self.dithering {
+                    let neighbors = [
+                        (r, c + 1, 7.0 / 16.0),
+                        (r + 1, c.wrapping_sub(1), 3.0 / 16.0),
+                        (r + 1, c, 5.0 / 16.0),
+                        (r + 1, c + 1, 1.0 / 16.0),
+                    ];
+
+                    for (rp, cp, factor) in neighbors {
+                        if rp < height && cp < width {
+                            for ch in 0..3 {
+                                errors[rp * width + cp][ch] += (target[ch] - nearest[ch]) * factor;
+                            }
+                        }
+                    }
+                }
+
+                *image.pix_at_mut(r as i32, c as i32) = from_channels(&nearest);
+            }
+        }
+    }
+}
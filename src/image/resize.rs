@@ -82,6 +82,10 @@ impl MultiLayerAction for Scale {
     fn undo(&mut self, layer_data: &mut Self::LayerData, image: &mut Image) {
         *image = layer_data.clone();
     }
+
+    fn map_path_point(&self, (x, y): (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        (x * self.w as f64 / width as f64, y * self.h as f64 / height as f64)
+    }
 }
 
 #[inline]
@@ -232,6 +236,36 @@ impl ExpandJustification {
         r_in_window && c_in_window
     }
 
+    /// Where the top-left corner of the old image lands in the new
+    /// one, as (x, y) (consistent with `take_idx`)
+    fn offset(&self, old_w: usize, old_h: usize, new_w: usize, new_h: usize) -> (usize, usize) {
+        let x = match self {
+            ExpandJustification::TopLeft |
+            ExpandJustification::MiddleLeft |
+            ExpandJustification::BottomLeft => 0,
+            ExpandJustification::TopCenter |
+            ExpandJustification::MiddleCenter |
+            ExpandJustification::BottomCenter => new_w / 2 - old_w / 2,
+            ExpandJustification::TopRight |
+            ExpandJustification::MiddleRight |
+            ExpandJustification::BottomRight => new_w - old_w,
+        };
+
+        let y = match self {
+            ExpandJustification::TopLeft |
+            ExpandJustification::TopCenter |
+            ExpandJustification::TopRight => 0,
+            ExpandJustification::MiddleLeft |
+            ExpandJustification::MiddleCenter |
+            ExpandJustification::MiddleRight => new_h / 2 - old_h / 2,
+            ExpandJustification::BottomLeft |
+            ExpandJustification::BottomCenter |
+            ExpandJustification::BottomRight => new_h - old_h,
+        };
+
+        (x, y)
+    }
+
     /// Given a height/width, plus amounts to truncate,
     /// determine the rectangle of the justified region
     pub fn bounding_box_in(
@@ -371,6 +405,11 @@ impl MultiLayerAction for Expand {
         image.width = old_w;
         image.pixels = old_pix;
     }
+
+    fn map_path_point(&self, (x, y): (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        let (dx, dy) = self.justification.offset(width, height, width + self.added_w, height + self.added_h);
+        (x + dx as f64, y + dy as f64)
+    }
 }
 
 pub struct CropUndoInfo {
@@ -478,4 +517,8 @@ impl MultiLayerAction for Crop {
         image.height = undo_info.old_h;
         image.pixels = old_pix;
     }
+
+    fn map_path_point(&self, (x, y): (f64, f64), _width: usize, _height: usize) -> (f64, f64) {
+        (x - self.x as f64, y - self.y as f64)
+    }
 }
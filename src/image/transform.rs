@@ -62,6 +62,14 @@ impl MultiLayerAction for Flip {
         // flips are their own inverse
         self.exec(&mut(), image);
     }
+
+    fn map_path_point(&self, (x, y): (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        match self {
+            Self::Vertical => (x, height as f64 - y),
+            Self::Horizontal => (width as f64 - x, y),
+            Self::Transpose => (y, x),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -113,4 +121,12 @@ impl MultiLayerAction for Rotate {
     fn undo(&mut self, _layer_data: &mut (), image: &mut Image) {
         self.invert().exec(&mut(), image)
     }
+
+    fn map_path_point(&self, (x, y): (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        match self {
+            Self::OneEighty => (width as f64 - x, height as f64 - y),
+            Self::Clockwise => (height as f64 - y, x),
+            Self::CounterClockwise => (y, width as f64 - x),
+        }
+    }
 }
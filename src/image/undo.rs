@@ -3,6 +3,7 @@ mod tree;
 
 use self::action::SingleLayerAction;
 use super::{FusedLayeredImage, Image, Layer, LayerIndex, Pixel};
+use super::vector_path::VectorPath;
 use tree::UndoTree;
 use action::{ActionName, MultiLayerActionWrapper};
 
@@ -169,6 +170,7 @@ enum ImageDiff {
     SwapLayers(LayerIndex, LayerIndex),
     MergeLayers(Layer, LayerIndex, Layer, LayerIndex), /// (save_top_layer, top_index, save_bottom_layer, bottom_index)
     MultiLayerManualUndo(MultiLayerActionWrapper),
+    Paths(Vec<VectorPath>, Vec<VectorPath>), /// (paths_before, paths_after)
     Null,
 }
 
@@ -225,6 +227,9 @@ impl ImageDiff {
                 action_struct.exec(image);
                 drawables_to_update.add_layers(image.layer_indices())
             }
+            ImageDiff::Paths(_before, after) => {
+                *image.paths_mut() = after.clone();
+            }
             ImageDiff::Null => (),
         }
     }
@@ -273,6 +278,9 @@ impl ImageDiff {
                 action_struct.undo(image);
                 drawables_to_update.add_layers(image.layer_indices())
             }
+            ImageDiff::Paths(before, _after) => {
+                *image.paths_mut() = before.clone();
+            }
             ImageDiff::Null => (),
         }
     }
@@ -501,4 +509,14 @@ impl ImageHistory {
 
         self.apply_and_push_diff(image_diff, ActionName::MergeLayers);
     }
+
+    /// Records the change from `paths_before` to the current paths
+    /// (which have already been edited in place)
+    pub fn push_paths_diff(&mut self, paths_before: Vec<VectorPath>, culprit: ActionName) {
+        self.commit_any_changes_on_active_layer();
+
+        let image_diff = ImageDiff::Paths(paths_before, self.now().paths().clone());
+
+        self.apply_and_push_diff(image_diff, culprit);
+    }
 }
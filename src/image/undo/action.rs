@@ -1,4 +1,5 @@
 use crate::image::{Image, ImageLikeUncheckedMut, LayerIndex, TrackedLayeredImage, FusedLayeredImage};
+use crate::image::vector_path::VectorPath;
 use super::{ImageDiff, ImageHistory, ImageStateDiff};
 
 use std::any::Any;
@@ -10,7 +11,20 @@ pub enum ActionName {
     Anonymous, // Caused by non-commited image writes, probably
                // due to an internal error, or gtk invariant issue
     Pencil,
+    Eraser,
+    Smudge,
+    Blur,
+    Sharpen,
+    CloneStamp,
+    Healing,
+    Dodge,
+    Burn,
+    Airbrush,
+    Line,
     Fill,
+    FillPath,
+    StrokePath,
+    Gradient,
     Delete,
     Rotate,
     Flip,
@@ -25,6 +39,11 @@ pub enum ActionName {
     Transform,
     InsertShape,
     InsertText,
+    Pixelate,
+    ReduceColors,
+    EditPath,
+    DeletePath,
+    SelectionToPath,
 }
 
 impl ActionName {
@@ -32,7 +51,20 @@ impl ActionName {
         match self {
             Self::Anonymous => "Anonyous",
             Self::Pencil => "Pencil",
+            Self::Eraser => "Eraser",
+            Self::Smudge => "Smudge",
+            Self::Blur => "Blur",
+            Self::Sharpen => "Sharpen",
+            Self::CloneStamp => "Clone Stamp",
+            Self::Healing => "Healing",
+            Self::Dodge => "Dodge",
+            Self::Burn => "Burn",
+            Self::Airbrush => "Airbrush",
+            Self::Line => "Line",
             Self::Fill => "Fill",
+            Self::FillPath => "Fill Path",
+            Self::StrokePath => "Stroke Path",
+            Self::Gradient => "Gradient",
             Self::Delete => "Delete",
             Self::Rotate => "Rotate",
             Self::Flip => "Flip",
@@ -47,6 +79,11 @@ impl ActionName {
             Self::Transform => "Transform",
             Self::InsertShape => "Insert Shape",
             Self::InsertText => "Insert Text",
+            Self::Pixelate => "Pixelate",
+            Self::ReduceColors => "Reduce Colors",
+            Self::EditPath => "Edit Path",
+            Self::DeletePath => "Delete Path",
+            Self::SelectionToPath => "Selection to Path",
         }
     }
 }
@@ -92,6 +129,12 @@ pub trait MultiLayerAction {
     fn name(&self) -> ActionName;
     fn exec(&mut self, layer_data: &mut Self::LayerData, image: &mut Image);
     fn undo(&mut self, layer_data: &mut Self::LayerData, image: &mut Image);
+
+    /// Where `point` (on a `width` by `height` image) ends up after
+    /// `exec`, so the image's paths can follow along
+    fn map_path_point(&self, point: (f64, f64), _width: usize, _height: usize) -> (f64, f64) {
+        point
+    }
 }
 
 pub trait StaticMultiUndoableAction<D>: MultiLayerAction + {
@@ -114,6 +157,7 @@ trait MultiLayerActionWrapperTrait {
     fn name(&self) -> ActionName;
     fn exec(&mut self, layer_data: &mut Box<dyn Any>, image: &mut Image);
     fn undo(&mut self, layer_data: &mut Box<dyn Any>, image: &mut Image);
+    fn map_path_point(&self, point: (f64, f64), width: usize, height: usize) -> (f64, f64);
 }
 
 impl<D: 'static> MultiLayerActionWrapperTrait for Box<dyn MultiLayerAction<LayerData = D>> {
@@ -132,13 +176,19 @@ impl<D: 'static> MultiLayerActionWrapperTrait for Box<dyn MultiLayerAction<Layer
     fn undo(&mut self, layer_data: &mut Box<dyn Any>, image: &mut Image) {
         MultiLayerAction::undo(self.as_mut(), layer_data.as_mut().downcast_mut().unwrap(), image)
     }
+
+    fn map_path_point(&self, point: (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        MultiLayerAction::map_path_point(self.as_ref(), point, width, height)
+    }
 }
 
 /// Wrapper struct for handling the vector of
-/// `layer_data`s
+/// `layer_data`s (and the image's paths, which are
+/// moved along with the layers)
 pub struct MultiLayerActionWrapper {
     action: Box<dyn MultiLayerActionWrapperTrait>,
     layer_datas: Option<Vec<Box<dyn Any>>>,
+    paths_before: Option<Vec<VectorPath>>,
 }
 
 impl MultiLayerActionWrapper {
@@ -146,6 +196,7 @@ impl MultiLayerActionWrapper {
         Self {
             action: Box::new(action),
             layer_datas: None,
+            paths_before: None,
         }
     }
 
@@ -164,6 +215,13 @@ impl MultiLayerActionWrapper {
 
     pub fn exec(&mut self, layered_image: &mut FusedLayeredImage) {
         self.init_layer_datas(layered_image);
+
+        let (width, height) = (layered_image.width() as usize, layered_image.height() as usize);
+        self.paths_before = Some(layered_image.paths().clone());
+        for path in layered_image.paths_mut().iter_mut() {
+            path.map_points(|point| self.action.map_path_point(point, width, height));
+        }
+
         let layer_datas = self.layer_datas.as_mut().unwrap();
 
         for (i, layer_data) in layer_datas.iter_mut().enumerate() {
@@ -181,6 +239,10 @@ impl MultiLayerActionWrapper {
             self.action.undo(layer_data, layered_image.image_at_layer_index_mut(LayerIndex::from_usize(i)));
         }
 
+        if let Some(paths) = self.paths_before.take() {
+            *layered_image.paths_mut() = paths;
+        }
+
         layered_image.update_drawable_sizes();
     }
 }
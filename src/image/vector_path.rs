@@ -0,0 +1,265 @@
+use super::bitmask::ImageBitmask;
+
+use gtk::cairo;
+use serde_derive::{Serialize, Deserialize};
+
+/// Number of straight pieces each curve is flattened into
+const CURVE_PIECES: usize = 48;
+
+pub fn lerp((x0, y0): (f64, f64), (x1, y1): (f64, f64), t: f64) -> (f64, f64) {
+    (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t)
+}
+
+pub fn distance((x0, y0): (f64, f64), (x1, y1): (f64, f64)) -> f64 {
+    ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt()
+}
+
+/// The point at `t` along the cubic Bezier curve from `p0` to `p3`
+pub fn bezier_point(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), p3: (f64, f64), t: f64) -> (f64, f64) {
+    let s = 1.0 - t;
+    let (a, b, c, d) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+
+    (
+        a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+        a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+    )
+}
+
+/// A point on a path, with the control points of the
+/// curves arriving at and leaving it (a handle that sits on
+/// the point itself leaves that side of the anchor straight)
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Anchor {
+    pub point: (f64, f64),
+    pub handle_in: (f64, f64),
+    pub handle_out: (f64, f64),
+}
+
+impl Anchor {
+    /// A corner anchor (with no handles pulled out)
+    pub fn new(point: (f64, f64)) -> Self {
+        Anchor {
+            point,
+            handle_in: point,
+            handle_out: point,
+        }
+    }
+
+    /// Pulls `handle_out` to `handle`, and `handle_in` to its mirror image
+    pub fn set_symmetric_handles(&mut self, handle: (f64, f64)) {
+        self.handle_out = handle;
+        self.handle_in = (2.0 * self.point.0 - handle.0, 2.0 * self.point.1 - handle.1);
+    }
+
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        for p in [&mut self.point, &mut self.handle_in, &mut self.handle_out] {
+            *p = (p.0 + dx, p.1 + dy);
+        }
+    }
+
+    /// Moves the anchor and both of its handles through `f`
+    pub fn map_points(&mut self, f: &impl Fn((f64, f64)) -> (f64, f64)) {
+        for p in [&mut self.point, &mut self.handle_in, &mut self.handle_out] {
+            *p = f(*p);
+        }
+    }
+}
+
+/// A connected run of cubic Bezier segments
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subpath {
+    pub anchors: Vec<Anchor>,
+    /// Whether there's a segment from the last anchor back to the first
+    pub closed: bool,
+}
+
+impl Subpath {
+    pub fn new(first: Anchor) -> Self {
+        Subpath {
+            anchors: vec![first],
+            closed: false,
+        }
+    }
+
+    /// The control points (start, handle, handle, end) of each segment
+    pub fn segments(&self) -> impl Iterator<Item = [(f64, f64); 4]> + '_ {
+        let n = self.anchors.len();
+        let n_segments = if self.closed && n > 1 { n } else { n.saturating_sub(1) };
+
+        (0..n_segments).map(move |i| {
+            let (a, b) = (&self.anchors[i], &self.anchors[(i + 1) % n]);
+            [a.point, a.handle_out, b.handle_in, b.point]
+        })
+    }
+
+    /// The subpath as a polyline (curves are split into `CURVE_PIECES` pieces)
+    fn flattened(&self) -> Vec<(f64, f64)> {
+        let mut points = self.anchors.first()
+            .map(|a| vec![a.point])
+            .unwrap_or_default();
+
+        for [p0, h0, h1, p1] in self.segments() {
+            if h0 == p0 && h1 == p1 {
+                points.push(p1);
+            } else {
+                points.extend((1..=CURVE_PIECES).map(|k| bezier_point(p0, h0, h1, p1, k as f64 / CURVE_PIECES as f64)));
+            }
+        }
+
+        points
+    }
+}
+
+/// A vector path (made of any number of subpaths) that's kept
+/// alongside the image, and can be filled, stroked, or turned
+/// into (and made from) a selection
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorPath {
+    pub subpaths: Vec<Subpath>,
+}
+
+impl VectorPath {
+    pub fn is_empty(&self) -> bool {
+        self.subpaths.iter().all(|subpath| subpath.anchors.is_empty())
+    }
+
+    /// Moves every anchor and handle through `f` (used to carry
+    /// the path along when the image is flipped, scaled, cropped, etc.)
+    pub fn map_points(&mut self, f: impl Fn((f64, f64)) -> (f64, f64)) {
+        for anchor in self.subpaths.iter_mut().flat_map(|subpath| subpath.anchors.iter_mut()) {
+            anchor.map_points(&f);
+        }
+    }
+
+    /// A path around the given rectangle
+    pub fn from_rectangle(x: usize, y: usize, w: usize, h: usize) -> Self {
+        let (x0, y0, x1, y1) = (x as f64, y as f64, (x + w) as f64, (y + h) as f64);
+
+        VectorPath {
+            subpaths: vec![Subpath {
+                anchors: [(x0, y0), (x1, y0), (x1, y1), (x0, y1)].into_iter().map(Anchor::new).collect(),
+                closed: true,
+            }],
+        }
+    }
+
+    /// A path along the edges between set and unset bits (with
+    /// a subpath for each outline, including those of holes)
+    pub fn from_bitmask(bitmask: &ImageBitmask) -> Self {
+        let subpaths = bitmask.edge_loops().into_iter()
+            .map(|mut points| {
+                if points.len() > 1 && points.first() == points.last() {
+                    points.pop();
+                }
+
+                // only keep the corners
+                let n = points.len();
+                let corners = (0..n).filter(|&i| {
+                    let (prev, p, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+                    !((prev.0 == p.0 && p.0 == next.0) || (prev.1 == p.1 && p.1 == next.1))
+                });
+
+                Subpath {
+                    anchors: corners.map(|i| Anchor::new((points[i].0 as f64, points[i].1 as f64))).collect(),
+                    closed: true,
+                }
+            })
+            .filter(|subpath| !subpath.anchors.is_empty())
+            .collect();
+
+        VectorPath {
+            subpaths,
+        }
+    }
+
+    /// Appends the path to `cr`'s current path
+    pub fn append_to(&self, cr: &cairo::Context) {
+        for subpath in self.subpaths.iter() {
+            let Some(first) = subpath.anchors.first() else {
+                continue;
+            };
+
+            cr.move_to(first.point.0, first.point.1);
+            for [_, h0, h1, p1] in subpath.segments() {
+                cr.curve_to(h0.0, h0.1, h1.0, h1.1, p1.0, p1.1);
+            }
+
+            if subpath.closed {
+                cr.close_path();
+            }
+        }
+    }
+
+    /// Points spaced `spacing` apart along each subpath
+    /// (always including their ends)
+    pub fn points_along(&self, spacing: f64) -> Vec<(f64, f64)> {
+        let mut points = vec![];
+
+        for polyline in self.subpaths.iter().map(|subpath| subpath.flattened()) {
+            let Some(&first) = polyline.first() else {
+                continue;
+            };
+
+            points.push(first);
+            let mut last = first;
+            let mut dist_till_next = spacing;
+
+            for &next in polyline[1..].iter() {
+                let mut piece_start = last;
+                let mut piece_length = distance(piece_start, next);
+
+                while piece_length >= dist_till_next {
+                    piece_start = lerp(piece_start, next, dist_till_next / piece_length);
+                    points.push(piece_start);
+                    piece_length -= dist_till_next;
+                    dist_till_next = spacing;
+                }
+
+                dist_till_next -= piece_length;
+                last = next;
+            }
+
+            if points.last() != Some(&last) {
+                points.push(last);
+            }
+        }
+
+        points
+    }
+
+    /// The pixels (of a `width` by `height` image) whose centers lie
+    /// inside the path, by the even-odd rule (open subpaths are
+    /// treated as if they were closed)
+    pub fn to_bitmask(&self, width: usize, height: usize) -> ImageBitmask {
+        let mut bits = vec![false; width * height];
+
+        let edges = self.subpaths.iter()
+            .map(|subpath| subpath.flattened())
+            .filter(|polyline| polyline.len() > 2)
+            .flat_map(|polyline| {
+                let n = polyline.len();
+                (0..n).map(move |i| (polyline[i], polyline[(i + 1) % n]))
+            })
+            .collect::<Vec<_>>();
+
+        for r in 0..height {
+            let y = r as f64 + 0.5;
+
+            let mut crossings = edges.iter()
+                .filter(|((_, y0), (_, y1))| (*y0 > y) != (*y1 > y))
+                .map(|((x0, y0), (x1, y1))| x0 + (y - y0) * (x1 - x0) / (y1 - y0))
+                .collect::<Vec<_>>();
+            crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            for span in crossings.chunks_exact(2) {
+                // the columns whose centers lie within the span
+                let c0 = (span[0] - 0.5).ceil().clamp(0.0, width as f64) as usize;
+                let c1 = (span[1] - 0.5).ceil().clamp(0.0, width as f64) as usize;
+
+                bits[r * width + c0..r * width + c1].fill(true);
+            }
+        }
+
+        ImageBitmask::from_flat_bits(height, width, bits)
+    }
+}
@@ -5,6 +5,7 @@ mod shape;
 mod geometry;
 mod clipboard;
 mod cli;
+mod config;
 
 use clap::Parser;
 
@@ -12,9 +12,10 @@ mod icon;
 
 use canvas::Canvas;
 use toolbar::Toolbar;
-use dialog::{about_dialog, cancel_discard_dialog_str, close_dialog, expand_dialog, no_button_dialog, ok_dialog_str_, scale_dialog, truncate_dialog, CloseDialog};
-use crate::image::{generate::{generate, NewImageProps}, Image, FusedLayeredImage, io::LayeredImage};
+use dialog::{about_dialog, cancel_discard_dialog_str, close_dialog, expand_dialog, no_button_dialog, ok_dialog_str_, pixelate_dialog, reduce_colors_dialog, scale_dialog, truncate_dialog, CloseDialog};
+use crate::image::{generate::{generate, NewImageProps}, Image, FusedLayeredImage, Pixel, io::LayeredImage};
 use crate::image::resize::Crop;
+use crate::image::quantize::{ReduceColors, ReducedPalette};
 use tab::{Tab, Tabbar};
 use toolbar::mode::{MouseMode, RectangleSelectMode};
 use crate::clipboard::Clipboard;
@@ -26,16 +27,6 @@ use std::cell::RefCell;
 use glib_macros::clone;
 use gtk::glib::signal::Propagation;
 
-fn get_parent_window(widget: &impl IsA<gtk::Widget>) -> Option<gtk::Window> {
-    let parent = widget.parent()?;
-
-    if let Ok(window) = parent.clone().downcast::<gtk::Window>() {
-        Some(window)
-    } else {
-        get_parent_window(&parent)
-    }
-}
-
 pub struct UiState {
     tabbar: Tabbar,
     toolbar_p: Rc<RefCell<Toolbar>>,
@@ -596,6 +587,122 @@ impl UiState {
         }
     }
 
+    fn pixelate_selection(ui_p: Rc<RefCell<Self>>) {
+        if let Some(canvas_p) = ui_p.borrow().active_canvas_p() {
+            if let selection::Selection::NoSelection = canvas_p.borrow().selection() {
+                ok_dialog_str_(
+                    ui_p.borrow().window(),
+                    "Make a Selection First",
+                    "Use a selection tool to select a region to pixelate."
+                );
+                return;
+            }
+
+            pixelate_dialog(&ui_p.borrow().window, clone!(@strong ui_p => move |block_size| {
+                if let Some(canvas_p) = ui_p.borrow().active_canvas_p() {
+                    canvas_p.borrow_mut().pixelate_selection(block_size);
+                }
+            }));
+        }
+    }
+
+    fn reduce_colors(ui_p: Rc<RefCell<Self>>) {
+        if let Some(_canvas_p) = ui_p.borrow().active_canvas_p() {
+            reduce_colors_dialog(&ui_p.borrow().window, clone!(@strong ui_p => move |method, num_colors, dithering| {
+                let palette = if let Some(method) = method {
+                    ReducedPalette::Generated(method, num_colors)
+                } else {
+                    let colors = ui_p.borrow().toolbar_p.borrow().palette_colors()
+                        .into_iter()
+                        .map(|rgba| Pixel::from_rgba_struct(rgba))
+                        .collect::<Vec<_>>();
+                    ReducedPalette::Fixed(colors)
+                };
+
+                if let Some(canvas_p) = ui_p.borrow().active_canvas_p() {
+                    canvas_p.borrow_mut().reduce_colors(ReduceColors::new(palette, dithering));
+                }
+            }));
+        }
+    }
+
+    /// The active canvas, if it has an active path
+    /// (otherwise, tells the user to make one)
+    fn active_canvas_with_path_p(ui_p: &Rc<RefCell<Self>>) -> Option<Rc<RefCell<Canvas>>> {
+        let canvas_p = ui_p.borrow().active_canvas_p()?.clone();
+
+        if canvas_p.borrow().active_path().is_none() {
+            ok_dialog_str_(
+                ui_p.borrow().window(),
+                "Make a Path First",
+                "Use the pen tool to draw a path (or click on one of its anchors)."
+            );
+            return None;
+        }
+
+        Some(canvas_p)
+    }
+
+    fn fill_path(ui_p: Rc<RefCell<Self>>) {
+        if let Some(canvas_p) = Self::active_canvas_with_path_p(&ui_p) {
+            let color = ui_p.borrow().toolbar_p.borrow().primary_color();
+            canvas_p.borrow_mut().fill_active_path(color);
+        }
+    }
+
+    fn stroke_path(ui_p: Rc<RefCell<Self>>) {
+        if let Some(canvas_p) = Self::active_canvas_with_path_p(&ui_p) {
+            let ui = ui_p.borrow();
+            canvas_p.borrow_mut().stroke_active_path(&ui.toolbar_p.borrow());
+        }
+    }
+
+    fn path_to_selection(ui_p: Rc<RefCell<Self>>) {
+        if let Some(canvas_p) = Self::active_canvas_with_path_p(&ui_p) {
+            canvas_p.borrow_mut().select_active_path();
+        }
+    }
+
+    fn selection_to_path(ui_p: Rc<RefCell<Self>>) {
+        if let Some(canvas_p) = ui_p.borrow().active_canvas_p() {
+            if let selection::Selection::NoSelection = canvas_p.borrow().selection() {
+                ok_dialog_str_(
+                    ui_p.borrow().window(),
+                    "Make a Selection First",
+                    "Use a selection tool to select the region to turn into a path."
+                );
+                return;
+            }
+
+            canvas_p.borrow_mut().selection_to_path();
+        }
+    }
+
+    fn delete_path(ui_p: Rc<RefCell<Self>>) {
+        if let Some(canvas_p) = Self::active_canvas_with_path_p(&ui_p) {
+            canvas_p.borrow_mut().delete_active_path();
+        }
+    }
+
+    /// Use the selected part of the active layer (cropped to the
+    /// selection's bounds) as the fill tool's pattern
+    fn fill_pattern_from_selection(ui_p: Rc<RefCell<Self>>) {
+        let Some(canvas_p) = ui_p.borrow().active_canvas_p().cloned() else {
+            return;
+        };
+
+        let Some(image) = canvas_p.borrow().selected_image() else {
+            ok_dialog_str_(
+                ui_p.borrow().window(),
+                "Make a Selection First",
+                "Use a selection tool to select the region to use as a pattern."
+            );
+            return;
+        };
+
+        ui_p.borrow().toolbar_p.borrow_mut().set_fill_pattern(image);
+    }
+
     fn paste(ui_p: Rc<RefCell<Self>>) {
         let image_to_paste = if let Some(image_to_paste) = ui_p.borrow_mut().clipboard.get_image() {
             image_to_paste
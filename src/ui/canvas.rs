@@ -5,7 +5,11 @@ use crate::geometry::{matrix_width_height, xywh_to_matrix, xywh_to_matrix_f};
 
 use super::super::image::{Image, FusedLayeredImage, TrackedLayeredImage, DrawableImage, mk_transparent_checkerboard};
 use super::super::image::bitmask::DeletePix;
+use super::super::image::filter::{average_pixels, block_average, dodge_burn_pixel, mix_pixels, neighborhood_average, shift_pixel, sharpen_pixel, Pixelate, ToneRange};
+use super::super::image::gradient::{DrawGradient, Gradient};
+use super::super::image::quantize::ReduceColors;
 use super::super::image::undo::{ImageHistory, action::ActionName};
+use super::super::image::vector_path::VectorPath;
 use super::super::image::resize::Crop;
 use super::selection::Selection;
 use super::tab::Tab;
@@ -23,9 +27,13 @@ use gtk::cairo;
 use gtk::glib::signal::Propagation;
 use std::rc::Rc;
 use std::cell::RefCell;
-use gtk::glib::SignalHandlerId;
+use gtk::glib::{SignalHandlerId, SourceId};
 use glib_macros::clone;
 use std::collections::HashMap;
+use std::time::Duration;
+
+/// How often a held drag "ticks" (for modes that act while the mouse is held still)
+const TICK_INTERVAL_MS: u64 = 30;
 
 pub struct Canvas {
     image_hist: ImageHistory,
@@ -41,6 +49,8 @@ pub struct Canvas {
     v_scrollbar: gtk::Scrollbar,
     h_scrollbar: gtk::Scrollbar,
     scrollbar_update_handlers: Option<(SignalHandlerId, SignalHandlerId)>,
+    /// The timer calling the mouse mode's `handle_tick` during the current drag
+    tick_source: Option<SourceId>,
     single_shot_draw_hooks: Vec<Box<dyn Fn(&cairo::Context)>>,
     draw_hook: Option<Box<dyn Fn(&cairo::Context)>>,
     transparent_checkerboard: Rc<RefCell<DrawableImage>>,
@@ -55,12 +65,55 @@ pub struct Canvas {
     /// during the current pencil stroke
     pencil_mask: Vec<usize>,
     pencil_mask_counter: usize,
+    /// Block averages sampled by the pixelating brush during the current
+    /// pencil stroke (keyed by (block size, block row, block col)), so blocks
+    /// stay uniform as the stroke overwrites them
+    pixelate_block_averages: HashMap<(usize, usize, usize), Pixel>,
+    /// The pixels drawn on during the pencil strokes (corresponding to
+    /// the flattened image pixel vector, like `pencil_mask`), so overlapping
+    /// stamps can build up to the stroke's opacity. Like `pencil_mask`,
+    /// only samples tagged with `pencil_mask_counter` are from the current
+    /// stroke, so the vector doesn't have to be cleared between strokes.
+    stroke_samples: Vec<Option<StrokeSample>>,
+    /// The color picked up by the smudge brush during the current stroke
+    /// (one per pixel of the brush; `None` where nothing's been picked up)
+    smudge_buffer: Vec<Option<Pixel>>,
+    /// The point (in pixel coords) the clone stamp copies from,
+    /// set by Ctrl-clicking
+    clone_source: Option<(i32, i32)>,
+    /// The offset from the cursor to the point being copied from
+    /// (`None` until a stroke begins from `clone_source`)
+    clone_offset: Option<(i32, i32)>,
+    /// Snapshot of what the clone stamp copies from during the current
+    /// stroke (so the stroke doesn't copy its own pixels)
+    clone_source_image: Option<Image>,
+    /// The point (in pixel coords) that symmetric strokes are mirrored
+    /// around (`None` for the center of the image)
+    symmetry_center: Option<(f64, f64)>,
+    /// The index of the path that the pen tool is editing
+    /// (and that the path actions apply to)
+    active_path: Option<usize>,
+    /// The paths as they were before the edits that haven't been
+    /// recorded in the undo history yet (see `commit_path_changes`)
+    paths_before_edit: Option<Vec<VectorPath>>,
     layer_window_p: Rc<RefCell<LayerWindow>>,
     lock_dialog_open: Rc<RefCell<bool>>,
     tab_thumbnail_p: Option<Rc<RefCell<gtk::DrawingArea>>>,
     transformation_selection: RefCell<Option<TransformationSelection>>,
 }
 
+/// A pixel drawn on during the current pencil stroke
+struct StrokeSample {
+    /// The `pencil_mask_counter` of the stroke that drew the pixel
+    stroke: usize,
+    /// The pixel before the stroke began
+    original: Pixel,
+    /// The color the stroke is drawing with (alpha is ignored)
+    color: Pixel,
+    /// The stroke's accumulated alpha, in [0, 1]
+    alpha: f64,
+}
+
 macro_rules! run_lockable_mouse_mode_hook {
     ($ui_p:expr, $canvas_p:expr, $controller:expr, $hook_name:ident) => {
         let ui = $ui_p.borrow();
@@ -132,6 +185,7 @@ impl Canvas {
             v_scrollbar,
             h_scrollbar,
             scrollbar_update_handlers: None,
+            tick_source: None,
             single_shot_draw_hooks: vec![],
             draw_hook: None,
             transparent_checkerboard: Rc::new(RefCell::new(mk_transparent_checkerboard())),
@@ -139,6 +193,15 @@ impl Canvas {
             history_id_to_cursor_pos_pix: HashMap::new(),
             pencil_mask: vec![0; image_net_size],
             pencil_mask_counter: 1,
+            pixelate_block_averages: HashMap::new(),
+            stroke_samples: vec![],
+            smudge_buffer: vec![],
+            clone_source: None,
+            clone_offset: None,
+            clone_source_image: None,
+            symmetry_center: None,
+            active_path: None,
+            paths_before_edit: None,
             layer_window_p: Rc::new(RefCell::new(LayerWindow::new())),
             lock_dialog_open: Rc::new(RefCell::new(false)),
             tab_thumbnail_p: None,
@@ -246,6 +309,7 @@ impl Canvas {
             .button(1) // left click
             .build();
         left_drag_controller.connect_begin(clone!(@strong ui_p, @strong canvas_p => move |dc, _| {
+            Self::start_ticking(&canvas_p, &ui_p, dc);
             run_lockable_mouse_mode_hook!(ui_p, canvas_p, dc, handle_drag_start);
         }));
 
@@ -254,6 +318,7 @@ impl Canvas {
         }));
 
         left_drag_controller.connect_drag_end(clone!(@strong ui_p, @strong canvas_p => move |dc, _, _| {
+            canvas_p.borrow_mut().stop_ticking();
             run_lockable_mouse_mode_hook!(ui_p, canvas_p, dc, handle_drag_end);
         }));
 
@@ -265,6 +330,7 @@ impl Canvas {
             .button(3) // right click
             .build();
         right_drag_controller.connect_begin(clone!(@strong ui_p, @strong canvas_p => move |dc, _| {
+            Self::start_ticking(&canvas_p, &ui_p, dc);
             run_lockable_mouse_mode_hook!(ui_p, canvas_p, dc, handle_right_drag_start);
         }));
 
@@ -273,6 +339,7 @@ impl Canvas {
         }));
 
         right_drag_controller.connect_drag_end(clone!(@strong ui_p, @strong canvas_p => move |dc, _, _| {
+            canvas_p.borrow_mut().stop_ticking();
             run_lockable_mouse_mode_hook!(ui_p, canvas_p, dc, handle_right_drag_end);
         }));
 
@@ -307,6 +374,33 @@ impl Canvas {
         })));
     }
 
+    /// If the mouse mode wants it, starts calling its `handle_tick`
+    /// every `TICK_INTERVAL_MS` (until `stop_ticking` is called)
+    fn start_ticking(canvas_p: &Rc<RefCell<Self>>, ui_p: &Rc<RefCell<UiState>>, controller: &gtk::GestureDrag) {
+        canvas_p.borrow_mut().stop_ticking();
+
+        let tick_while_dragging = ui_p.borrow().toolbar_p.borrow().mouse_mode().tick_while_dragging();
+        if !tick_while_dragging || canvas_p.borrow().active_layer_locked() {
+            return;
+        }
+
+        let source_id = gtk::glib::timeout_add_local(
+            Duration::from_millis(TICK_INTERVAL_MS),
+            clone!(@strong ui_p, @strong canvas_p, @strong controller => move || {
+                run_non_lockable_mouse_mode_hook!(ui_p, canvas_p, controller, handle_tick);
+                gtk::glib::ControlFlow::Continue
+            }),
+        );
+
+        canvas_p.borrow_mut().tick_source = Some(source_id);
+    }
+
+    fn stop_ticking(&mut self) {
+        if let Some(source_id) = self.tick_source.take() {
+            source_id.remove();
+        }
+    }
+
     pub fn widget(&self) -> &gtk::Grid {
         &self.grid
     }
@@ -377,6 +471,21 @@ impl Canvas {
         (x.floor() as i32, y.floor() as i32)
     }
 
+    pub fn symmetry_center(&self) -> (f64, f64) {
+        self.symmetry_center.unwrap_or((
+            self.image_width() as f64 / 2.0,
+            self.image_height() as f64 / 2.0,
+        ))
+    }
+
+    /// Moves the symmetry center to the cursor (snapped
+    /// to the nearest pixel corner, edge or center)
+    pub fn move_symmetry_center_to_cursor(&mut self) {
+        let (x, y) = self.cursor_pos_pix_f();
+        let snap = |v: f64| (v * 2.0).round() / 2.0;
+        self.symmetry_center = Some((snap(x), snap(y)));
+    }
+
     pub fn inc_zoom(&mut self, inc: f64) {
         const MAX_ZOOM: f64 = 500.0;
         const MIN_ZOOM: f64 = 0.1;
@@ -608,10 +717,19 @@ impl Canvas {
         self.infobar.update(self)
     }
 
+    /// Displays the color (picked by the eyedropper) in the infobar
+    pub fn show_sampled_color(&self, pix: &Pixel) {
+        self.infobar.set_sampled_color(pix)
+    }
+
     pub fn update_after_undo_or_redo(&mut self) {
         let last_cursor_pos_pix = self.last_cursor_pos_pix();
-        if let MouseMode::Pencil(ref mut pencil_state) = self.ui_p.borrow_mut()
-                .toolbar_p.borrow_mut().mouse_mode_mut() {
+        if let MouseMode::Pencil(ref mut pencil_state) | MouseMode::Eraser(ref mut pencil_state) |
+               MouseMode::Smudge(ref mut pencil_state) | MouseMode::Blur(ref mut pencil_state) |
+               MouseMode::Sharpen(ref mut pencil_state) | MouseMode::CloneStamp(ref mut pencil_state) |
+               MouseMode::Healing(ref mut pencil_state) | MouseMode::Dodge(ref mut pencil_state) |
+               MouseMode::Burn(ref mut pencil_state) =
+                self.ui_p.borrow_mut().toolbar_p.borrow_mut().mouse_mode_mut() {
             pencil_state.set_last_cursor_pos_pix(last_cursor_pos_pix)
         }
 
@@ -693,11 +811,13 @@ impl Canvas {
     }
 
     pub fn undo(&mut self) {
+        self.commit_path_changes(ActionName::EditPath);
         self.image_hist.undo();
         self.update_after_undo_or_redo();
     }
 
     pub fn redo(&mut self) {
+        self.commit_path_changes(ActionName::EditPath);
         self.image_hist.redo();
         self.update_after_undo_or_redo();
     }
@@ -731,6 +851,8 @@ impl Canvas {
     }
 
     pub fn exec_multi_undoable_action<D: 'static>(&mut self, action: Box<dyn MultiLayerAction<LayerData = D>>) {
+        // the action moves the paths, so earlier edits to them go first
+        self.commit_path_changes(ActionName::EditPath);
         self.image_hist.exec_multi_undoable_action(action);
         self.save_cursor_pos_after_history_commit();
         self.update();
@@ -771,6 +893,185 @@ impl Canvas {
         self.update();
     }
 
+    pub fn pixelate_selection(&mut self, block_size: usize) {
+        if self.active_layer_locked() {
+            self.alert_user_of_lock("Can't pixelate selection: active layer locked");
+            return;
+        }
+
+        let action = Pixelate::new(self.selection.iter(), block_size);
+        self.image_hist.exec_doable_action(action);
+        self.save_cursor_pos_after_history_commit();
+        self.update();
+    }
+
+    /// Draws `gradient` over the selection (or the whole
+    /// layer, if there's no selection)
+    pub fn draw_gradient(&mut self, gradient: Gradient) {
+        let (width, height) = (self.image_width() as usize, self.image_height() as usize);
+
+        let pix_iter: Box<dyn Iterator<Item = (usize, usize)>> = match self.selection {
+            Selection::NoSelection => Box::new((0..height).flat_map(move |r| (0..width).map(move |c| (r, c)))),
+            _ => self.selection.iter(),
+        };
+
+        let action = DrawGradient::new(pix_iter, gradient);
+        self.image_hist.exec_doable_action(action);
+        self.save_cursor_pos_after_history_commit();
+        self.update();
+    }
+
+    pub fn reduce_colors(&mut self, action: ReduceColors) {
+        if self.active_layer_locked() {
+            self.alert_user_of_lock("Can't reduce colors: active layer locked");
+            return;
+        }
+
+        self.exec_auto_diff_action(action);
+    }
+
+    pub fn paths(&self) -> &Vec<VectorPath> {
+        self.image_hist.now().paths()
+    }
+
+    /// The image's paths, for editing (the edits are recorded in
+    /// the undo history by the next `commit_path_changes`)
+    pub fn paths_mut(&mut self) -> &mut Vec<VectorPath> {
+        if self.paths_before_edit.is_none() {
+            self.paths_before_edit = Some(self.paths().clone());
+        }
+
+        self.image_hist.now_mut().paths_mut()
+    }
+
+    /// Records any edits to the paths (since the last commit) as one undo step
+    pub fn commit_path_changes(&mut self, culprit: ActionName) {
+        if let Some(paths_before) = self.paths_before_edit.take() {
+            if &paths_before != self.paths() {
+                self.image_hist.push_paths_diff(paths_before, culprit);
+                self.save_cursor_pos_after_history_commit();
+            }
+        }
+    }
+
+    /// The index of the active path (if it still exists)
+    pub fn active_path_index(&self) -> Option<usize> {
+        self.active_path.filter(|&i| i < self.paths().len())
+    }
+
+    pub fn set_active_path_index(&mut self, index: Option<usize>) {
+        self.active_path = index;
+    }
+
+    pub fn active_path(&self) -> Option<&VectorPath> {
+        self.active_path_index().map(|i| &self.paths()[i])
+    }
+
+    pub fn active_path_mut(&mut self) -> Option<&mut VectorPath> {
+        self.active_path_index().map(|i| &mut self.paths_mut()[i])
+    }
+
+    /// Adds `path` to the image's paths, making it the active one
+    pub fn add_path(&mut self, path: VectorPath) {
+        self.paths_mut().push(path);
+        self.active_path = Some(self.paths().len() - 1);
+    }
+
+    pub fn delete_active_path(&mut self) {
+        if let Some(i) = self.active_path_index() {
+            self.paths_mut().remove(i);
+            self.active_path = None;
+            self.commit_path_changes(ActionName::DeletePath);
+            self.update();
+        }
+    }
+
+    /// Fills the inside of the active path (by the even-odd rule) with `color`
+    pub fn fill_active_path(&mut self, color: RGBA) {
+        if self.active_layer_locked() {
+            self.alert_user_of_lock("Can't fill path: active layer locked");
+            return;
+        }
+
+        let Some(path) = self.active_path() else {
+            return;
+        };
+
+        let bitmask = path.to_bitmask(self.image_width() as usize, self.image_height() as usize);
+        let image = self.active_image_mut();
+        let p = Pixel::from_rgba_struct(color);
+
+        for (r, c) in bitmask.coords_of_active_bits() {
+            *image.pix_at_mut(r as i32, c as i32) = p.clone();
+        }
+
+        self.commit_changes(ActionName::FillPath);
+        self.update();
+    }
+
+    /// Strokes the active path with the pencil's brush (in the primary color)
+    pub fn stroke_active_path(&mut self, toolbar: &Toolbar) {
+        if self.active_layer_locked() {
+            self.alert_user_of_lock("Can't stroke path: active layer locked");
+            return;
+        }
+
+        let Some(path) = self.active_path().cloned() else {
+            return;
+        };
+
+        toolbar.stroke_with_pencil_brush(self, toolbar.primary_color(), |spacing| path.points_along(spacing));
+        self.commit_changes(ActionName::StrokePath);
+        self.clear_pencil_mask();
+        self.update();
+    }
+
+    /// Selects the inside of the active path
+    pub fn select_active_path(&mut self) {
+        if let Some(path) = self.active_path() {
+            let bitmask = path.to_bitmask(self.image_width() as usize, self.image_height() as usize);
+            self.set_selection(Selection::Bitmask(bitmask));
+            self.update();
+        }
+    }
+
+    /// Adds a path around the selection (if there is one), making it the active path
+    pub fn selection_to_path(&mut self) {
+        let path = match self.selection {
+            Selection::Rectangle(x, y, w, h) => VectorPath::from_rectangle(x, y, w, h),
+            Selection::Bitmask(ref bitmask) => VectorPath::from_bitmask(bitmask),
+            Selection::NoSelection => return,
+        };
+
+        self.add_path(path);
+        self.commit_path_changes(ActionName::SelectionToPath);
+        self.update();
+    }
+
+    /// The selected part of the active layer, cropped to the selection's
+    /// bounds (with unselected pixels left transparent); `None` if
+    /// nothing's selected
+    pub fn selected_image(&self) -> Option<Image> {
+        let active_image = self.active_image();
+        let coords = self.selection.iter()
+            .filter(|&(r, c)| r < active_image.height() && c < active_image.width())
+            .collect::<Vec<_>>();
+
+        let r0 = coords.iter().map(|&(r, _)| r).min()?;
+        let r1 = coords.iter().map(|&(r, _)| r).max()?;
+        let c0 = coords.iter().map(|&(_, c)| c).min()?;
+        let c1 = coords.iter().map(|&(_, c)| c).max()?;
+
+        let (width, height) = (c1 - c0 + 1, r1 - r0 + 1);
+        let mut pixels = vec![Pixel::from_rgba(0, 0, 0, 0); width * height];
+
+        for (r, c) in coords.into_iter() {
+            pixels[(r - r0) * width + (c - c0)] = active_image.pix_at(r, c).clone();
+        }
+
+        Some(Image::new(pixels, width, height))
+    }
+
     /// If `self.selection` is out of bounds/invalid, unselect it
     fn validate_selection(&mut self) {
         let is_valid = match self.selection {
@@ -799,6 +1100,21 @@ impl Canvas {
         }
     }
 
+    /// Resize `self.stroke_samples` if it's become too small
+    fn validate_stroke_samples(&mut self) {
+        let image_net_size = (self.image_width() * self.image_height()) as usize;
+        if self.stroke_samples.len() < image_net_size {
+            self.stroke_samples.resize_with(image_net_size, || None);
+        }
+    }
+
+    /// The current stroke's sample at flat index `idx` (if it's drawn there)
+    fn stroke_sample_at(&self, idx: usize) -> Option<&StrokeSample> {
+        self.stroke_samples.get(idx)
+            .and_then(|sample| sample.as_ref())
+            .filter(|sample| sample.stroke == self.pencil_mask_counter)
+    }
+
     pub fn last_cursor_pos_pix(&self) -> (f64, f64) {
         let hist_id = self.image_hist.now_id();
         *self.history_id_to_cursor_pos_pix.get(&hist_id)
@@ -819,43 +1135,395 @@ impl Canvas {
     }
 
     pub fn clear_pencil_mask(&mut self) {
-        self.pencil_mask_counter += 1
+        self.pencil_mask_counter += 1;
+        self.pixelate_block_averages.clear();
+        self.smudge_buffer.clear();
+        self.clone_source_image = None;
     }
 
-    /// Draws `other` onto self.image() at (x, y),
-    /// setting the pencil mask at the drawn pixels,
-    /// and not drawing any pixels that are aready
-    /// set in the mask
-    pub fn sample_image_respecting_pencil_mask(
+    /// Stamps `other` onto self.image() at (x, y) as part of the current
+    /// stroke, setting the pencil mask at the drawn pixels. Each of `other`'s pixels moves the stroke's
+    /// alpha at that pixel `flow` of the way toward (its alpha * `opacity`),
+    /// so overlapping stamps build up, but never past `opacity`.
+    pub fn stamp_image_in_stroke(
         &mut self,
         other: &impl ImageLike,
         blending_mode: &BlendingMode,
+        flow: f64,
+        opacity: f64,
         x: i32,
         y: i32
     ) {
+        self.validate_stroke_samples();
+        let w = self.image_width() as usize;
+
         for i in 0..other.height() {
             for j in 0..other.width() {
                 let ip = i as i32 + y;
                 let jp = j as i32 + x;
 
+                if ip < 0 || jp < 0 || ip >= self.image_height() || jp >= self.image_width() {
+                    continue;
+                }
+
+                let op = if let Some(op) = other.try_pix_at(i as usize, j as usize) {
+                    op
+                } else {
+                    continue;
+                };
+
+                let idx = ip as usize * w + jp as usize;
+                let target_alpha = op.alpha() as f64 / 255.0 * opacity;
+
+                if self.stroke_sample_at(idx).is_none() {
+                    let original = self.layered_image().pix_at(ip, jp).clone();
+                    self.stroke_samples[idx] = Some(StrokeSample {
+                        stroke: self.pencil_mask_counter,
+                        original,
+                        color: op.clone(),
+                        alpha: 0.0,
+                    });
+                }
+
+                let Some(sample) = self.stroke_samples[idx].as_mut() else {
+                    continue;
+                };
+
+                if sample.alpha >= target_alpha {
+                    continue;
+                }
+
+                sample.alpha += (target_alpha - sample.alpha) * flow;
+
+                let above = Pixel::from_rgba(
+                    sample.color.red(),
+                    sample.color.green(),
+                    sample.color.blue(),
+                    (sample.alpha * 255.0).round() as u8,
+                );
+                let blended = blending_mode.blend(&above, &sample.original);
+
+                *self.active_image_mut().pix_at_mut(ip, jp) = blended;
+                self.set_pencil_mask_at(ip as usize, jp as usize);
+            }
+        }
+    }
+
+    /// Whether the current stroke has drawn on the pixel at (`x`, `y`)
+    pub fn stroke_drew_at(&self, x: i32, y: i32) -> bool {
+        let w = self.image_width() as usize;
+
+        x >= 0 && y >= 0 && (x as usize) < w && self.stroke_sample_at(y as usize * w + x as usize).is_some()
+    }
+
+    /// Restores the pixel at (`x`, `y`) to how it was before the current stroke
+    /// (and clears it from the pencil mask), as if the stroke never drew on it
+    pub fn undraw_stroke_pixel(&mut self, x: i32, y: i32) {
+        if x < 0 || y < 0 {
+            return;
+        }
+
+        let w = self.image_width() as usize;
+        if x as usize >= w {
+            return;
+        }
+
+        let idx = y as usize * w + x as usize;
+        if self.stroke_sample_at(idx).is_none() {
+            return;
+        }
+
+        if let Some(sample) = self.stroke_samples[idx].take() {
+            *self.active_image_mut().pix_at_mut(y, x) = sample.original;
+
+            self.validate_pencil_mask();
+            // the counter only increases, so this will never match it
+            self.pencil_mask[idx] = 0;
+        }
+    }
+
+    /// Like `stamp_image_in_stroke`, but instead of drawing
+    /// `footprint`'s pixels, the average of the `block_size` by `block_size`
+    /// block is blended onto each of the pixels beneath them
+    pub fn pixelate_respecting_pencil_mask(
+        &mut self,
+        footprint: &impl ImageLike,
+        blending_mode: &BlendingMode,
+        block_size: usize,
+        x: i32,
+        y: i32
+    ) {
+        let block_size = block_size.max(1);
+
+        for i in 0..footprint.height() {
+            for j in 0..footprint.width() {
+                let ip = i as i32 + y;
+                let jp = j as i32 + x;
+
                 if ip < 0 || jp < 0 || ip >= self.image_height() || jp >= self.image_width() ||
+                   footprint.try_pix_at(i, j).is_none() ||
                    self.test_pencil_mask_at(ip as usize, jp as usize) {
                     continue;
                 }
 
+                let key = (block_size, ip as usize / block_size, jp as usize / block_size);
+                let average = if let Some(average) = self.pixelate_block_averages.get(&key) {
+                    average.clone()
+                } else {
+                    let average = block_average(self.layered_image(), block_size, (key.1, key.2));
+                    self.pixelate_block_averages.insert(key, average.clone());
+                    average
+                };
+
                 let p = self.active_image_mut().pix_at_mut(ip, jp);
-                let mut success = false;
+                *p = blending_mode.blend(&average, &p);
+                self.set_pencil_mask_at(ip as usize, jp as usize);
+            }
+        }
+    }
+
+    /// Like `pixelate_respecting_pencil_mask`, but each of the pixels beneath
+    /// `footprint` is lightened (or, if `burn`, darkened) within `range`, by
+    /// `exposure` (scaled by the footprint's alpha). Pixels are only adjusted
+    /// once per stroke, so overlapping stamps don't compound.
+    pub fn dodge_burn_respecting_pencil_mask(
+        &mut self,
+        footprint: &impl ImageLike,
+        range: ToneRange,
+        exposure: f64,
+        burn: bool,
+        x: i32,
+        y: i32
+    ) {
+        for i in 0..footprint.height() {
+            for j in 0..footprint.width() {
+                let ip = i as i32 + y;
+                let jp = j as i32 + x;
+
+                if ip < 0 || jp < 0 || ip >= self.image_height() || jp >= self.image_width() ||
+                   self.test_pencil_mask_at(ip as usize, jp as usize) {
+                    continue;
+                }
+
+                let amount = if let Some(op) = footprint.try_pix_at(i, j) {
+                    op.alpha() as f64 / 255.0 * exposure
+                } else {
+                    continue;
+                };
+
+                let p = self.active_image_mut().pix_at_mut(ip, jp);
+                *p = dodge_burn_pixel(&p, range, amount, burn);
+                self.set_pencil_mask_at(ip as usize, jp as usize);
+            }
+        }
+    }
+
+    /// Blurs (or, if `sharpen`, sharpens) each of the pixels beneath `footprint`,
+    /// by `strength` (scaled by the footprint's alpha). Pixels are filtered from
+    /// their values before the stroke, and only once per stroke, so overlapping
+    /// stamps don't compound.
+    pub fn filter_beneath(
+        &mut self,
+        footprint: &impl ImageLike,
+        sharpen: bool,
+        strength: f64,
+        x: i32,
+        y: i32
+    ) {
+        for i in 0..footprint.height() {
+            for j in 0..footprint.width() {
+                let ip = i as i32 + y;
+                let jp = j as i32 + x;
+
+                if ip < 0 || jp < 0 || ip >= self.image_height() || jp >= self.image_width() ||
+                   self.test_pencil_mask_at(ip as usize, jp as usize) {
+                    continue;
+                }
+
+                let amount = if let Some(op) = footprint.try_pix_at(i, j) {
+                    op.alpha() as f64 / 255.0 * strength
+                } else {
+                    continue;
+                };
+
+                let image = self.layered_image();
+                let p = image.committed_pix_at(ip as usize, jp as usize);
+                let blurred = neighborhood_average(image, ip as usize, jp as usize);
+                let filtered = if sharpen {
+                    sharpen_pixel(p, &blurred, amount)
+                } else {
+                    mix_pixels(p, &blurred, amount)
+                };
+
+                *self.active_image_mut().pix_at_mut(ip, jp) = filtered;
+                self.set_pencil_mask_at(ip as usize, jp as usize);
+            }
+        }
+    }
+
+    /// Drags the color picked up by the previous stamp of the current stroke onto
+    /// the pixels beneath `footprint`, blending by `strength` (scaled by the footprint's
+    /// alpha). The first stamp of a stroke only picks up color.
+    pub fn smudge_beneath(
+        &mut self,
+        footprint: &impl ImageLike,
+        strength: f64,
+        x: i32,
+        y: i32
+    ) {
+        let (width, height) = (footprint.width(), footprint.height());
 
-                if let Some(op) = other.try_pix_at(i as usize, j as usize) {
-                    *p = blending_mode.blend(op, &p);
-                    success = true;
+        if self.smudge_buffer.len() != width * height {
+            self.smudge_buffer = vec![None; width * height];
+        }
+
+        for i in 0..height {
+            for j in 0..width {
+                let ip = i as i32 + y;
+                let jp = j as i32 + x;
+
+                if ip < 0 || jp < 0 || ip >= self.image_height() || jp >= self.image_width() {
+                    continue;
                 }
 
-                if success {
-                    self.set_pencil_mask_at(ip as usize, jp as usize);
+                let amount = if let Some(op) = footprint.try_pix_at(i, j) {
+                    op.alpha() as f64 / 255.0 * strength
+                } else {
+                    continue;
+                };
+
+                let beneath = self.layered_image().pix_at(ip, jp).clone();
+
+                self.smudge_buffer[i * width + j] = Some(match self.smudge_buffer[i * width + j].take() {
+                    Some(carried) => {
+                        let smudged = mix_pixels(&beneath, &carried, amount);
+                        *self.active_image_mut().pix_at_mut(ip, jp) = smudged.clone();
+                        smudged
+                    },
+                    None => beneath,
+                });
+            }
+        }
+    }
+
+    /// Makes the cursor's position the clone stamp's source
+    pub fn set_clone_source_to_cursor(&mut self) {
+        self.clone_source = Some(self.cursor_pos_pix_i());
+        self.clone_offset = None;
+    }
+
+    /// The point the clone stamp would copy from: the source point, or, if
+    /// `following` (and a stroke has started from it), the cursor plus the offset
+    pub fn clone_sample_point(&self, following: bool) -> Option<(i32, i32)> {
+        let (x, y) = self.cursor_pos_pix_i();
+
+        match (self.clone_offset, following) {
+            (Some((dx, dy)), true) => Some((x + dx, y + dy)),
+            _ => self.clone_source,
+        }
+    }
+
+    /// Prepares a clone stroke starting at the cursor, copying from the active
+    /// layer (or the visible composite, if `sample_merged`). Unless `aligned` (and
+    /// an earlier stroke set the offset), the stroke starts copying from the
+    /// source point. Returns `false` if there's no source point.
+    pub fn begin_clone_stroke(&mut self, aligned: bool, sample_merged: bool) -> bool {
+        let Some((source_x, source_y)) = self.clone_source else {
+            return false;
+        };
+
+        if !aligned || self.clone_offset.is_none() {
+            let (x, y) = self.cursor_pos_pix_i();
+            self.clone_offset = Some((source_x - x, source_y - y));
+        }
+
+        let image = self.layered_image();
+        self.clone_source_image = Some(if sample_merged {
+            image.composite_image()
+        } else {
+            image.active_image().clone()
+        });
+
+        true
+    }
+
+    /// Copies the pixels at the clone offset onto those beneath `footprint`
+    /// (blending by the footprint's alpha). If `heal`, the copied pixels are
+    /// shifted to match the colors surrounding the footprint. Like
+    /// `filter_beneath`, this works from the pixels as they were before
+    /// the stroke, and only changes each pixel once per stroke.
+    pub fn clone_beneath(
+        &mut self,
+        footprint: &impl ImageLike,
+        heal: bool,
+        x: i32,
+        y: i32
+    ) {
+        // (the source is borrowed throughout, so the mask is read directly)
+        self.validate_pencil_mask();
+        let image_width = self.image_width() as usize;
+
+        let (Some(source), Some((dx, dy))) = (self.clone_source_image.as_ref(), self.clone_offset) else {
+            return;
+        };
+
+        let (width, height) = (footprint.width(), footprint.height());
+        let in_bounds = |r: i32, c: i32| r >= 0 && c >= 0 &&
+            r < source.height() as i32 && c < source.width() as i32;
+
+        // the surroundings are sampled along the edges of the footprint's bounds
+        let correction = if heal {
+            let rim = (0..height)
+                .flat_map(|i| (0..width).map(move |j| (i, j)))
+                .filter(|&(i, j)| i == 0 || j == 0 || i == height - 1 || j == width - 1)
+                .map(|(i, j)| (i as i32 + y, j as i32 + x))
+                .filter(|&(r, c)| in_bounds(r, c) && in_bounds(r + dy, c + dx))
+                .collect::<Vec<_>>();
+
+            let image = self.layered_image();
+            let target = average_pixels(rim.iter().map(|&(r, c)| image.committed_pix_at(r as usize, c as usize)));
+            let copied = average_pixels(rim.iter().map(|&(r, c)| {
+                source.pix_at((r + dy) as usize, (c + dx) as usize)
+            }));
+
+            Some((target, copied))
+        } else {
+            None
+        };
+
+        let mut cloned = vec![];
+
+        for i in 0..height {
+            for j in 0..width {
+                let ip = i as i32 + y;
+                let jp = j as i32 + x;
+
+                if !in_bounds(ip, jp) || !in_bounds(ip + dy, jp + dx) ||
+                   self.pencil_mask[ip as usize * image_width + jp as usize] == self.pencil_mask_counter {
+                    continue;
                 }
+
+                let amount = if let Some(op) = footprint.try_pix_at(i, j) {
+                    op.alpha() as f64 / 255.0
+                } else {
+                    continue;
+                };
+
+                let copied = source.pix_at((ip + dy) as usize, (jp + dx) as usize);
+                let copied = match correction {
+                    Some((ref target, ref source_average)) => shift_pixel(copied, source_average, target),
+                    None => copied.clone(),
+                };
+
+                let p = self.layered_image().committed_pix_at(ip as usize, jp as usize);
+                cloned.push((ip, jp, mix_pixels(p, &copied, amount)));
             }
         }
+
+        for (ip, jp, p) in cloned.into_iter() {
+            *self.active_image_mut().pix_at_mut(ip, jp) = p;
+            self.set_pencil_mask_at(ip as usize, jp as usize);
+        }
     }
 
     pub fn append_layer(&mut self, fill_color: RGBA) -> LayerIndex {
@@ -6,9 +6,11 @@ pub use nary::*;
 
 use crate::image::generate::NewImageProps;
 use crate::ui::form::{ColorField, ExpandJustificationField};
-use super::form::DropdownField;
+use super::form::{DropdownField, NaturalField, TextField};
 use super::form::{Form, FormBuilderIsh, gadget::AspectRatioGadget};
 use crate::image::resize::{Scale, ScaleMethod, Expand, ExpandJustification, Crop};
+use crate::image::quantize::{ColorOrder, Dithering, QuantizeMethod};
+use crate::image::brush::MaskSource;
 
 use gtk::prelude::*;
 use gtk::glib::{object::IsA, error::Error as GError};
@@ -40,20 +42,6 @@ pub fn choose_file_dialog<P: FnOnce(Result<File, GError>) + 'static>(
     }
 }
 
-pub fn choose_color_dialog<P: FnOnce(Result<RGBA, GError>) + 'static>(
-    parent: Option<&impl IsA<gtk::Window>>,
-    callback: P
-) {
-    let dialog = gtk::ColorDialog::builder()
-        .with_alpha(true)
-        .build();
-
-    dialog.choose_rgba(parent,
-                       Some(&RGBA::new(0.5, 0.5, 0.5, 0.5)),
-                       None::<&Cancellable>,
-                       callback);
-}
-
 pub fn new_image_dialog<P: Fn(NewImageProps) + 'static>(
     parent: &impl IsA<gtk::Window>,
     callback: P
@@ -209,3 +197,155 @@ pub fn truncate_dialog<P: Fn((i32, i32, i32, i32)) + 'static>(
 
     ok_cancel_dialog(parent, "Expand", form.widget(), on_ok, on_cancel, || ());
 }
+
+pub fn pixelate_dialog<P: Fn(usize) + 'static>(
+    parent: &impl IsA<gtk::Window>,
+    callback: P
+) {
+    const DEFAULT_BLOCK_SIZE: usize = 8;
+
+    let block_size_field = NaturalField::new(Some("Block Size"), 1, 255, 1, DEFAULT_BLOCK_SIZE);
+
+    let form = Form::builder()
+        .title("Pixelate Selection")
+        .with_field(&block_size_field)
+        .build();
+
+    let on_ok = move || {
+        callback(block_size_field.value());
+        CloseDialog::Yes
+    };
+
+    let on_cancel = || CloseDialog::Yes;
+
+    ok_cancel_dialog(parent, "Pixelate", form.widget(), on_ok, on_cancel, || ());
+}
+
+/// The callback is given the quantization method (`None` to
+/// use the toolbar palette), the number of colors, and the dithering
+pub fn reduce_colors_dialog<P: Fn(Option<QuantizeMethod>, usize, Dithering) + 'static>(
+    parent: &impl IsA<gtk::Window>,
+    callback: P
+) {
+    const DEFAULT_NUM_COLORS: usize = 16;
+
+    let palette_sources = QuantizeMethod::labeled_variants()
+        .map(|(label, method)| (label, Some(method)))
+        .chain(std::iter::once(("Toolbar Palette", None)))
+        .collect::<Vec<_>>();
+    let palette_field = DropdownField::new(Some("Palette"), palette_sources, 0);
+    let num_colors_field = NaturalField::new(Some("Number of Colors"), 2, 256, 1, DEFAULT_NUM_COLORS);
+    let dithering_field = DropdownField::new(
+        Some("Dithering"),
+        Dithering::labeled_variants().collect::<Vec<_>>(),
+        0,
+    );
+
+    let form = Form::builder()
+        .title("Reduce Colors")
+        .with_field(&palette_field)
+        .with_field(&num_colors_field)
+        .with_field(&dithering_field)
+        .build();
+
+    let on_ok = move || {
+        callback(
+            palette_field.value().clone(),
+            num_colors_field.value(),
+            dithering_field.value().clone(),
+        );
+        CloseDialog::Yes
+    };
+
+    let on_cancel = || CloseDialog::Yes;
+
+    ok_cancel_dialog(parent, "Reduce Colors", form.widget(), on_ok, on_cancel, || ());
+}
+
+pub fn extract_palette_dialog<P: Fn(QuantizeMethod, usize, ColorOrder) + 'static>(
+    parent: &impl IsA<gtk::Window>,
+    default_num_colors: usize,
+    callback: P
+) {
+    let method_field = DropdownField::new(
+        Some("Method"),
+        QuantizeMethod::labeled_variants().collect::<Vec<_>>(),
+        0,
+    );
+    let num_colors_field = NaturalField::new(Some("Number of Colors"), 1, 256, 1, default_num_colors);
+    let order_field = DropdownField::new(
+        Some("Order By"),
+        ColorOrder::labeled_variants().collect::<Vec<_>>(),
+        0,
+    );
+
+    let form = Form::builder()
+        .title("Extract Palette")
+        .with_field(&method_field)
+        .with_field(&num_colors_field)
+        .with_field(&order_field)
+        .build();
+
+    let on_ok = move || {
+        callback(
+            method_field.value().clone(),
+            num_colors_field.value(),
+            order_field.value().clone(),
+        );
+        CloseDialog::Yes
+    };
+
+    let on_cancel = || CloseDialog::Yes;
+
+    ok_cancel_dialog(parent, "Extract Palette", form.widget(), on_ok, on_cancel, || ());
+}
+
+pub fn brush_preset_dialog<P: Fn(String) + 'static>(
+    parent: &impl IsA<gtk::Window>,
+    default_name: &str,
+    callback: P
+) {
+    let name_field = TextField::new(Some("Name"), default_name, "Preset Name");
+
+    let form = Form::builder()
+        .title("Save Brush Preset")
+        .with_field(&name_field)
+        .build();
+
+    let on_ok = move || {
+        callback(name_field.value());
+        CloseDialog::Yes
+    };
+
+    let on_cancel = || CloseDialog::Yes;
+
+    ok_cancel_dialog(parent, "Save Brush Preset", form.widget(), on_ok, on_cancel, || ());
+}
+
+pub fn brush_tip_dialog<P: Fn(String, MaskSource) + 'static>(
+    parent: &impl IsA<gtk::Window>,
+    default_name: &str,
+    callback: P
+) {
+    let name_field = TextField::new(Some("Name"), default_name, "Brush Name");
+    let mask_source_field = DropdownField::new(
+        Some("Mask From"),
+        MaskSource::labeled_variants().collect::<Vec<_>>(),
+        0,
+    );
+
+    let form = Form::builder()
+        .title("New Brush Tip")
+        .with_field(&name_field)
+        .with_field(&mask_source_field)
+        .build();
+
+    let on_ok = move || {
+        callback(name_field.value(), mask_source_field.value().clone());
+        CloseDialog::Yes
+    };
+
+    let on_cancel = || CloseDialog::Yes;
+
+    ok_cancel_dialog(parent, "New Brush Tip", form.widget(), on_ok, on_cancel, || ());
+}
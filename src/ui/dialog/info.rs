@@ -49,6 +49,34 @@ pub fn keyboard_shortcuts_dialog(parent: &impl IsA<gtk::Window>) {
 
     let draw_mode = group_from_specs("Draw Mode", &[
         ("Draw Straight Line", "<Shift>Pointer_Left"),
+        ("Move Symmetry Center", "<Ctrl>Pointer_Left"),
+    ]);
+
+    let clone_stamp_mode = group_from_specs("Clone Stamp / Healing Mode", &[
+        ("Set Source", "<Ctrl>Pointer_Left"),
+        ("Draw Straight Line", "<Shift>Pointer_Left"),
+    ]);
+
+    let line_mode = group_from_specs("Line Mode", &[
+        ("Snap to 15° Angles", "<Shift>Pointer_Left"),
+        ("Commit Line", "Pointer_Right"),
+        ("Discard Line", "<Ctrl>Pointer_Right"),
+    ]);
+
+    let pen_mode = group_from_specs("Pen Mode", &[
+        ("Add Anchor (Drag for Handles)", "Pointer_Left"),
+        ("Move Handle Independently", "<Ctrl>Pointer_Left"),
+        ("Remove Anchor / Finish Path", "Pointer_Right"),
+    ]);
+
+    let fill_mode = group_from_specs("Fill Mode", &[
+        ("Move Symmetry Center", "<Ctrl>Pointer_Left"),
+    ]);
+
+    let gradient_mode = group_from_specs("Gradient Mode", &[
+        ("Draw Gradient", "Pointer_Left"),
+        ("Draw Reversed Gradient", "Pointer_Right"),
+        ("Snap to 15° Angles", "<Shift>Pointer_Left"),
     ]);
 
     let rectangle_select_mode = group_from_specs("Rectangle Select Mode", &[
@@ -82,6 +110,12 @@ pub fn keyboard_shortcuts_dialog(parent: &impl IsA<gtk::Window>) {
         ("Remove Palette Color", "<Ctrl>Pointer_Right"),
     ]);
 
+    let brush_presets = group_from_specs("Brush Presets", &[
+        ("Next Brush Preset", "<Ctrl>bracketright"),
+        ("Previous Brush Preset", "<Ctrl>bracketleft"),
+        ("Select Brush Preset 1-9", "<Alt>1...<Alt>9"),
+    ]);
+
     let undo = group_from_specs("Undo", &[
         ("Undo", "<Ctrl>z"),
         ("Redo", "<Ctrl>y"),
@@ -109,12 +143,18 @@ pub fn keyboard_shortcuts_dialog(parent: &impl IsA<gtk::Window>) {
     main_section.add_group(&undo);
     main_section.add_group(&editing);
     main_section.add_group(&draw_mode);
+    main_section.add_group(&clone_stamp_mode);
+    main_section.add_group(&line_mode);
+    main_section.add_group(&pen_mode);
+    main_section.add_group(&fill_mode);
+    main_section.add_group(&gradient_mode);
     main_section.add_group(&rectangle_select_mode);
     main_section.add_group(&magic_wand_mode);
     main_section.add_group(&eyedropper_mode);
     main_section.add_group(&free_transform_mode);
     main_section.add_group(&all_modes);
     main_section.add_group(&palette);
+    main_section.add_group(&brush_presets);
     main_section.add_group(&misc);
     main_section.add_group(&io);
 
@@ -171,12 +171,20 @@ impl CheckboxField {
         self.button.is_active()
     }
 
+    pub fn set_value(&self, is_checked: bool) {
+        self.button.set_active(is_checked);
+    }
+
     pub fn set_toggled_hook<F: Fn(bool) + 'static>(&self, f: F)
     {
         self.button.connect_toggled(move |b| {
             f(b.is_active())
         });
     }
+
+    pub fn set_sensitive(&self, sensitive: bool) {
+        self.button.set_sensitive(sensitive);
+    }
 }
 
 impl FormField for CheckboxField {
@@ -287,6 +295,20 @@ impl<T> DropdownField<T> {
         &self.variants[self.dropdown.selected() as usize]
     }
 
+    pub fn set_selected(&self, index: usize) {
+        self.dropdown.set_selected(index as u32);
+    }
+
+    /// Selects the first variant equal to `value` (if there is one)
+    pub fn set_value(&self, value: &T)
+    where
+        T: PartialEq,
+    {
+        if let Some(index) = self.variants.iter().position(|v| v == value) {
+            self.set_selected(index);
+        }
+    }
+
     pub fn set_orientation(&self, orientation: gtk::Orientation) {
         self.wrapper.set_orientation(orientation);
     }
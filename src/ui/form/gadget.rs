@@ -155,6 +155,10 @@ impl NumberedSliderGadget {
     pub fn value(&self) -> usize {
         self.slider_field.value()
     }
+
+    pub fn set_value(&self, new_value: usize) {
+        self.slider_field.set_value(new_value)
+    }
 }
 
 impl FormGadget for NumberedSliderGadget {
@@ -16,10 +16,22 @@ macro_rules! static_icon_texture {
 // Main ribbon
 static_icon_texture!("../../icons/cursor.png", CURSOR);
 static_icon_texture!("../../icons/pencil.png", PENCIL);
+static_icon_texture!("../../icons/eraser.png", ERASER);
+static_icon_texture!("../../icons/smudge.png", SMUDGE);
+static_icon_texture!("../../icons/blur.png", BLUR);
+static_icon_texture!("../../icons/sharpen.png", SHARPEN);
+static_icon_texture!("../../icons/clone-stamp.png", CLONE_STAMP);
+static_icon_texture!("../../icons/healing.png", HEALING);
+static_icon_texture!("../../icons/dodge.png", DODGE);
+static_icon_texture!("../../icons/burn.png", BURN);
+static_icon_texture!("../../icons/airbrush.png", AIRBRUSH);
+static_icon_texture!("../../icons/line.png", LINE);
+static_icon_texture!("../../icons/pen.png", PEN);
 static_icon_texture!("../../icons/eyedropper.png", EYEDROPPER);
 static_icon_texture!("../../icons/rectangle-select.png", RECTANGLE_SELECT);
 static_icon_texture!("../../icons/magic-wand.png", MAGIC_WAND);
 static_icon_texture!("../../icons/fill.png", FILL);
+static_icon_texture!("../../icons/gradient.png", GRADIENT);
 static_icon_texture!("../../icons/free-transform.png", FREE_TRANSFORM);
 static_icon_texture!("../../icons/shape.png", SHAPE);
 static_icon_texture!("../../icons/text.png", TEXT);
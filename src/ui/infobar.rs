@@ -1,10 +1,22 @@
 use gtk::prelude::*;
 
 use super::canvas::Canvas;
+use crate::image::Pixel;
+use crate::image::color::{pixel_to_hex, rgb_to_hsv};
+
+use std::rc::Rc;
+use std::cell::Cell;
 
 pub struct Infobar {
     size_label: gtk::Label,
     cursor_pos_label: gtk::Label,
+    /// Shows the last color sampled by the eyedropper
+    sampled_color_box: gtk::Box,
+    sampled_color_swatch: gtk::DrawingArea,
+    sampled_color: Rc<Cell<(f64, f64, f64, f64)>>,
+    /// hex, rgb, and hsv representations of the sampled color
+    /// (each copies its label to the clipboard when clicked)
+    sampled_color_buttons: [gtk::Button; 3],
     widget: gtk::CenterBox,
 }
 
@@ -19,13 +31,63 @@ impl Infobar {
             .margin_end(15)
             .build();
 
+        let sampled_color = Rc::new(Cell::new((0.0, 0.0, 0.0, 0.0)));
+
+        let sampled_color_swatch = gtk::DrawingArea::builder()
+            .content_height(14)
+            .content_width(14)
+            .valign(gtk::Align::Center)
+            .build();
+
+        let sampled_color_clone = sampled_color.clone();
+        sampled_color_swatch.set_draw_func(move |_, cr, width, height| {
+            let (r, g, b, a) = sampled_color_clone.get();
+            cr.set_source_rgba(r, g, b, a);
+            cr.rectangle(0.0, 0.0, width as f64, height as f64);
+            let _ = cr.fill_preserve();
+            cr.set_line_width(1.0);
+            cr.set_source_rgb(0.0, 0.0, 0.0);
+            let _ = cr.stroke();
+        });
+
+        let sampled_color_buttons = [(); 3].map(|_| {
+            let button = gtk::Button::builder()
+                .css_classes(["flat", "no-padding"])
+                .tooltip_text("Copy to Clipboard")
+                .build();
+
+            button.connect_clicked(|button| {
+                if let Some(text) = button.label() {
+                    button.clipboard().set_text(&text);
+                }
+            });
+
+            button
+        });
+
+        let sampled_color_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(8)
+            .visible(false)
+            .build();
+
+        sampled_color_box.append(&sampled_color_swatch);
+        for button in sampled_color_buttons.iter() {
+            sampled_color_box.append(button);
+        }
+
         widget.set_start_widget(Some(&cursor_pos_label));
+        widget.set_center_widget(Some(&sampled_color_box));
         widget.set_end_widget(Some(&size_label));
 
         Infobar {
             widget,
             size_label,
             cursor_pos_label,
+            sampled_color_box,
+            sampled_color_swatch,
+            sampled_color,
+            sampled_color_buttons,
         }
     }
 
@@ -40,8 +102,32 @@ impl Infobar {
         self.cursor_pos_label.set_label(format!("{x}, {y}").as_str());
     }
 
+    pub fn set_sampled_color(&self, pix: &Pixel) {
+        let rgba = pix.to_rgba_struct();
+        let (r, g, b) = (rgba.red() as f64, rgba.green() as f64, rgba.blue() as f64);
+        let (h, s, v) = rgb_to_hsv(r, g, b);
+
+        let labels = [
+            pixel_to_hex(pix),
+            format!("rgb({}, {}, {})", pix.red(), pix.green(), pix.blue()),
+            format!(
+                "hsv({:.0}, {:.0}%, {:.0}%)",
+                h.unwrap_or(0.0),
+                s.unwrap_or(0.0) * 100.0,
+                v * 100.0,
+            ),
+        ];
+
+        for (button, label) in self.sampled_color_buttons.iter().zip(labels) {
+            button.set_label(&label);
+        }
+
+        self.sampled_color.set((r, g, b, rgba.alpha() as f64));
+        self.sampled_color_swatch.queue_draw();
+        self.sampled_color_box.set_visible(true);
+    }
+
     pub fn widget(&self) -> &impl IsA<gtk::Widget> {
         &self.widget
     }
 }
-
@@ -10,7 +10,7 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use glib_macros::clone;
 
-fn mk_file_filter_list(extss: Vec<Vec<&str>>) -> ListStore {
+pub(super) fn mk_file_filter_list(extss: Vec<Vec<&str>>) -> ListStore {
     let list = ListStore::new::<gtk::FileFilter>();
 
     let supported = gtk::FileFilter::new();
@@ -127,6 +127,29 @@ impl UiState {
         }))
     }
 
+    /// Prompt user for an image file, then use it as the fill tool's pattern
+    pub fn import_fill_pattern(ui_p: Rc<RefCell<UiState>>) {
+        let valid_filetypes = mk_file_filter_list(image_import_formats());
+
+        choose_file_dialog(&ui_p.borrow().window, "Choose a pattern image",
+                    "Import", &valid_filetypes, false,
+                    clone!(@strong ui_p => move |res| {
+            if let Ok(res) = res {
+                let path = res.path().unwrap();
+                match Image::from_path(&path) {
+                    Ok(img) => ui_p.borrow().toolbar_p.borrow_mut().set_fill_pattern(img),
+                    Err(mesg) => {
+                        ok_dialog_str_(
+                            ui_p.borrow().window(),
+                            "Import Error",
+                            format!("Error during import: {}", mesg).as_str()
+                        );
+                    }
+                }
+            }
+        }))
+    }
+
     pub fn export(ui_p: Rc<RefCell<UiState>>) {
         let valid_filetypes = mk_file_filter_list(image_export_formats());
 
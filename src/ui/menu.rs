@@ -67,7 +67,11 @@ pub fn mk_menu(ui_state: Rc<RefCell<UiState>>) -> (gio::Menu, Vec<gio::SimpleAct
         .item_with_keybind("<Ctrl>z", "Undo", "undop", Box::new(clone!(@strong ui_state => move || UiState::undo(ui_state.clone()))))
         .item_with_keybind("<Ctrl>y", "Redo", "redo", Box::new(clone!(@strong ui_state => move || UiState::redo(ui_state.clone()))))
         .item_with_keybind("<Ctrl>h", "History", "history", Box::new(clone!(@strong ui_state => move || UiState::undo_history_dialog(ui_state.clone()))))
-        .item_with_keybind("<Ctrl>l", "Layers", "layers", Box::new(clone!(@strong ui_state => move || UiState::layers_dialog(ui_state.clone()))));
+        .item_with_keybind("<Ctrl>l", "Layers", "layers", Box::new(clone!(@strong ui_state => move || UiState::layers_dialog(ui_state.clone()))))
+        .submenu("Fill Pattern",
+            MenuBuilder::new()
+            .item("Import", "import-fill-pattern", Box::new(clone!(@strong ui_state => move || UiState::import_fill_pattern(ui_state.clone()))))
+            .item("From Selection", "fill-pattern-from-selection", Box::new(clone!(@strong ui_state => move || UiState::fill_pattern_from_selection(ui_state.clone())))));
 
     // image menu helpers
 
@@ -83,6 +87,8 @@ pub fn mk_menu(ui_state: Rc<RefCell<UiState>>) -> (gio::Menu, Vec<gio::SimpleAct
     let expand_fn = Box::new(clone!(@strong ui_state => move || UiState::expand(ui_state.clone())));
     let crop_fn = Box::new(clone!(@strong ui_state => move || UiState::crop_to_selection(ui_state.clone())));
     let truncate_fn = Box::new(clone!(@strong ui_state => move || UiState::truncate(ui_state.clone())));
+    let pixelate_fn = Box::new(clone!(@strong ui_state => move || UiState::pixelate_selection(ui_state.clone())));
+    let reduce_colors_fn = Box::new(clone!(@strong ui_state => move || UiState::reduce_colors(ui_state.clone())));
     let flip_horiz_fn = Box::new(mk_do_uaction(Box::new(Flip::Horizontal)));
     let flip_vert_fn = Box::new(mk_do_uaction(Box::new(Flip::Vertical)));
     let flip_transpose_fn = Box::new(mk_do_uaction(Box::new(Flip::Transpose)));
@@ -95,6 +101,8 @@ pub fn mk_menu(ui_state: Rc<RefCell<UiState>>) -> (gio::Menu, Vec<gio::SimpleAct
         .item("Expand", "expand", expand_fn)
         .item("Crop To Selection", "crop-to-selection", crop_fn)
         .item("Truncate", "truncate", truncate_fn)
+        .item("Pixelate Selection", "pixelate-selection", pixelate_fn)
+        .item("Indexed / Reduce Colors", "reduce-colors", reduce_colors_fn)
         .submenu("Flip",
             MenuBuilder::new()
             .item("Horizontally", "flip-horiz", flip_horiz_fn)
@@ -106,9 +114,37 @@ pub fn mk_menu(ui_state: Rc<RefCell<UiState>>) -> (gio::Menu, Vec<gio::SimpleAct
             .item("90\u{00B0} Counter-Clockwise", "rotate-90-counter-clockwise", rotate_counter_clockwise_fn)
             .item("180\u{00B0}", "rotate-180", rotate_180_fn));
 
+    let path_menu = MenuBuilder::new()
+        .item("Fill Path", "fill-path", Box::new(clone!(@strong ui_state => move || UiState::fill_path(ui_state.clone()))))
+        .item("Stroke Path", "stroke-path", Box::new(clone!(@strong ui_state => move || UiState::stroke_path(ui_state.clone()))))
+        .item("Path to Selection", "path-to-selection", Box::new(clone!(@strong ui_state => move || UiState::path_to_selection(ui_state.clone()))))
+        .item("Selection to Path", "selection-to-path", Box::new(clone!(@strong ui_state => move || UiState::selection_to_path(ui_state.clone()))))
+        .item("Delete Path", "delete-path", Box::new(clone!(@strong ui_state => move || UiState::delete_path(ui_state.clone()))));
+
     let palette_menu = MenuBuilder::new()
         .item("Import", "import-palette", Box::new(clone!(@strong ui_state => move || UiState::import_palette(ui_state.clone()))))
-        .item("Export", "export-palette", Box::new(clone!(@strong ui_state => move || UiState::export_palette(ui_state.clone()))));
+        .item("Export", "export-palette", Box::new(clone!(@strong ui_state => move || UiState::export_palette(ui_state.clone()))))
+        .item("Extract from Image", "extract-palette", Box::new(clone!(@strong ui_state => move || UiState::extract_palette(ui_state.clone()))));
+
+    let select_preset_menu = (1..=9).fold(MenuBuilder::new(), |menu, i| {
+        menu.item_with_keybind(
+            format!("<Alt>{}", i).as_str(),
+            format!("Preset {}", i).as_str(),
+            format!("select-brush-preset-{}", i).as_str(),
+            Box::new(clone!(@strong ui_state => move || UiState::select_brush_preset(ui_state.clone(), i - 1)))
+        )
+    });
+
+    let brushes_menu = MenuBuilder::new()
+        .item("Import Brush Tip", "import-brush", Box::new(clone!(@strong ui_state => move || UiState::import_brush(ui_state.clone()))))
+        .item("Brush Tip from Selection", "brush-from-selection", Box::new(clone!(@strong ui_state => move || UiState::brush_from_selection(ui_state.clone()))))
+        .item("Save Brush Preset", "save-brush-preset", Box::new(clone!(@strong ui_state => move || UiState::save_brush_preset(ui_state.clone()))))
+        .item("Delete Brush Preset", "delete-brush-preset", Box::new(clone!(@strong ui_state => move || UiState::delete_brush_preset(ui_state.clone()))))
+        .item_with_keybind("<Ctrl>bracketright", "Next Brush Preset", "next-brush-preset", Box::new(clone!(@strong ui_state => move || UiState::cycle_brush_preset(ui_state.clone(), true))))
+        .item_with_keybind("<Ctrl>bracketleft", "Previous Brush Preset", "prev-brush-preset", Box::new(clone!(@strong ui_state => move || UiState::cycle_brush_preset(ui_state.clone(), false))))
+        .submenu("Select Brush Preset", select_preset_menu)
+        .item("Import Brush Presets", "import-brush-presets", Box::new(clone!(@strong ui_state => move || UiState::import_brush_presets(ui_state.clone()))))
+        .item("Export Brush Presets", "export-brush-presets", Box::new(clone!(@strong ui_state => move || UiState::export_brush_presets(ui_state.clone()))));
 
     let help_menu = MenuBuilder::new()
         .item("Keyboard Shortcuts", "keyboard-shortcuts",
@@ -120,7 +156,9 @@ pub fn mk_menu(ui_state: Rc<RefCell<UiState>>) -> (gio::Menu, Vec<gio::SimpleAct
         .submenu("File", file_menu)
         .submenu("Edit", edit_menu)
         .submenu("Image", image_menu)
+        .submenu("Path", path_menu)
         .submenu("Palette", palette_menu)
+        .submenu("Brushes", brushes_menu)
         .submenu("Help", help_menu)
         .build()
 }
@@ -1,13 +1,21 @@
 pub mod mode;
 mod palette;
+mod brush_library;
+mod brush_presets;
 
 use gtk::gdk::RGBA;
-use mode::{MouseMode, MouseModeVariant};
+use mode::{AirbrushTip, FillSource, GradientColors, MouseMode, MouseModeVariant, RetouchTool, Stabilizer, Symmetry};
+use mode::{CloneSettings, DodgeBurnSettings, EraserSettings, GradientSettings, PencilSettings, RetouchSettings};
 use super::canvas::Canvas;
 use super::UiState;
 use palette::Palette;
-use crate::image::brush::{Brush, BrushType};
+use brush_library::BrushLibrary;
+use brush_presets::{BrushPreset, BrushPresets};
+use crate::image::brush::{Brush, BrushDynamics, BrushTip, BrushType};
 use crate::image::blend::BlendingMode;
+use crate::image::{Image, ImageLike};
+use crate::image::filter::ToneRange;
+use crate::image::gradient::GradientShape;
 use crate::image::resize::ScaleMethod;
 use crate::ui::icon;
 use crate::shape::ShapeType;
@@ -23,20 +31,27 @@ pub struct Toolbar {
     widget: gtk::Box,
     mode_button_box: gtk::Box,
     palette_p: Rc<RefCell<Palette>>,
+    brush_library_p: Rc<RefCell<BrushLibrary>>,
+    brush_presets_p: Rc<RefCell<BrushPresets>>,
     mouse_mode: MouseMode,
     mouse_mode_buttons: Vec<MouseModeButton>,
     mode_change_hook: Option<Box<dyn Fn(&Toolbar, Option<MouseMode>, MouseMode)>>,
     mode_toolbar: ModeToolbar,
     primary_brush: Brush,
     secondary_brush: Brush,
-    /// One-pixel brush to use for the eyedropper,
-    /// solely for the visual of highlighting one pixel
+    eraser_brush: Brush,
+    /// Shared by the smudge, blur, sharpen, clone stamp, healing, dodge and burn tools
+    retouch_brush: Brush,
+    /// Square brush (sized to the sample size) to use for the
+    /// eyedropper, solely for the visual of highlighting the sampled pixels
     eyedropper_brush: Brush,
     /// Used by self-closing modes (free transform) to recover
     /// the previous mode
     last_two_mode_variants: (MouseModeVariant, MouseModeVariant),
     /// Hack to allow text tool to use non-copyable state
     boxed_transformable: RefCell<Option<Box<dyn Transformable>>>,
+    /// Tiled by the fill tool when it's filling with a pattern
+    fill_pattern: Option<Image>,
 }
 
 struct MouseModeButton {
@@ -71,9 +86,18 @@ impl Toolbar {
             .hexpand(true)
             .vexpand(false)
             .build();
-        let mode_toolbar = ModeToolbar::new(&mode_toolbar_wrapper, Some(INITIAL_MODE.variant()));
+        let brush_library_p = Rc::new(RefCell::new(BrushLibrary::load()));
+        let brush_presets_p = BrushPresets::new_p();
+        let mode_toolbar = ModeToolbar::new(
+            &mode_toolbar_wrapper,
+            Some(INITIAL_MODE.variant()),
+            &brush_library_p,
+            &brush_presets_p,
+        );
         let primary_brush = Brush::new(default_primary_color, default_secondary_color, BrushType::Round, 5);
         let secondary_brush = Brush::new(default_secondary_color, default_primary_color, BrushType::Round, 5);
+        let eraser_brush = Brush::new(default_primary_color, default_secondary_color, BrushType::Round, 10);
+        let retouch_brush = Brush::new(default_primary_color, default_secondary_color, BrushType::Round, 10);
         let eyedropper_brush = Brush::new(default_primary_color, default_secondary_color, BrushType::Square, 1);
 
         widget.append(&mode_button_box);
@@ -87,15 +111,20 @@ impl Toolbar {
             widget,
             mode_button_box,
             palette_p,
+            brush_library_p,
+            brush_presets_p,
             mouse_mode: INITIAL_MODE,
             mouse_mode_buttons: vec![],
             mode_change_hook: None,
             mode_toolbar,
             primary_brush,
             secondary_brush,
+            eraser_brush,
+            retouch_brush,
             eyedropper_brush,
             last_two_mode_variants: (MouseModeVariant::Cursor, MouseModeVariant::Cursor),
             boxed_transformable: RefCell::new(None),
+            fill_pattern: None,
         }));
 
         toolbar_p
@@ -107,10 +136,22 @@ impl Toolbar {
         let button_info: Vec<(_, &str, fn(&mut Canvas) -> MouseMode, fn() -> MouseMode)> = vec![
             (&icon::CURSOR, "Cursor", MouseMode::cursor, MouseMode::cursor_default),
             (&icon::PENCIL, "Pencil", MouseMode::pencil, MouseMode::pencil_default),
+            (&icon::ERASER, "Eraser", MouseMode::eraser, MouseMode::eraser_default),
+            (&icon::SMUDGE, "Smudge", MouseMode::smudge, MouseMode::smudge_default),
+            (&icon::BLUR, "Blur", MouseMode::blur, MouseMode::blur_default),
+            (&icon::SHARPEN, "Sharpen", MouseMode::sharpen, MouseMode::sharpen_default),
+            (&icon::CLONE_STAMP, "Clone Stamp", MouseMode::clone_stamp, MouseMode::clone_stamp_default),
+            (&icon::HEALING, "Healing", MouseMode::healing, MouseMode::healing_default),
+            (&icon::DODGE, "Dodge", MouseMode::dodge, MouseMode::dodge_default),
+            (&icon::BURN, "Burn", MouseMode::burn, MouseMode::burn_default),
+            (&icon::AIRBRUSH, "Airbrush", MouseMode::airbrush, MouseMode::airbrush_default),
+            (&icon::LINE, "Line", MouseMode::line, MouseMode::line_default),
+            (&icon::PEN, "Pen", MouseMode::pen, MouseMode::pen_default),
             (&icon::EYEDROPPER, "Eyedropper", MouseMode::eyedropper, MouseMode::eyedropper_default),
             (&icon::RECTANGLE_SELECT, "Rectangle Select", MouseMode::rectangle_select, MouseMode::rectangle_select_default),
             (&icon::MAGIC_WAND, "Magic Wand", MouseMode::magic_wand, MouseMode::magic_wand_default),
             (&icon::FILL, "Fill", MouseMode::fill, MouseMode::fill_default),
+            (&icon::GRADIENT, "Gradient", MouseMode::gradient, MouseMode::gradient_default),
             (&icon::FREE_TRANSFORM, "Free Transform", MouseMode::free_transform, MouseMode::free_transform_default),
             (&icon::SHAPE, "Shape", MouseMode::shape, MouseMode::shape_default),
             (&icon::TEXT, "Text", MouseMode::text, MouseMode::text_default),
@@ -218,10 +259,45 @@ impl Toolbar {
         self.palette_p.borrow_mut().set_secondary_color(color);
     }
 
+    pub fn palette_colors(&self) -> Vec<RGBA> {
+        self.palette_p.borrow().colors()
+    }
+
     fn add_color_to_palette(&mut self, color: RGBA) -> Result<(), ()> {
         self.palette_p.borrow_mut().add_color(color)
     }
 
+    pub fn set_fill_pattern(&mut self, pattern: Image) {
+        self.fill_pattern = Some(pattern);
+    }
+
+    fn fill_pattern(&self) -> Option<&Image> {
+        self.fill_pattern.as_ref()
+    }
+
+    /// Saves `tip` to the brush library, and selects it as the pencil's brush
+    pub fn add_brush_tip(&self, tip: BrushTip) -> Result<(), String> {
+        self.brush_library_p.borrow_mut().add(tip)
+    }
+
+    /// The pencil's current settings, as a preset named `name`
+    fn current_brush_preset(&self, name: String) -> BrushPreset {
+        let settings = self.mode_toolbar.get_pencil_settings();
+
+        BrushPreset {
+            name,
+            brush_type: settings.brush_type,
+            blending_mode: settings.blending_mode,
+            radius: settings.radius,
+            pixelate_block_size: settings.pixelate_block_size,
+            tip: settings.tip.map(|tip| tip.name().to_string()),
+            dynamics: settings.dynamics,
+            antialias: settings.antialias,
+            pixel_perfect: settings.pixel_perfect,
+            stabilizer: settings.stabilizer,
+        }
+    }
+
     pub fn widget(&self) -> &gtk::Box {
         &self.widget
     }
@@ -233,47 +309,292 @@ impl Toolbar {
     fn get_primary_brush(&mut self) -> &Brush {
         let primary_color = self.primary_color();
         let secondary_color = self.secondary_color();
-        let (brush_type, _blending_mode, radius) = self.mode_toolbar.get_pencil_settings();
-        self.primary_brush.modify(primary_color, secondary_color, brush_type, radius);
+        let PencilSettings { brush_type, radius, tip, .. } = self.mode_toolbar.get_pencil_settings();
+        self.primary_brush.modify_with_tip(primary_color, secondary_color, brush_type, radius, tip);
         &self.primary_brush
     }
 
     fn get_secondary_brush(&mut self) -> &Brush {
         let primary_color = self.primary_color();
         let secondary_color = self.secondary_color();
-        let (brush_type, _blending_mode, radius) = self.mode_toolbar.get_pencil_settings();
-        self.secondary_brush.modify(secondary_color, primary_color, brush_type, radius);
+        let PencilSettings { brush_type, radius, tip, .. } = self.mode_toolbar.get_pencil_settings();
+        self.secondary_brush.modify_with_tip(secondary_color, primary_color, brush_type, radius, tip);
         &self.secondary_brush
     }
 
+    /// A new brush with the pencil's settings, drawing `color`
+    /// (for when the toolbar can't be borrowed mutably)
+    fn get_pencil_brush(&self, color: RGBA) -> Brush {
+        let PencilSettings { brush_type, radius, tip, .. } = self.mode_toolbar.get_pencil_settings();
+        let mut brush = Brush::new(color, self.secondary_color(), brush_type, radius);
+        brush.modify_with_tip(color, self.secondary_color(), brush_type, radius, tip);
+        brush
+    }
+
+    /// Stamps the pencil's brush (drawing `color`) at the points that
+    /// `points_along` gives for the brush's spacing, without committing
+    pub fn stroke_with_pencil_brush(&self, canvas: &mut Canvas, color: RGBA, points_along: impl FnOnce(f64) -> Vec<(f64, f64)>) {
+        let brush = self.get_pencil_brush(color);
+        let blending_mode = self.get_blending_mode();
+        let block_size = self.get_pixelate_block_size();
+        let spacing = self.get_brush_dynamics().sample_distance(brush.radius());
+        let antialias = self.get_pencil_antialias();
+
+        let image = &brush.brush_image;
+        let x_offset = (image.width() as i32 - 1) / 2;
+        let y_offset = (image.height() as i32 - 1) / 2;
+
+        for point in points_along(spacing) {
+            if antialias {
+                if let Some((image, x, y)) = brush.antialiased_image(brush.radius() as f64, point) {
+                    canvas.stamp_image_in_stroke(&image, &blending_mode, 1.0, 1.0, x, y);
+                    continue;
+                }
+            }
+
+            let x = point.0.floor() as i32 - x_offset;
+            let y = point.1.floor() as i32 - y_offset;
+
+            match brush.brush_type() {
+                BrushType::Pixelate => canvas.pixelate_respecting_pencil_mask(image, &blending_mode, block_size, x, y),
+                _ => canvas.stamp_image_in_stroke(image, &blending_mode, 1.0, 1.0, x, y),
+            }
+        }
+    }
+
     fn get_primary_brush_mut(&mut self) -> &mut Brush {
         self.get_primary_brush();
         &mut self.primary_brush
     }
 
+    fn get_secondary_brush_mut(&mut self) -> &mut Brush {
+        self.get_secondary_brush();
+        &mut self.secondary_brush
+    }
+
+    /// The eraser's brush (colored to match the returned blending mode): it erases
+    /// alpha, or paints the secondary color if `erase_to_secondary` is set
+    fn get_eraser_brush_mut(&mut self, on_base_layer: bool) -> (&mut Brush, BlendingMode) {
+        let EraserSettings { brush_type, radius, strength, erase_to_secondary, .. } = self.mode_toolbar.get_eraser_settings();
+        let secondary_color = self.secondary_color();
+        let transparent = RGBA::new(0.0, 0.0, 0.0, 0.0);
+
+        let (color, blending_mode) = if erase_to_secondary && on_base_layer && secondary_color.alpha() > 0.0 {
+            let alpha = secondary_color.alpha() * strength as f32;
+            (secondary_color.with_alpha(alpha), BlendingMode::Paint)
+        } else {
+            (RGBA::new(0.0, 0.0, 0.0, strength as f32), BlendingMode::Erase)
+        };
+
+        self.eraser_brush.modify(color, transparent, brush_type, radius);
+        (&mut self.eraser_brush, blending_mode)
+    }
+
+    /// The brush of the given retouch tool (only its shape, given
+    /// by its alpha, matters)
+    fn get_retouch_brush_mut(&mut self, tool: RetouchTool) -> &mut Brush {
+        let RetouchSettings { brush_type, radius, .. } = self.mode_toolbar.get_retouch_settings(tool);
+        let opaque = RGBA::new(0.0, 0.0, 0.0, 1.0);
+        let transparent = RGBA::new(0.0, 0.0, 0.0, 0.0);
+
+        self.retouch_brush.modify(opaque, transparent, brush_type, radius);
+        &mut self.retouch_brush
+    }
+
+    fn get_retouch_strength(&self, tool: RetouchTool) -> f64 {
+        self.mode_toolbar.get_retouch_settings(tool).strength
+    }
+
+    /// The brush of the clone stamp (or healing tool, if `heal`);
+    /// like the retouch tools', only its shape matters
+    fn get_clone_brush_mut(&mut self, heal: bool) -> &mut Brush {
+        let CloneSettings { brush_type, radius, .. } = self.mode_toolbar.get_clone_settings(heal);
+        let opaque = RGBA::new(0.0, 0.0, 0.0, 1.0);
+        let transparent = RGBA::new(0.0, 0.0, 0.0, 0.0);
+
+        self.retouch_brush.modify(opaque, transparent, brush_type, radius);
+        &mut self.retouch_brush
+    }
+
+    /// Whether successive clone strokes keep the same offset from their source
+    fn get_clone_aligned(&self, heal: bool) -> bool {
+        self.mode_toolbar.get_clone_settings(heal).aligned
+    }
+
+    fn get_clone_sample_merged(&self, heal: bool) -> bool {
+        self.mode_toolbar.get_clone_settings(heal).sample_merged
+    }
+
+    /// The brush of the dodge (or burn, if `burn`) tool; like
+    /// the retouch tools', only its shape matters
+    fn get_dodge_burn_brush_mut(&mut self, burn: bool) -> &mut Brush {
+        let DodgeBurnSettings { brush_type, radius, .. } = self.mode_toolbar.get_dodge_burn_settings(burn);
+        let opaque = RGBA::new(0.0, 0.0, 0.0, 1.0);
+        let transparent = RGBA::new(0.0, 0.0, 0.0, 0.0);
+
+        self.retouch_brush.modify(opaque, transparent, brush_type, radius);
+        &mut self.retouch_brush
+    }
+
+    fn get_dodge_burn_range(&self, burn: bool) -> ToneRange {
+        self.mode_toolbar.get_dodge_burn_settings(burn).range
+    }
+
+    fn get_dodge_burn_exposure(&self, burn: bool) -> f64 {
+        self.mode_toolbar.get_dodge_burn_settings(burn).exposure
+    }
+
+    fn get_airbrush_tip(&self) -> AirbrushTip {
+        self.mode_toolbar.get_airbrush_settings().tip
+    }
+
+    fn get_airbrush_radius(&self) -> u8 {
+        self.mode_toolbar.get_airbrush_settings().radius
+    }
+
+    /// Number of dots (or stamps) sprayed per tick
+    fn get_airbrush_density(&self) -> usize {
+        self.mode_toolbar.get_airbrush_settings().density
+    }
+
+    fn get_airbrush_flow(&self) -> f64 {
+        self.mode_toolbar.get_airbrush_settings().flow
+    }
+
+    fn get_airbrush_seed(&self) -> usize {
+        self.mode_toolbar.get_airbrush_settings().seed
+    }
+
+    fn get_gradient_shape(&self) -> GradientShape {
+        self.mode_toolbar.get_gradient_settings().shape
+    }
+
+    /// The gradient's stops (as positions from 0 to 1, and colors),
+    /// filling in the primary and secondary colors if they're used
+    fn get_gradient_stops(&self) -> Vec<(f64, RGBA)> {
+        let GradientSettings { colors, custom_stops, .. } = self.mode_toolbar.get_gradient_settings();
+        let primary = self.primary_color();
+
+        match colors {
+            GradientColors::PrimaryToSecondary => vec![(0.0, primary), (1.0, self.secondary_color())],
+            GradientColors::PrimaryToTransparent => vec![
+                (0.0, primary),
+                (1.0, RGBA::new(primary.red(), primary.green(), primary.blue(), 0.0)),
+            ],
+            GradientColors::Custom => custom_stops,
+        }
+    }
+
+    fn get_gradient_dither(&self) -> bool {
+        self.mode_toolbar.get_gradient_settings().dither
+    }
+
     fn get_eyedropper_brush_mut(&mut self) -> &mut Brush {
+        let primary_color = self.primary_color();
+        let secondary_color = self.secondary_color();
+        let sample_size = self.get_eyedropper_sample_size();
+        self.eyedropper_brush.modify(primary_color, secondary_color, BrushType::Square, sample_size as u8);
         &mut self.eyedropper_brush
     }
 
+    fn get_eyedropper_sample_merged(&self) -> bool {
+        self.mode_toolbar.get_eyedropper_settings().0
+    }
+
+    fn get_eyedropper_sample_size(&self) -> usize {
+        self.mode_toolbar.get_eyedropper_settings().1
+    }
+
     fn get_blending_mode(&self) -> BlendingMode {
-        let (_brush_type, blending_mode, _radius) = self.mode_toolbar.get_pencil_settings();
-        blending_mode
+        self.mode_toolbar.get_pencil_settings().blending_mode
+    }
+
+    fn get_brush_dynamics(&self) -> BrushDynamics {
+        self.mode_toolbar.get_pencil_settings().dynamics
+    }
+
+    /// Whether round pencil brushes are anti-aliased (and follow
+    /// the cursor's sub-pixel position), rather than crisp
+    fn get_pencil_antialias(&self) -> bool {
+        self.mode_toolbar.get_pencil_settings().antialias
+    }
+
+    /// Whether one-pixel pencil strokes drop the redundant
+    /// corner pixels of their diagonal steps
+    fn get_pencil_pixel_perfect(&self) -> bool {
+        self.mode_toolbar.get_pencil_settings().pixel_perfect
+    }
+
+    fn get_pencil_stabilizer(&self) -> Stabilizer {
+        self.mode_toolbar.get_pencil_settings().stabilizer
+    }
+
+    fn get_pencil_symmetry(&self) -> Symmetry {
+        self.mode_toolbar.get_pencil_settings().symmetry
+    }
+
+    fn get_eraser_symmetry(&self) -> Symmetry {
+        self.mode_toolbar.get_eraser_settings().symmetry
+    }
+
+    fn get_pixelate_block_size(&self) -> usize {
+        self.mode_toolbar.get_pencil_settings().pixelate_block_size as usize
     }
 
     fn get_magic_wand_tolerance(&self) -> f64 {
-        self.mode_toolbar.get_magic_wand_settings().0
+        self.mode_toolbar.get_magic_wand_settings().tolerance
     }
 
     fn get_fill_tolerance(&self) -> f64 {
-        self.mode_toolbar.get_fill_settings().0
+        self.mode_toolbar.get_fill_settings().tolerance
     }
 
     fn get_magic_wand_relativity(&self) -> bool {
-        self.mode_toolbar.get_magic_wand_settings().1
+        self.mode_toolbar.get_magic_wand_settings().relative_tolerance
+    }
+
+    fn get_magic_wand_contiguous(&self) -> bool {
+        self.mode_toolbar.get_magic_wand_settings().contiguous
+    }
+
+    fn get_magic_wand_diagonal(&self) -> bool {
+        self.mode_toolbar.get_magic_wand_settings().diagonal
     }
 
     fn get_fill_relativity(&self) -> bool {
-        self.mode_toolbar.get_fill_settings().1
+        self.mode_toolbar.get_fill_settings().relative_tolerance
+    }
+
+    fn get_fill_symmetry(&self) -> Symmetry {
+        self.mode_toolbar.get_fill_settings().symmetry
+    }
+
+    fn get_fill_sample_merged(&self) -> bool {
+        self.mode_toolbar.get_fill_settings().sample_merged
+    }
+
+    fn get_fill_whole_selection(&self) -> bool {
+        self.mode_toolbar.get_fill_settings().whole_selection
+    }
+
+    fn get_fill_source(&self) -> FillSource {
+        self.mode_toolbar.get_fill_settings().source
+    }
+
+    fn get_fill_antialias(&self) -> bool {
+        self.mode_toolbar.get_fill_settings().antialias
+    }
+
+    fn get_fill_expansion(&self) -> usize {
+        self.mode_toolbar.get_fill_settings().expansion
+    }
+
+    fn get_fill_contiguous(&self) -> bool {
+        self.mode_toolbar.get_fill_settings().contiguous
+    }
+
+    fn get_fill_diagonal(&self) -> bool {
+        self.mode_toolbar.get_fill_settings().diagonal
     }
 
     fn get_shape_type(&self) -> ShapeType {
@@ -0,0 +1,172 @@
+use crate::config::config_path;
+use crate::image::Image;
+use crate::image::brush::{BrushTip, MaskSource};
+use crate::ui::UiState;
+use crate::ui::io::{image_import_formats, mk_file_filter_list};
+use crate::ui::dialog::{brush_tip_dialog, choose_file_dialog, ok_dialog_str_};
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use gtk::prelude::*;
+use glib_macros::clone;
+
+const BRUSH_DIR_NAME: &str = "brushes";
+
+fn brush_dir() -> PathBuf {
+    config_path(BRUSH_DIR_NAME)
+}
+
+/// The user's custom brush tips, saved as PNGs (the mask
+/// in the alpha channel) in the `brushes` config directory
+pub struct BrushLibrary {
+    tips: Vec<Rc<BrushTip>>,
+    /// Names of `tips` (in the same order), to be used as
+    /// the model of any dropdowns for choosing a tip
+    names: gtk::StringList,
+}
+
+impl BrushLibrary {
+    /// Loads every tip in the brush directory (which
+    /// is fine to not exist yet)
+    pub fn load() -> Self {
+        let mut paths = std::fs::read_dir(brush_dir())
+            .map(|entries| {
+                entries.filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("png"))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or(vec![]);
+
+        paths.sort();
+
+        let tips = paths.iter()
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_str()?;
+                let image = Image::from_path(path).ok()?;
+                Some(Rc::new(BrushTip::from_image(name, &image, MaskSource::Alpha)))
+            })
+            .collect::<Vec<_>>();
+
+        let names = gtk::StringList::new(
+            &tips.iter().map(|tip| tip.name()).collect::<Vec<_>>()
+        );
+
+        BrushLibrary {
+            tips,
+            names,
+        }
+    }
+
+    pub fn names(&self) -> &gtk::StringList {
+        &self.names
+    }
+
+    /// The tip at index `i` (`None` if `i` is out of bounds,
+    /// including `gtk::INVALID_LIST_POSITION`)
+    pub fn tip(&self, i: u32) -> Option<Rc<BrushTip>> {
+        self.tips.get(i as usize).cloned()
+    }
+
+    /// The index of the tip named `name`
+    pub fn index_of(&self, name: &str) -> Option<u32> {
+        self.tips.iter()
+            .position(|tip| tip.name() == name)
+            .map(|i| i as u32)
+    }
+
+    /// Saves `tip` to the brush directory and adds it to the
+    /// library, replacing any existing tip with the same name
+    pub fn add(&mut self, tip: BrushTip) -> Result<(), String> {
+        std::fs::create_dir_all(brush_dir())
+            .map_err(|e| e.to_string())?;
+        tip.to_image().to_file(&brush_dir().join(format!("{}.png", tip.name())))?;
+
+        let tip = Rc::new(tip);
+
+        if let Some(i) = self.tips.iter().position(|t| t.name() == tip.name()) {
+            self.tips[i] = tip.clone();
+            self.names.splice(i as u32, 1, &[tip.name()]);
+        } else {
+            self.tips.push(tip.clone());
+            self.names.append(tip.name());
+        }
+
+        Ok(())
+    }
+}
+
+/// Makes `name` usable as a file name
+fn sanitize_brush_name(name: &str) -> String {
+    let name = name.trim().replace(['/', '\\'], "_");
+
+    if name.is_empty() {
+        String::from("Brush")
+    } else {
+        name
+    }
+}
+
+impl UiState {
+    fn add_brush_tip(ui_p: &Rc<RefCell<UiState>>, image: Image, default_name: &str) {
+        let window = ui_p.borrow().window.clone();
+
+        brush_tip_dialog(&window, default_name, clone!(@strong ui_p => move |name, mask_source| {
+            let tip = BrushTip::from_image(&sanitize_brush_name(&name), &image, mask_source);
+            let res = ui_p.borrow().toolbar_p.borrow().add_brush_tip(tip);
+
+            if let Err(mesg) = res {
+                ok_dialog_str_(
+                    ui_p.borrow().window(),
+                    "Brush Error",
+                    format!("Error saving brush: {}", mesg).as_str()
+                );
+            }
+        }));
+    }
+
+    /// Prompt user for an image file, then add it to
+    /// the brush library as a custom brush tip
+    pub fn import_brush(ui_p: Rc<RefCell<UiState>>) {
+        let valid_filetypes = mk_file_filter_list(image_import_formats());
+
+        choose_file_dialog(&ui_p.borrow().window, "Choose a brush tip image",
+                    "Import", &valid_filetypes, false,
+                    clone!(@strong ui_p => move |res| {
+            if let Ok(res) = res {
+                let path = res.path().unwrap();
+                let name = path.file_stem().and_then(|os| os.to_str()).unwrap_or("Brush");
+
+                match Image::from_path(&path) {
+                    Ok(image) => UiState::add_brush_tip(&ui_p, image, name),
+                    Err(mesg) => {
+                        ok_dialog_str_(
+                            ui_p.borrow().window(),
+                            "Import Error",
+                            format!("Error during import: {}", mesg).as_str()
+                        );
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Add the selected part of the active layer (cropped to the
+    /// selection's bounds) to the brush library
+    pub fn brush_from_selection(ui_p: Rc<RefCell<UiState>>) {
+        let Some(canvas_p) = ui_p.borrow().active_canvas_p().cloned() else {
+            return;
+        };
+
+        let Some(image) = canvas_p.borrow().selected_image() else {
+            ok_dialog_str_(
+                ui_p.borrow().window(),
+                "Make a Selection First",
+                "Use a selection tool to select the region to use as a brush tip."
+            );
+            return;
+        };
+
+        UiState::add_brush_tip(&ui_p, image, "Brush");
+    }
+}
@@ -0,0 +1,397 @@
+use crate::config::{read_config_bytes, write_config_file};
+use crate::image::blend::BlendingMode;
+use crate::image::brush::{BrushDynamics, BrushType, VelocityDynamics};
+use crate::ui::UiState;
+use super::mode::Stabilizer;
+use crate::ui::io::mk_file_filter_list;
+use crate::ui::dialog::{brush_preset_dialog, choose_file_dialog, ok_dialog_str_};
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::path::Path;
+use gtk::prelude::*;
+use glib_macros::clone;
+use serde_derive::{Serialize, Deserialize};
+
+const PRESETS_FILE: &str = "brush-presets";
+
+pub fn brush_preset_formats() -> Vec<Vec<&'static str>> {
+    vec![
+        vec!["rsbrushes"],
+    ]
+}
+
+/// A named snapshot of every pencil setting
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BrushPreset {
+    pub name: String,
+    pub brush_type: BrushType,
+    pub blending_mode: BlendingMode,
+    pub radius: u8,
+    pub pixelate_block_size: u8,
+    /// Name of the custom brush tip (in the brush library)
+    pub tip: Option<String>,
+    pub dynamics: BrushDynamics,
+    /// (hard edges if missing, like the pencil's default)
+    #[serde(default)]
+    pub antialias: bool,
+    /// (missing from presets saved before it existed)
+    #[serde(default)]
+    pub pixel_perfect: bool,
+    /// (off if missing, like `pixel_perfect`)
+    #[serde(default)]
+    pub stabilizer: Stabilizer,
+}
+
+fn default_presets() -> Vec<BrushPreset> {
+    vec![
+        BrushPreset {
+            name: String::from("Inking"),
+            brush_type: BrushType::Round,
+            blending_mode: BlendingMode::Paint,
+            radius: 6,
+            pixelate_block_size: 8,
+            tip: None,
+            dynamics: BrushDynamics {
+                velocity: VelocityDynamics {
+                    fast_size: 0.4,
+                    fade_in: 12.0,
+                    fade_out: 24.0,
+                    ..VelocityDynamics::default()
+                },
+                ..BrushDynamics::default()
+            },
+            antialias: true,
+            pixel_perfect: false,
+            stabilizer: Stabilizer::default(),
+        },
+        BrushPreset {
+            name: String::from("Sketching"),
+            brush_type: BrushType::Round,
+            blending_mode: BlendingMode::Paint,
+            radius: 3,
+            pixelate_block_size: 8,
+            tip: None,
+            dynamics: BrushDynamics {
+                flow: 0.4,
+                opacity: 0.6,
+                size_jitter: 0.2,
+                ..BrushDynamics::default()
+            },
+            antialias: true,
+            pixel_perfect: false,
+            stabilizer: Stabilizer::default(),
+        },
+        BrushPreset {
+            name: String::from("Pixel"),
+            brush_type: BrushType::Square,
+            blending_mode: BlendingMode::Overwrite,
+            radius: 1,
+            pixelate_block_size: 8,
+            tip: None,
+            dynamics: BrushDynamics::default(),
+            antialias: false,
+            pixel_perfect: true,
+            stabilizer: Stabilizer::default(),
+        },
+    ]
+}
+
+fn read_presets(path: &Path) -> Result<Vec<BrushPreset>, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    serde_cbor::from_slice(&bytes).map_err(|e| e.to_string())
+}
+
+/// The user's named brush presets (persisted in the config directory),
+/// along with the dropdown that selects them
+pub struct BrushPresets {
+    presets: Vec<BrushPreset>,
+    /// Names of `presets` (in the same order): the dropdown's model
+    names: gtk::StringList,
+    dropdown: gtk::DropDown,
+    /// Applies a preset to the pencil's settings
+    on_select: Option<Rc<dyn Fn(&BrushPreset)>>,
+    /// Set while `names` is being modified, so the dropdown's
+    /// resulting selection changes aren't mistaken for the user's
+    updating: bool,
+}
+
+impl BrushPresets {
+    pub fn new_p() -> Rc<RefCell<Self>> {
+        let presets = read_config_bytes(PRESETS_FILE)
+            .and_then(|bytes| serde_cbor::from_slice::<Vec<BrushPreset>>(&bytes).ok())
+            .unwrap_or_else(default_presets);
+
+        let names = gtk::StringList::new(
+            &presets.iter().map(|preset| preset.name.as_str()).collect::<Vec<_>>()
+        );
+
+        let dropdown = gtk::DropDown::builder()
+            .model(&names)
+            .valign(gtk::Align::Center)
+            .selected(gtk::INVALID_LIST_POSITION)
+            .build();
+
+        let presets_p = Rc::new(RefCell::new(BrushPresets {
+            presets,
+            names,
+            dropdown: dropdown.clone(),
+            on_select: None,
+            updating: false,
+        }));
+
+        // weak, since the presets own the dropdown
+        let presets_weak = Rc::downgrade(&presets_p);
+        dropdown.connect_selected_notify(move |dropdown| {
+            if let Some(presets_p) = presets_weak.upgrade() {
+                Self::apply(&presets_p, dropdown.selected());
+            }
+        });
+
+        presets_p
+    }
+
+    pub fn widget(&self) -> &gtk::DropDown {
+        &self.dropdown
+    }
+
+    pub fn set_on_select(&mut self, f: Rc<dyn Fn(&BrushPreset)>) {
+        self.on_select = Some(f);
+    }
+
+    /// The name of the selected preset
+    pub fn selected_name(&self) -> Option<String> {
+        self.presets.get(self.dropdown.selected() as usize)
+            .map(|preset| preset.name.clone())
+    }
+
+    fn apply(presets_p: &Rc<RefCell<Self>>, i: u32) {
+        let (preset, on_select) = {
+            let presets = presets_p.borrow();
+            if presets.updating {
+                return;
+            }
+            (presets.presets.get(i as usize).cloned(), presets.on_select.clone())
+        };
+
+        if let (Some(preset), Some(on_select)) = (preset, on_select) {
+            on_select(&preset);
+        }
+    }
+
+    /// Selects (and applies) the `i`th preset, if it exists
+    pub fn select(presets_p: &Rc<RefCell<Self>>, i: u32) {
+        let (dropdown, len) = {
+            let presets = presets_p.borrow();
+            (presets.dropdown.clone(), presets.presets.len())
+        };
+
+        if i as usize >= len {
+            return;
+        }
+
+        if dropdown.selected() == i {
+            // re-apply (the settings may have been changed since)
+            Self::apply(presets_p, i);
+        } else {
+            dropdown.set_selected(i);
+        }
+    }
+
+    /// Selects the preset after (or before, if `!forward`) the selected one
+    pub fn cycle(presets_p: &Rc<RefCell<Self>>, forward: bool) {
+        let (selected, len) = {
+            let presets = presets_p.borrow();
+            (presets.dropdown.selected(), presets.presets.len() as u32)
+        };
+
+        if len == 0 {
+            return;
+        }
+
+        let next = match (selected == gtk::INVALID_LIST_POSITION, forward) {
+            (true, true) => 0,
+            (true, false) => len - 1,
+            (false, true) => (selected + 1) % len,
+            (false, false) => (selected + len - 1) % len,
+        };
+
+        Self::select(presets_p, next);
+    }
+
+    /// Adds `preset` (replacing any preset with the same name), saves
+    /// the presets, then selects `preset`
+    pub fn add(presets_p: &Rc<RefCell<Self>>, preset: BrushPreset) -> Result<(), String> {
+        let index = Self::insert(presets_p, preset);
+        let res = presets_p.borrow().save();
+        presets_p.borrow().dropdown.set_selected(index);
+        res
+    }
+
+    /// Adds `preset` (replacing any preset with the same name),
+    /// without saving, returning its index
+    fn insert(presets_p: &Rc<RefCell<Self>>, preset: BrushPreset) -> u32 {
+        let name = preset.name.clone();
+        let (names, index, replaced) = {
+            let mut presets = presets_p.borrow_mut();
+            presets.updating = true;
+
+            let (index, replaced) = match presets.presets.iter().position(|p| p.name == name) {
+                Some(i) => {
+                    presets.presets[i] = preset;
+                    (i, true)
+                },
+                None => {
+                    presets.presets.push(preset);
+                    (presets.presets.len() - 1, false)
+                },
+            };
+
+            (presets.names.clone(), index as u32, replaced)
+        };
+
+        if replaced {
+            names.splice(index, 1, &[name.as_str()]);
+        } else {
+            names.append(&name);
+        }
+
+        presets_p.borrow_mut().updating = false;
+        index
+    }
+
+    /// Removes (and saves the removal of) the selected preset
+    pub fn remove_selected(presets_p: &Rc<RefCell<Self>>) -> Result<(), String> {
+        let (names, index) = {
+            let mut presets = presets_p.borrow_mut();
+            let index = presets.dropdown.selected();
+
+            if index as usize >= presets.presets.len() {
+                return Ok(());
+            }
+
+            presets.updating = true;
+            presets.presets.remove(index as usize);
+            (presets.names.clone(), index)
+        };
+
+        names.remove(index);
+
+        let dropdown = {
+            let mut presets = presets_p.borrow_mut();
+            presets.updating = false;
+            presets.dropdown.clone()
+        };
+
+        dropdown.set_selected(gtk::INVALID_LIST_POSITION);
+        presets_p.borrow().save()
+    }
+
+    /// Adds every preset from the given file, replacing those with the same names
+    pub fn import(presets_p: &Rc<RefCell<Self>>, path: &Path) -> Result<(), String> {
+        for preset in read_presets(path)?.into_iter() {
+            Self::insert(presets_p, preset);
+        }
+
+        presets_p.borrow().save()
+    }
+
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let bytes = serde_cbor::to_vec(&self.presets).map_err(|e| e.to_string())?;
+        std::fs::write(path, bytes).map_err(|e| e.to_string())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let bytes = serde_cbor::to_vec(&self.presets).map_err(|e| e.to_string())?;
+        write_config_file(PRESETS_FILE, bytes)
+    }
+}
+
+impl UiState {
+    fn brush_preset_error(ui_p: &Rc<RefCell<UiState>>, res: Result<(), String>) {
+        if let Err(mesg) = res {
+            ok_dialog_str_(
+                ui_p.borrow().window(),
+                "Brush Preset Error",
+                format!("Error saving brush presets: {}", mesg).as_str()
+            );
+        }
+    }
+
+    /// Prompt user for a name, then save the pencil's current settings as a preset
+    pub fn save_brush_preset(ui_p: Rc<RefCell<UiState>>) {
+        let default_name = ui_p.borrow().toolbar_p.borrow().brush_presets_p.borrow()
+            .selected_name()
+            .unwrap_or(String::from("Preset"));
+        let window = ui_p.borrow().window.clone();
+
+        brush_preset_dialog(&window, &default_name, clone!(@strong ui_p => move |name| {
+            let name = String::from(name.trim());
+            if name.is_empty() {
+                return;
+            }
+
+            let (preset, presets_p) = {
+                let ui = ui_p.borrow();
+                let toolbar = ui.toolbar_p.borrow();
+                (toolbar.current_brush_preset(name), toolbar.brush_presets_p.clone())
+            };
+
+            UiState::brush_preset_error(&ui_p, BrushPresets::add(&presets_p, preset));
+        }));
+    }
+
+    pub fn delete_brush_preset(ui_p: Rc<RefCell<UiState>>) {
+        let presets_p = ui_p.borrow().toolbar_p.borrow().brush_presets_p.clone();
+        UiState::brush_preset_error(&ui_p, BrushPresets::remove_selected(&presets_p));
+    }
+
+    pub fn select_brush_preset(ui_p: Rc<RefCell<UiState>>, i: u32) {
+        let presets_p = ui_p.borrow().toolbar_p.borrow().brush_presets_p.clone();
+        BrushPresets::select(&presets_p, i);
+    }
+
+    pub fn cycle_brush_preset(ui_p: Rc<RefCell<UiState>>, forward: bool) {
+        let presets_p = ui_p.borrow().toolbar_p.borrow().brush_presets_p.clone();
+        BrushPresets::cycle(&presets_p, forward);
+    }
+
+    pub fn import_brush_presets(ui_p: Rc<RefCell<UiState>>) {
+        choose_file_dialog(&ui_p.borrow().window, "Choose brush presets to import",
+            "Import", &mk_file_filter_list(brush_preset_formats()), false,
+            clone!(@strong ui_p => move |res| {
+                if let Ok(gfile) = res {
+                    let presets_p = ui_p.borrow().toolbar_p.borrow().brush_presets_p.clone();
+
+                    if let Err(mesg) = BrushPresets::import(&presets_p, &gfile.path().unwrap()) {
+                        ok_dialog_str_(
+                            ui_p.borrow().window(),
+                            "Brush Preset Import Error",
+                            format!("Error during import: {}", mesg).as_str()
+                        );
+                    }
+                }
+            })
+        );
+    }
+
+    pub fn export_brush_presets(ui_p: Rc<RefCell<UiState>>) {
+        choose_file_dialog(&ui_p.borrow().window, "Export Brush Presets",
+            "Export", &mk_file_filter_list(brush_preset_formats()), true,
+            clone!(@strong ui_p => move |res| {
+                if let Ok(gfile) = res {
+                    let res = ui_p.borrow().toolbar_p.borrow().brush_presets_p.borrow()
+                        .export(&gfile.path().unwrap());
+
+                    if let Err(mesg) = res {
+                        ok_dialog_str_(
+                            ui_p.borrow().window(),
+                            "Brush Preset Export Error",
+                            format!("Error during export: {}", mesg).as_str()
+                        );
+                    }
+                }
+            })
+        );
+    }
+}
@@ -2,25 +2,39 @@ mod mode_toolbar;
 mod rectangle_select;
 mod cursor;
 mod pencil;
+mod airbrush;
+mod line;
+mod pen;
 mod eyedropper;
 mod magic_wand;
 mod fill;
+mod gradient;
 mod free_transform;
 mod shape;
 mod text;
+mod symmetry;
 
 use crate::ui::{canvas::Canvas, toolbar::Toolbar};
-pub use mode_toolbar::ModeToolbar;
+pub use mode_toolbar::{CloneSettings, DodgeBurnSettings, EraserSettings, GradientSettings, ModeToolbar, PencilSettings, RetouchSettings};
 
 pub use cursor::CursorState;
 use magic_wand::MagicWandState;
 use pencil::PencilState;
+pub use pencil::{RetouchTool, Stabilizer};
+use airbrush::AirbrushState;
+use line::LineState;
+use pen::PenState;
+pub use airbrush::AirbrushTip;
 use fill::FillState;
+pub use fill::FillSource;
+use gradient::GradientState;
+pub use gradient::GradientColors;
 use self::eyedropper::EyedropperState;
 pub use self::rectangle_select::{RectangleSelectState, RectangleSelectMode};
 pub use free_transform::{FreeTransformState, TransformationSelection};
 use shape::ShapeState;
 use text::TextState;
+pub use symmetry::Symmetry;
 
 use gtk::cairo::Context;
 use gtk::gdk::ModifierType;
@@ -29,10 +43,22 @@ use gtk::gdk::ModifierType;
 pub enum MouseMode {
     Cursor(CursorState),
     Pencil(PencilState),
+    Eraser(PencilState),
+    Smudge(PencilState),
+    Blur(PencilState),
+    Sharpen(PencilState),
+    CloneStamp(PencilState),
+    Healing(PencilState),
+    Dodge(PencilState),
+    Burn(PencilState),
+    Airbrush(AirbrushState),
+    Line(LineState),
+    Pen(PenState),
     RectangleSelect(RectangleSelectState),
     Eyedropper(EyedropperState),
     MagicWand(MagicWandState),
     Fill(FillState),
+    Gradient(GradientState),
     FreeTransform(FreeTransformState),
     Shape(ShapeState),
     Text(TextState)
@@ -42,10 +68,22 @@ pub enum MouseMode {
 pub enum MouseModeVariant {
     Cursor,
     Pencil,
+    Eraser,
+    Smudge,
+    Blur,
+    Sharpen,
+    CloneStamp,
+    Healing,
+    Dodge,
+    Burn,
+    Airbrush,
+    Line,
+    Pen,
     RectangleSelect,
     Eyedropper,
     MagicWand,
     Fill,
+    Gradient,
     FreeTransform,
     Shape,
     Text,
@@ -64,6 +102,8 @@ trait MouseModeState {
 
     fn handle_motion(&mut self, _mod_keys: &ModifierType, _canvas: &mut Canvas, _toolbar: &mut Toolbar) {}
     fn handle_mod_key_update(&mut self, _mod_keys: &ModifierType, _canvas: &mut Canvas, _toolbar: &mut Toolbar) {}
+    /// Called periodically while a drag is held (only for modes that `tick_while_dragging`)
+    fn handle_tick(&mut self, _mod_keys: &ModifierType, _canvas: &mut Canvas, _toolbar: &mut Toolbar) {}
     fn draw(&self, _canvas: &Canvas, _cr: &Context, _toolbar: &mut Toolbar) {}
 
     /// Hack to transfer from one MouseMode to another
@@ -97,6 +137,94 @@ impl MouseMode {
         MouseMode::Pencil(PencilState::default_no_canvas())
     }
 
+    pub fn eraser(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Eraser(PencilState::eraser(canvas))
+    }
+
+    pub fn eraser_default() -> MouseMode {
+        MouseMode::Eraser(PencilState::eraser_no_canvas())
+    }
+
+    pub fn smudge(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Smudge(PencilState::retouch(canvas, RetouchTool::Smudge))
+    }
+
+    pub fn smudge_default() -> MouseMode {
+        MouseMode::Smudge(PencilState::retouch_no_canvas(RetouchTool::Smudge))
+    }
+
+    pub fn blur(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Blur(PencilState::retouch(canvas, RetouchTool::Blur))
+    }
+
+    pub fn blur_default() -> MouseMode {
+        MouseMode::Blur(PencilState::retouch_no_canvas(RetouchTool::Blur))
+    }
+
+    pub fn sharpen(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Sharpen(PencilState::retouch(canvas, RetouchTool::Sharpen))
+    }
+
+    pub fn sharpen_default() -> MouseMode {
+        MouseMode::Sharpen(PencilState::retouch_no_canvas(RetouchTool::Sharpen))
+    }
+
+    pub fn clone_stamp(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::CloneStamp(PencilState::clone_stamp(canvas, false))
+    }
+
+    pub fn clone_stamp_default() -> MouseMode {
+        MouseMode::CloneStamp(PencilState::clone_stamp_no_canvas(false))
+    }
+
+    pub fn healing(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Healing(PencilState::clone_stamp(canvas, true))
+    }
+
+    pub fn healing_default() -> MouseMode {
+        MouseMode::Healing(PencilState::clone_stamp_no_canvas(true))
+    }
+
+    pub fn dodge(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Dodge(PencilState::dodge_burn(canvas, false))
+    }
+
+    pub fn dodge_default() -> MouseMode {
+        MouseMode::Dodge(PencilState::dodge_burn_no_canvas(false))
+    }
+
+    pub fn burn(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Burn(PencilState::dodge_burn(canvas, true))
+    }
+
+    pub fn burn_default() -> MouseMode {
+        MouseMode::Burn(PencilState::dodge_burn_no_canvas(true))
+    }
+
+    pub fn airbrush(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Airbrush(AirbrushState::default(canvas))
+    }
+
+    pub fn airbrush_default() -> MouseMode {
+        MouseMode::Airbrush(AirbrushState::default_no_canvas())
+    }
+
+    pub fn line(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Line(LineState::default(canvas))
+    }
+
+    pub fn line_default() -> MouseMode {
+        MouseMode::Line(LineState::default_no_canvas())
+    }
+
+    pub fn pen(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Pen(PenState::default(canvas))
+    }
+
+    pub fn pen_default() -> MouseMode {
+        MouseMode::Pen(PenState::default_no_canvas())
+    }
+
     pub fn rectangle_select(canvas: &mut Canvas) -> MouseMode {
         MouseMode::RectangleSelect(RectangleSelectState::default(canvas))
     }
@@ -129,6 +257,14 @@ impl MouseMode {
         MouseMode::Fill(FillState::default_no_canvas())
     }
 
+    pub fn gradient(canvas: &mut Canvas) -> MouseMode {
+        MouseMode::Gradient(GradientState::default(canvas))
+    }
+
+    pub fn gradient_default() -> MouseMode {
+        MouseMode::Gradient(GradientState::default_no_canvas())
+    }
+
     pub fn free_transform(canvas: &mut Canvas) -> MouseMode {
         MouseMode::FreeTransform(FreeTransformState::default(canvas))
     }
@@ -157,10 +293,22 @@ impl MouseMode {
         match self {
             MouseMode::Cursor(ref mut s) => s,
             MouseMode::Pencil(ref mut s) => s,
+            MouseMode::Eraser(ref mut s) => s,
+            MouseMode::Smudge(ref mut s) => s,
+            MouseMode::Blur(ref mut s) => s,
+            MouseMode::Sharpen(ref mut s) => s,
+            MouseMode::CloneStamp(ref mut s) => s,
+            MouseMode::Healing(ref mut s) => s,
+            MouseMode::Dodge(ref mut s) => s,
+            MouseMode::Burn(ref mut s) => s,
+            MouseMode::Airbrush(ref mut s) => s,
+            MouseMode::Line(ref mut s) => s,
+            MouseMode::Pen(ref mut s) => s,
             MouseMode::RectangleSelect(ref mut s) => s,
             MouseMode::Eyedropper(ref mut s) => s,
             MouseMode::MagicWand(ref mut s) => s,
             MouseMode::Fill(ref mut s) => s,
+            MouseMode::Gradient(ref mut s) => s,
             MouseMode::FreeTransform(ref mut s) => s,
             MouseMode::Shape(ref mut s) => s,
             MouseMode::Text(ref mut s) => s,
@@ -171,10 +319,22 @@ impl MouseMode {
         match self {
             MouseMode::Cursor(ref s) => s,
             MouseMode::Pencil(ref s) => s,
+            MouseMode::Eraser(ref s) => s,
+            MouseMode::Smudge(ref s) => s,
+            MouseMode::Blur(ref s) => s,
+            MouseMode::Sharpen(ref s) => s,
+            MouseMode::CloneStamp(ref s) => s,
+            MouseMode::Healing(ref s) => s,
+            MouseMode::Dodge(ref s) => s,
+            MouseMode::Burn(ref s) => s,
+            MouseMode::Airbrush(ref s) => s,
+            MouseMode::Line(ref s) => s,
+            MouseMode::Pen(ref s) => s,
             MouseMode::RectangleSelect(ref s) => s,
             MouseMode::Eyedropper(ref s) => s,
             MouseMode::MagicWand(ref s) => s,
             MouseMode::Fill(ref s) => s,
+            MouseMode::Gradient(ref s) => s,
             MouseMode::FreeTransform(ref s) => s,
             MouseMode::Shape(ref s) => s,
             MouseMode::Text(ref s) => s,
@@ -213,6 +373,10 @@ impl MouseMode {
         self.get_state().handle_mod_key_update(mod_keys, canvas, toolbar);
     }
 
+    pub fn handle_tick(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
+        self.get_state().handle_tick(mod_keys, canvas, toolbar);
+    }
+
     pub fn draw(&self, canvas: &Canvas, context: &Context, toolbar: &mut Toolbar) {
         self.get_state_immutable().draw(canvas, context, toolbar);
     }
@@ -221,10 +385,22 @@ impl MouseMode {
         match self {
             MouseMode::Cursor(_) => MouseModeVariant::Cursor,
             MouseMode::Pencil(_) => MouseModeVariant::Pencil,
+            MouseMode::Eraser(_) => MouseModeVariant::Eraser,
+            MouseMode::Smudge(_) => MouseModeVariant::Smudge,
+            MouseMode::Blur(_) => MouseModeVariant::Blur,
+            MouseMode::Sharpen(_) => MouseModeVariant::Sharpen,
+            MouseMode::CloneStamp(_) => MouseModeVariant::CloneStamp,
+            MouseMode::Healing(_) => MouseModeVariant::Healing,
+            MouseMode::Dodge(_) => MouseModeVariant::Dodge,
+            MouseMode::Burn(_) => MouseModeVariant::Burn,
+            MouseMode::Airbrush(_) => MouseModeVariant::Airbrush,
+            MouseMode::Line(_) => MouseModeVariant::Line,
+            MouseMode::Pen(_) => MouseModeVariant::Pen,
             MouseMode::RectangleSelect(_) => MouseModeVariant::RectangleSelect,
             MouseMode::Eyedropper(_) => MouseModeVariant::Eyedropper,
             MouseMode::MagicWand(_) => MouseModeVariant::MagicWand,
             MouseMode::Fill(_) => MouseModeVariant::Fill,
+            MouseMode::Gradient(_) => MouseModeVariant::Gradient,
             MouseMode::FreeTransform(_) => MouseModeVariant::FreeTransform,
             MouseMode::Shape(_) => MouseModeVariant::Shape,
             MouseMode::Text(_) => MouseModeVariant::Text,
@@ -235,24 +411,53 @@ impl MouseMode {
         match self {
             MouseMode::Cursor(_) => false,
             MouseMode::Pencil(_) => true,
+            MouseMode::Eraser(_) => true,
+            MouseMode::Smudge(_) => true,
+            MouseMode::Blur(_) => true,
+            MouseMode::Sharpen(_) => true,
+            MouseMode::CloneStamp(_) => true,
+            MouseMode::Healing(_) => true,
+            MouseMode::Dodge(_) => true,
+            MouseMode::Burn(_) => true,
+            MouseMode::Airbrush(_) => true,
+            MouseMode::Line(_) => true,
+            MouseMode::Pen(_) => false,
             MouseMode::RectangleSelect(_) => false,
             MouseMode::Eyedropper(_) => false,
             MouseMode::MagicWand(_) => false,
             MouseMode::Fill(_) => true,
+            MouseMode::Gradient(_) => true,
             MouseMode::FreeTransform(_) => true,
             MouseMode::Shape(_) => true,
             MouseMode::Text(_) => true,
         }
     }
 
+    /// Whether the mode's `handle_tick` should be called while a drag is held
+    pub fn tick_while_dragging(&self) -> bool {
+        matches!(self, MouseMode::Airbrush(_))
+    }
+
     pub fn from_variant(variant: MouseModeVariant, canvas: &mut Canvas) -> Self {
         match variant {
             MouseModeVariant::Cursor => Self::cursor(canvas),
             MouseModeVariant::Pencil => Self::pencil(canvas),
+            MouseModeVariant::Eraser => Self::eraser(canvas),
+            MouseModeVariant::Smudge => Self::smudge(canvas),
+            MouseModeVariant::Blur => Self::blur(canvas),
+            MouseModeVariant::Sharpen => Self::sharpen(canvas),
+            MouseModeVariant::CloneStamp => Self::clone_stamp(canvas),
+            MouseModeVariant::Healing => Self::healing(canvas),
+            MouseModeVariant::Dodge => Self::dodge(canvas),
+            MouseModeVariant::Burn => Self::burn(canvas),
+            MouseModeVariant::Airbrush => Self::airbrush(canvas),
+            MouseModeVariant::Line => Self::line(canvas),
+            MouseModeVariant::Pen => Self::pen(canvas),
             MouseModeVariant::RectangleSelect => Self::rectangle_select(canvas),
             MouseModeVariant::Eyedropper => Self::eyedropper(canvas),
             MouseModeVariant::MagicWand => Self::magic_wand(canvas),
             MouseModeVariant::Fill => Self::fill(canvas),
+            MouseModeVariant::Gradient => Self::gradient(canvas),
             MouseModeVariant::FreeTransform => Self::free_transform(canvas),
             MouseModeVariant::Shape => Self::shape(canvas),
             MouseModeVariant::Text => Self::text(canvas),
@@ -0,0 +1,167 @@
+use super::{Canvas, Toolbar};
+use crate::image::brush::{BrushImage, StrokeRng};
+use crate::image::undo::action::ActionName;
+use crate::image::{ImageLike, Pixel};
+
+use gtk::gdk::{ModifierType, RGBA};
+use gtk::cairo::Context;
+use std::f64::consts::TAU;
+
+/// What the airbrush sprays
+#[derive(Clone, Copy, PartialEq)]
+pub enum AirbrushTip {
+    /// Single pixels
+    Dots,
+    /// Small stamps that fade out towards their edges
+    Soft,
+}
+
+impl AirbrushTip {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Dots", AirbrushTip::Dots),
+            ("Soft", AirbrushTip::Soft),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct AirbrushState {
+    /// The color being sprayed (while a mouse button is held)
+    spraying: Option<RGBA>,
+    /// Where the spray lands (reseeded each press)
+    rng: StrokeRng,
+}
+
+impl AirbrushState {
+    pub fn default(_canvas: &Canvas) -> AirbrushState {
+        Self::default_no_canvas()
+    }
+
+    pub fn default_no_canvas() -> AirbrushState {
+        AirbrushState {
+            spraying: None,
+            rng: StrokeRng::new(0),
+        }
+    }
+
+    /// Deposits one burst of spray around the cursor
+    fn spray(&mut self, canvas: &mut Canvas, toolbar: &Toolbar, color: RGBA) {
+        let tip = toolbar.get_airbrush_tip();
+        let radius = toolbar.get_airbrush_radius() as f64;
+        let density = toolbar.get_airbrush_density();
+        let flow = toolbar.get_airbrush_flow();
+        let blending_mode = toolbar.get_blending_mode();
+
+        let stamp = match tip {
+            AirbrushTip::Dots => BrushImage::from_pixels_options(vec![vec![Some(Pixel::from_rgba_struct(color))]]),
+            AirbrushTip::Soft => soft_stamp(color, (radius / 4.0).max(1.0)),
+        };
+        let (x_offset, y_offset) = ((stamp.width() as i32 - 1) / 2, (stamp.height() as i32 - 1) / 2);
+        let (cx, cy) = canvas.cursor_pos_pix_f();
+
+        for _ in 0..density {
+            // uniformly distributed over the spray's disc
+            let theta = self.rng.next_f64() * TAU;
+            let dist = self.rng.next_f64().sqrt() * radius;
+            let x = (cx + theta.cos() * dist).floor() as i32;
+            let y = (cy + theta.sin() * dist).floor() as i32;
+
+            canvas.stamp_image_in_stroke(&stamp, &blending_mode, flow, 1.0, x - x_offset, y - y_offset);
+        }
+
+        canvas.update();
+    }
+
+    fn drag_start(&mut self, canvas: &mut Canvas, toolbar: &mut Toolbar, color: RGBA) {
+        // seed with the press's position, so identical presses spray identically
+        let seed = toolbar.get_airbrush_seed() as u64;
+        let (x, y) = canvas.cursor_pos_pix_i();
+        self.rng = StrokeRng::new((seed << 48) ^ ((x as u32 as u64) << 24) ^ y as u32 as u64);
+
+        self.spraying = Some(color);
+        self.spray(canvas, toolbar, color);
+    }
+
+    fn drag_end(&mut self, canvas: &mut Canvas) {
+        if self.spraying.take().is_some() {
+            canvas.commit_changes(ActionName::Airbrush);
+            canvas.clear_pencil_mask();
+        }
+    }
+}
+
+/// A round stamp of the given radius, whose alpha falls off
+/// quadratically from the center
+fn soft_stamp(color: RGBA, radius: f64) -> BrushImage {
+    let n = (radius.ceil() as usize) * 2 + 1;
+    let center = (n / 2) as f64;
+
+    let pixels = (0..n).map(|i| {
+        (0..n).map(|j| {
+            let d = ((i as f64 - center).powi(2) + (j as f64 - center).powi(2)).sqrt() / (radius + 0.5);
+
+            if d >= 1.0 {
+                None
+            } else {
+                let alpha = color.alpha() as f64 * (1.0 - d) * (1.0 - d);
+                Some(Pixel::from_rgba_struct(RGBA::new(color.red(), color.green(), color.blue(), alpha as f32)))
+            }
+        }).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    BrushImage::from_pixels_options(pixels)
+}
+
+impl super::MouseModeState for AirbrushState {
+    fn handle_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
+        let color = toolbar.primary_color();
+        self.drag_start(canvas, toolbar, color);
+    }
+
+    fn handle_right_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
+        let color = toolbar.secondary_color();
+        self.drag_start(canvas, toolbar, color);
+    }
+
+    fn handle_drag_update(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        // the spray follows the cursor, but only lands on each tick
+        canvas.update();
+    }
+
+    fn handle_right_drag_update(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        canvas.update();
+    }
+
+    fn handle_drag_end(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        self.drag_end(canvas);
+    }
+
+    fn handle_right_drag_end(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        self.drag_end(canvas);
+    }
+
+    fn handle_tick(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
+        if let Some(color) = self.spraying {
+            self.spray(canvas, toolbar, color);
+        }
+    }
+
+    fn handle_motion(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        canvas.update();
+    }
+
+    fn draw(&self, canvas: &Canvas, cr: &Context, toolbar: &mut Toolbar) {
+        let (x, y) = canvas.cursor_pos_pix_f();
+        let radius = toolbar.get_airbrush_radius() as f64;
+
+        let _ = cr.save();
+        {
+            cr.set_line_width(1.0 / *canvas.zoom());
+            cr.arc(x, y, radius, 0.0, TAU);
+            cr.set_source_rgb(0.0, 1.0, 0.0);
+            let _ = cr.stroke();
+        }
+        let _ = cr.restore();
+    }
+}
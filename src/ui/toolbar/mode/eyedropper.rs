@@ -1,6 +1,7 @@
 use super::{Canvas, Toolbar};
-use crate::image::ImageLike;
+use crate::image::{ImageLike, Pixel};
 use crate::image::TrackedLayeredImage;
+use crate::image::filter::average_pixels;
 
 use gtk::gdk::ModifierType;
 use gtk::cairo::Context;
@@ -20,10 +21,49 @@ impl EyedropperState {
     }
 }
 
+/// Averages the `sample_size` by `sample_size` square centered on
+/// the cursor (from the active layer, or the visible composite if
+/// `sample_merged`); `None` if the cursor isn't on the image
+fn sample_color(canvas: &Canvas, sample_merged: bool, sample_size: usize) -> Option<Pixel> {
+    let (x, y) = canvas.cursor_pos_pix_i();
+    let image = canvas.layered_image();
+
+    let try_sample_at = |r: i32, c: i32| {
+        if sample_merged {
+            image.try_composite_pix_at(r, c)
+        } else {
+            image.try_pix_at(r, c).cloned()
+        }
+    };
+
+    // the center pixel must be on the image; the rest are clipped
+    try_sample_at(y, x)?;
+
+    let offset = (sample_size as i32 - 1) / 2;
+    let pixels = (y - offset..y - offset + sample_size as i32)
+        .flat_map(|r| (x - offset..x - offset + sample_size as i32).map(move |c| (r, c)))
+        .filter_map(|(r, c)| try_sample_at(r, c))
+        .collect::<Vec<_>>();
+
+    Some(average_pixels(pixels.iter()))
+}
+
+impl EyedropperState {
+    fn sample(canvas: &Canvas, toolbar: &Toolbar) -> Option<Pixel> {
+        let pix = sample_color(
+            canvas,
+            toolbar.get_eyedropper_sample_merged(),
+            toolbar.get_eyedropper_sample_size(),
+        )?;
+
+        canvas.show_sampled_color(&pix);
+        Some(pix)
+    }
+}
+
 impl super::MouseModeState for EyedropperState {
     fn handle_drag_start(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
-        let (x, y) = canvas.cursor_pos_pix_u();
-        if let Some(pix) = canvas.layered_image().try_pix_at(y as i32, x as i32) {
+        if let Some(pix) = Self::sample(canvas, toolbar) {
             if mod_keys.contains(ModifierType::CONTROL_MASK) {
                 let _ = toolbar.add_color_to_palette(pix.to_rgba_struct());
             } else {
@@ -33,8 +73,7 @@ impl super::MouseModeState for EyedropperState {
     }
 
     fn handle_right_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
-        let (x, y) = canvas.cursor_pos_pix_u();
-        if let Some(pix) = canvas.layered_image().try_pix_at(y as i32, x as i32) {
+        if let Some(pix) = Self::sample(canvas, toolbar) {
            toolbar.set_secondary_color(pix.to_rgba_struct());
         }
     }
@@ -44,7 +83,7 @@ impl super::MouseModeState for EyedropperState {
     }
 
     fn draw(&self, canvas: &Canvas, cr: &Context, toolbar: &mut Toolbar) {
-        // draw one-pixel "brush" to highlight the target pixel
+        // draw square "brush" to highlight the sampled pixels
         let cursor_pos_f = canvas.cursor_pos_pix_f();
         let cursor_pos = (cursor_pos_f.0.floor(), cursor_pos_f.1.floor());
 
@@ -68,8 +107,9 @@ impl super::MouseModeState for EyedropperState {
         {
             cr.translate(cursor_pos_f.0 - x_offset as f64, cursor_pos_f.1 - y_offset as f64);
             cr.scale(1.0 / canvas.zoom(), 1.0 / canvas.zoom());
-            let (x, y) = canvas.cursor_pos_pix_u();
-            if let Some(pix) = canvas.active_image().try_pix_at(y, x) {
+            let sample_merged = toolbar.get_eyedropper_sample_merged();
+            let sample_size = toolbar.get_eyedropper_sample_size();
+            if let Some(pix) = sample_color(canvas, sample_merged, sample_size) {
                 let rgba = pix.to_rgba_struct();
                 cr.rectangle(20.0, 30.0, 30.0, 30.0);
 
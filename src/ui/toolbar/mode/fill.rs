@@ -1,12 +1,35 @@
-use crate::image::{bitmask::ImageBitmask, ImageLikeUnchecked, Pixel};
+use crate::image::{bitmask::ImageBitmask, ImageLike, ImageLikeUnchecked, Pixel};
+use crate::image::blend::composite_over;
+use crate::ui::selection::Selection;
 use super::{Canvas, Toolbar};
 use crate::image::undo::action::ActionName;
 use crate::image::TrackedLayeredImage;
 
 use gtk::gdk::{RGBA, ModifierType};
+use gtk::cairo::Context;
+
+/// What the fill tool fills with
+#[derive(Clone, Copy, PartialEq)]
+pub enum FillSource {
+    Color,
+    /// The fill pattern, tiled from the image's top-left corner
+    Pattern,
+}
+
+impl FillSource {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Color", FillSource::Color),
+            ("Pattern", FillSource::Pattern),
+        ].iter().map(|x| x.clone())
+    }
+}
 
 #[derive(Clone, Copy)]
-pub struct FillState;
+pub struct FillState {
+    /// Whether the current drag is moving the symmetry center (instead of filling)
+    moving_symmetry_center: bool,
+}
 
 impl FillState {
     pub fn default(_canvas: &Canvas) -> FillState {
@@ -14,35 +37,182 @@ impl FillState {
     }
 
     pub const fn default_no_canvas() -> FillState {
-        FillState
+        FillState {
+            moving_symmetry_center: false,
+        }
     }
 
-    fn do_fill(canvas: &mut Canvas, toolbar: &mut Toolbar, color: RGBA) {
+    /// The pixels to fill when filling the whole selection (which
+    /// is the whole image, if nothing's selected)
+    fn selection_region(canvas: &Canvas) -> ImageBitmask {
+        let (width, height) = (canvas.image_width() as usize, canvas.image_height() as usize);
+
+        let bits = if let Selection::NoSelection = canvas.selection() {
+            vec![true; width * height]
+        } else {
+            let mut bits = vec![false; width * height];
+            for (r, c) in canvas.selection().iter().filter(|&(r, c)| r < height && c < width) {
+                bits[r * width + c] = true;
+            }
+            bits
+        };
+
+        ImageBitmask::from_flat_bits(height, width, bits)
+    }
+
+    /// The pixels reached by flood-filling from the cursor (and its
+    /// symmetric copies), through the active layer or the visible composite
+    /// (or, if not contiguous, every pixel matching the colors at those points)
+    fn flood_region(canvas: &Canvas, toolbar: &Toolbar) -> ImageBitmask {
         let (oc, or) = canvas.cursor_pos_pix_u();
         let tolerance = toolbar.get_fill_tolerance();
         let relativity = toolbar.get_fill_relativity();
-        // only reference the selected pixel if tolerance is absolute (not relative)
-        let tolerance_reference = Some(canvas.active_image().pix_at(or, oc)).filter(|_| !relativity);
+        let symmetry = toolbar.get_fill_symmetry();
+        let center = canvas.symmetry_center();
+        let (width, height) = (canvas.image_width(), canvas.image_height());
+
+        let mut origins = symmetry.copies().iter()
+            .map(|copy| copy.pixel(center, (oc as i32, or as i32)))
+            .filter(|&(c, r)| c >= 0 && r >= 0 && c < width && r < height)
+            .map(|(c, r)| (r as usize, c as usize))
+            .collect::<Vec<_>>();
+        origins.sort();
+        origins.dedup();
+
+        let composite;
+        let image = if toolbar.get_fill_sample_merged() {
+            composite = canvas.layered_image().composite_image();
+            &composite
+        } else {
+            canvas.active_image()
+        };
+
+        let contiguous = toolbar.get_fill_contiguous();
+        let diagonal = toolbar.get_fill_diagonal();
+
+        // find every region before filling any of them, so
+        // the copies don't see each other's fills
+        let mut bits = vec![false; image.width() * image.height()];
+        for (or, oc) in origins.into_iter() {
+            let bitmask = if contiguous {
+                // only reference the selected pixel if tolerance is absolute (not relative)
+                let tolerance_reference = Some(image.pix_at(or, oc)).filter(|_| !relativity);
+                ImageBitmask::from_flood_fill(image, tolerance, or, oc, tolerance_reference, diagonal)
+            } else {
+                ImageBitmask::from_color_match(image, tolerance, image.pix_at(or, oc))
+            };
+
+            for (r, c) in bitmask.coords_of_active_bits() {
+                bits[r * image.width() + c] = true;
+            }
+        }
+
+        ImageBitmask::from_flat_bits(image.height(), image.width(), bits)
+    }
+
+    /// Fills the region under the cursor (or the selection) with `color`
+    /// or the fill pattern (falling back to `color` if there's no pattern)
+    fn do_fill(canvas: &mut Canvas, toolbar: &mut Toolbar, color: RGBA) {
+        let region = if toolbar.get_fill_whole_selection() {
+            FillState::selection_region(canvas)
+        } else {
+            FillState::flood_region(canvas, toolbar)
+        };
+        let region = region.expanded(toolbar.get_fill_expansion());
+        let (width, height) = (region.width(), region.height());
+
+        // the expansion and antialiasing mustn't reach past the selection
+        let selection = match canvas.selection() {
+            Selection::NoSelection => None,
+            _ => Some(FillState::selection_region(canvas)),
+        };
+        let selected = |i: usize| selection.as_ref().is_none_or(|selection| selection.bit_at(i));
 
-        let bitmask = ImageBitmask::from_flood_fill(canvas.active_image(), tolerance, or, oc, tolerance_reference);
-        let image = canvas.active_image_mut();
         let p = Pixel::from_rgba_struct(color);
+        let pattern = Some(toolbar.fill_pattern())
+            .filter(|_| toolbar.get_fill_source() == FillSource::Pattern)
+            .flatten()
+            .filter(|pattern| pattern.width() > 0 && pattern.height() > 0);
+        let fill_pix_at = |r: usize, c: usize| match pattern {
+            Some(pattern) => pattern.pix_at(r % pattern.height(), c % pattern.width()).clone(),
+            None => p.clone(),
+        };
+
+        // antialiasing partially covers the pixels just outside the
+        // region (by the fraction of their 3x3 neighborhood inside it)
+        let edge_coverage = if toolbar.get_fill_antialias() {
+            (0..width * height)
+                .filter(|&i| !region.bit_at(i) && selected(i))
+                .filter_map(|i| {
+                    let (r, c) = (i / width, i % width);
+                    let n_covered = (r.saturating_sub(1)..(r + 2).min(height))
+                        .flat_map(|nr| (c.saturating_sub(1)..(c + 2).min(width)).map(move |nc| (nr, nc)))
+                        .filter(|&(nr, nc)| region.bit_at(nr * width + nc))
+                        .count();
+
+                    Some((r, c, n_covered as f64 / 9.0)).filter(|_| n_covered > 0)
+                })
+                .collect::<Vec<_>>()
+        } else {
+            vec![]
+        };
+
+        let image = canvas.active_image_mut();
 
-        for (r, c) in bitmask.coords_of_active_bits() {
-            *image.pix_at_mut(r as i32, c as i32) = p.clone();
+        for (r, c) in region.coords_of_active_bits().filter(|&(r, c)| selected(r * width + c)) {
+            *image.pix_at_mut(r as i32, c as i32) = fill_pix_at(r, c);
+        }
+
+        for (r, c, coverage) in edge_coverage.into_iter() {
+            let above = fill_pix_at(r, c).scale_alpha(coverage);
+            let below = image.pix_at(r as i32, c as i32);
+            *image.pix_at_mut(r as i32, c as i32) = composite_over(&above, below);
         }
 
         canvas.commit_changes(ActionName::Fill);
         canvas.update()
     }
+
+    fn drag_start(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar, color: RGBA) {
+        self.moving_symmetry_center = mod_keys.intersects(ModifierType::CONTROL_MASK)
+            && !toolbar.get_fill_symmetry().is_off();
+
+        if self.moving_symmetry_center {
+            canvas.move_symmetry_center_to_cursor();
+            canvas.update();
+        } else {
+            FillState::do_fill(canvas, toolbar, color);
+        }
+    }
+
+    fn drag_update(&mut self, canvas: &mut Canvas) {
+        if self.moving_symmetry_center {
+            canvas.move_symmetry_center_to_cursor();
+            canvas.update();
+        }
+    }
 }
 
 impl super::MouseModeState for FillState {
-    fn handle_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
-        FillState::do_fill(canvas, toolbar, toolbar.primary_color());
+    fn handle_drag_start(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
+        self.drag_start(mod_keys, canvas, toolbar, toolbar.primary_color());
+    }
+
+    fn handle_right_drag_start(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
+        self.drag_start(mod_keys, canvas, toolbar, toolbar.secondary_color());
+    }
+
+    fn handle_drag_update(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        self.drag_update(canvas);
+    }
+
+    fn handle_right_drag_update(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        self.drag_update(canvas);
     }
 
-    fn handle_right_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
-        FillState::do_fill(canvas, toolbar, toolbar.secondary_color());
+    fn draw(&self, canvas: &Canvas, cr: &Context, toolbar: &mut Toolbar) {
+        let symmetry = toolbar.get_fill_symmetry();
+        let (width, height) = (canvas.image_width() as f64, canvas.image_height() as f64);
+        symmetry.draw_guides(cr, canvas.symmetry_center(), width, height, *canvas.zoom());
     }
 }
@@ -0,0 +1,162 @@
+use super::{Canvas, Toolbar};
+use crate::geometry::snap_to_angle;
+use crate::image::gradient::Gradient;
+use crate::image::Pixel;
+
+use gtk::gdk::ModifierType;
+use gtk::cairo::Context;
+use std::f64::consts::{PI, TAU};
+
+/// Radius (in screen pixels) of the start and end markers
+const MARKER_RADIUS: f64 = 4.0;
+/// Shift-dragged gradients snap to multiples of this angle
+const SNAP_ANGLE: f64 = PI / 12.0;
+
+/// Where the gradient's colors come from
+#[derive(Clone, Copy, PartialEq)]
+pub enum GradientColors {
+    PrimaryToSecondary,
+    PrimaryToTransparent,
+    /// The stops from the toolbar's gradient editor
+    Custom,
+}
+
+impl GradientColors {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Primary \u{2192} Secondary", GradientColors::PrimaryToSecondary),
+            ("Primary \u{2192} Transparent", GradientColors::PrimaryToTransparent),
+            ("Custom Stops", GradientColors::Custom),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct GradientDrag {
+    start: (f64, f64),
+    end: (f64, f64),
+    /// Whether the stops run from end to start (right-click drags)
+    reversed: bool,
+}
+
+#[derive(Clone)]
+pub struct GradientState {
+    drag: Option<GradientDrag>,
+}
+
+impl GradientState {
+    pub fn default(_canvas: &Canvas) -> GradientState {
+        Self::default_no_canvas()
+    }
+
+    pub fn default_no_canvas() -> GradientState {
+        GradientState {
+            drag: None,
+        }
+    }
+
+    fn drag_start(&mut self, canvas: &mut Canvas, reversed: bool) {
+        let point = canvas.cursor_pos_pix_f();
+
+        self.drag = Some(GradientDrag {
+            start: point,
+            end: point,
+            reversed,
+        });
+        canvas.update();
+    }
+
+    fn drag_update(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas) {
+        let point = canvas.cursor_pos_pix_f();
+
+        if let Some(ref mut drag) = self.drag {
+            drag.end = if mod_keys.intersects(ModifierType::SHIFT_MASK) {
+                snap_to_angle(drag.start, point, SNAP_ANGLE)
+            } else {
+                point
+            };
+        }
+
+        canvas.update();
+    }
+
+    fn drag_end(&mut self, canvas: &mut Canvas, toolbar: &Toolbar) {
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+
+        if drag.start == drag.end {
+            canvas.update();
+            return;
+        }
+
+        let stops = toolbar.get_gradient_stops().into_iter()
+            .map(|(position, color)| {
+                let position = if drag.reversed { 1.0 - position } else { position };
+                (position, Pixel::from_rgba_struct(color))
+            })
+            .collect::<Vec<_>>();
+
+        let gradient = Gradient::new(
+            toolbar.get_gradient_shape(),
+            stops,
+            drag.start,
+            drag.end,
+            toolbar.get_gradient_dither(),
+        );
+
+        canvas.draw_gradient(gradient);
+    }
+}
+
+impl super::MouseModeState for GradientState {
+    fn handle_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        self.drag_start(canvas, false);
+    }
+
+    fn handle_right_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        self.drag_start(canvas, true);
+    }
+
+    fn handle_drag_update(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        self.drag_update(mod_keys, canvas);
+    }
+
+    fn handle_right_drag_update(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        self.drag_update(mod_keys, canvas);
+    }
+
+    fn handle_drag_end(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
+        self.drag_end(canvas, toolbar);
+    }
+
+    fn handle_right_drag_end(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
+        self.drag_end(canvas, toolbar);
+    }
+
+    fn draw(&self, canvas: &Canvas, cr: &Context, _toolbar: &mut Toolbar) {
+        let Some(drag) = self.drag else {
+            return;
+        };
+
+        let zoom = *canvas.zoom();
+
+        let _ = cr.save();
+        {
+            cr.set_line_width(1.0 / zoom);
+            cr.set_source_rgb(0.0, 0.0, 0.0);
+            cr.move_to(drag.start.0, drag.start.1);
+            cr.line_to(drag.end.0, drag.end.1);
+            let _ = cr.stroke();
+
+            for (x, y) in [drag.start, drag.end] {
+                cr.arc(x, y, MARKER_RADIUS / zoom, 0.0, TAU);
+                cr.set_source_rgb(1.0, 1.0, 1.0);
+                let _ = cr.fill_preserve();
+                cr.set_source_rgb(0.0, 0.0, 0.0);
+                let _ = cr.stroke();
+            }
+        }
+        let _ = cr.restore();
+    }
+}
@@ -0,0 +1,247 @@
+use super::{Canvas, MouseMode, Toolbar};
+use crate::image::undo::action::ActionName;
+use crate::image::vector_path::{distance, lerp, Anchor, Subpath, VectorPath};
+use crate::geometry::snap_to_angle;
+
+use gtk::gdk::ModifierType;
+use gtk::cairo::{Context, LineCap, LineJoin};
+use std::f64::consts::{PI, TAU};
+
+/// Radius (in screen pixels) of the vertex and handle markers
+const MARKER_RADIUS: f64 = 5.0;
+/// Shift-dragged vertices snap to multiples of this angle
+const SNAP_ANGLE: f64 = PI / 12.0;
+
+#[derive(Clone, Copy)]
+enum LineDrag {
+    None,
+    /// Dragging the vertex at the given index
+    Vertex(usize),
+    /// Dragging one (0 or 1) of the given segment's curve handles
+    Handle(usize, usize),
+}
+
+/// A polyline (with optionally curved segments) that can be edited
+/// until it's committed (stroked with the pencil's brush)
+#[derive(Clone)]
+pub struct LineState {
+    vertices: Vec<(f64, f64)>,
+    /// The cubic Bezier control points of the segment from each
+    /// vertex to the next (`None` while the segment is straight)
+    handles: Vec<Option<[(f64, f64); 2]>>,
+    drag: LineDrag,
+}
+
+impl LineState {
+    pub fn default(_canvas: &Canvas) -> LineState {
+        Self::default_no_canvas()
+    }
+
+    pub fn default_no_canvas() -> LineState {
+        LineState {
+            vertices: vec![],
+            handles: vec![],
+            drag: LineDrag::None,
+        }
+    }
+
+    /// The control points of the `i`th segment (a third of the
+    /// way along each end, if it's straight)
+    fn segment_handles(&self, i: usize) -> [(f64, f64); 2] {
+        self.handles[i].unwrap_or_else(|| {
+            let (p0, p1) = (self.vertices[i], self.vertices[i + 1]);
+            [lerp(p0, p1, 1.0 / 3.0), lerp(p0, p1, 2.0 / 3.0)]
+        })
+    }
+
+    /// The handle (segment index, handle index) within reach of `point`
+    fn handle_near(&self, point: (f64, f64), reach: f64) -> Option<(usize, usize)> {
+        (0..self.handles.len())
+            .flat_map(|i| {
+                let [h0, h1] = self.segment_handles(i);
+                [((i, 0), h0), ((i, 1), h1)]
+            })
+            .find(|(_, handle)| distance(*handle, point) <= reach)
+            .map(|(handle, _)| handle)
+    }
+
+    /// The index of the vertex within reach of `point`
+    fn vertex_near(&self, point: (f64, f64), reach: f64) -> Option<usize> {
+        self.vertices.iter().position(|vertex| distance(*vertex, point) <= reach)
+    }
+
+    /// The line as an (open) path
+    fn to_vector_path(&self) -> VectorPath {
+        let mut anchors = self.vertices.iter()
+            .map(|&vertex| Anchor::new(vertex))
+            .collect::<Vec<_>>();
+
+        for (i, handles) in self.handles.iter().enumerate() {
+            if let Some([h0, h1]) = *handles {
+                anchors[i].handle_out = h0;
+                anchors[i + 1].handle_in = h1;
+            }
+        }
+
+        VectorPath {
+            subpaths: vec![Subpath { anchors, closed: false }],
+        }
+    }
+
+    /// Strokes the path with the pencil's brush, committing it as one action
+    fn commit(&self, canvas: &mut Canvas, toolbar: &Toolbar) {
+        if self.vertices.len() < 2 {
+            return;
+        }
+
+        let path = self.to_vector_path();
+        toolbar.stroke_with_pencil_brush(canvas, toolbar.primary_color(), |spacing| path.points_along(spacing));
+
+        canvas.commit_changes(ActionName::Line);
+        canvas.clear_pencil_mask();
+        canvas.update();
+    }
+
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.handles.clear();
+        self.drag = LineDrag::None;
+    }
+}
+
+impl super::MouseModeState for LineState {
+    fn handle_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        let point = canvas.cursor_pos_pix_f();
+        let reach = MARKER_RADIUS / *canvas.zoom();
+
+        if let Some((i, k)) = self.handle_near(point, reach) {
+            self.handles[i] = Some(self.segment_handles(i));
+            self.drag = LineDrag::Handle(i, k);
+        } else if let Some(i) = self.vertex_near(point, reach) {
+            self.drag = LineDrag::Vertex(i);
+        } else {
+            if self.vertices.is_empty() {
+                // dragging out the first segment
+                self.vertices.push(point);
+            }
+
+            self.vertices.push(point);
+            self.handles.push(None);
+            self.drag = LineDrag::Vertex(self.vertices.len() - 1);
+        }
+
+        canvas.update();
+    }
+
+    fn handle_drag_update(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        let point = canvas.cursor_pos_pix_f();
+
+        match self.drag {
+            LineDrag::None => (),
+            LineDrag::Vertex(i) => {
+                // snap relative to the previous vertex (or the next, for the first)
+                let anchor = if i > 0 { self.vertices.get(i - 1) } else { self.vertices.get(1) };
+
+                self.vertices[i] = match anchor {
+                    Some(&anchor) if mod_keys.intersects(ModifierType::SHIFT_MASK) => snap_to_angle(anchor, point, SNAP_ANGLE),
+                    _ => point,
+                };
+            },
+            LineDrag::Handle(i, k) => {
+                if let Some(ref mut handles) = self.handles[i] {
+                    handles[k] = point;
+                }
+            },
+        }
+
+        canvas.update();
+    }
+
+    fn handle_drag_end(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        // a click (rather than a drag) on the last vertex doesn't add a segment
+        if let LineDrag::Vertex(i) = self.drag {
+            if i > 0 && i == self.vertices.len() - 1 && distance(self.vertices[i], self.vertices[i - 1]) < 0.5 {
+                self.vertices.pop();
+                self.handles.pop();
+            }
+        }
+
+        self.drag = LineDrag::None;
+        canvas.update();
+    }
+
+    fn handle_right_drag_start(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar) {
+        if !mod_keys.intersects(ModifierType::CONTROL_MASK) {
+            self.commit(canvas, toolbar);
+        }
+
+        self.clear();
+        canvas.update();
+    }
+
+    fn handle_motion(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        canvas.update();
+    }
+
+    fn handle_close(&self, canvas: &mut Canvas, toolbar: &Toolbar, _new_mode: &MouseMode) {
+        self.commit(canvas, toolbar);
+    }
+
+    fn draw(&self, canvas: &Canvas, cr: &Context, toolbar: &mut Toolbar) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let zoom = *canvas.zoom();
+        let color = toolbar.primary_color();
+        let width = toolbar.get_pencil_brush(color).radius() as f64;
+
+        let _ = cr.save();
+        {
+            // a preview of the stroke
+            cr.set_line_cap(LineCap::Round);
+            cr.set_line_join(LineJoin::Round);
+            cr.set_line_width(width);
+            cr.set_source_rgba(color.red() as f64, color.green() as f64, color.blue() as f64, color.alpha() as f64);
+            cr.move_to(self.vertices[0].0, self.vertices[0].1);
+            for i in 0..self.handles.len() {
+                let [h0, h1] = self.segment_handles(i);
+                let p1 = self.vertices[i + 1];
+                cr.curve_to(h0.0, h0.1, h1.0, h1.1, p1.0, p1.1);
+            }
+            let _ = cr.stroke();
+
+            // the handles (tied to their vertices), then the vertices
+            cr.set_line_width(1.0 / zoom);
+            for i in 0..self.handles.len() {
+                let [h0, h1] = self.segment_handles(i);
+                let (p0, p1) = (self.vertices[i], self.vertices[i + 1]);
+
+                cr.set_source_rgb(0.0, 0.6, 1.0);
+                cr.move_to(p0.0, p0.1);
+                cr.line_to(h0.0, h0.1);
+                cr.move_to(p1.0, p1.1);
+                cr.line_to(h1.0, h1.1);
+                let _ = cr.stroke();
+
+                for (x, y) in [h0, h1] {
+                    cr.arc(x, y, MARKER_RADIUS / zoom * 0.8, 0.0, TAU);
+                    cr.set_source_rgb(1.0, 1.0, 1.0);
+                    let _ = cr.fill_preserve();
+                    cr.set_source_rgb(0.0, 0.6, 1.0);
+                    let _ = cr.stroke();
+                }
+            }
+
+            for &(x, y) in self.vertices.iter() {
+                let r = MARKER_RADIUS / zoom;
+                cr.rectangle(x - r, y - r, 2.0 * r, 2.0 * r);
+                cr.set_source_rgb(1.0, 1.0, 1.0);
+                let _ = cr.fill_preserve();
+                cr.set_source_rgb(0.0, 0.0, 0.0);
+                let _ = cr.stroke();
+            }
+        }
+        let _ = cr.restore();
+    }
+}
@@ -24,10 +24,16 @@ impl super::MouseModeState for MagicWandState {
         let (oc, or) = canvas.cursor_pos_pix_u();
         let tolerance = toolbar.get_magic_wand_tolerance();
         let relativity = toolbar.get_magic_wand_relativity();
-        // only reference the selected pixel if tolerance is absolute (not relative)
-        let tolerance_reference = Some(canvas.active_image().pix_at(or, oc)).filter(|_| !relativity);
+        let image = canvas.active_image();
 
-        let bitmask = ImageBitmask::from_flood_fill(canvas.active_image(), tolerance, or, oc, tolerance_reference);
+        let bitmask = if toolbar.get_magic_wand_contiguous() {
+            // only reference the selected pixel if tolerance is absolute (not relative)
+            let tolerance_reference = Some(image.pix_at(or, oc)).filter(|_| !relativity);
+            let diagonal = toolbar.get_magic_wand_diagonal();
+            ImageBitmask::from_flood_fill(image, tolerance, or, oc, tolerance_reference, diagonal)
+        } else {
+            ImageBitmask::from_color_match(image, tolerance, image.pix_at(or, oc))
+        };
         canvas.set_selection(Selection::Bitmask(bitmask));
         canvas.update()
     }
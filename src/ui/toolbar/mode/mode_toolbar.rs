@@ -1,12 +1,21 @@
 use super::shape::ShapeState;
 use super::{FreeTransformState, MouseModeVariant};
+use super::symmetry::{Symmetry, SymmetryType};
+use super::pencil::{RetouchTool, Stabilizer, StabilizerType};
+use super::airbrush::AirbrushTip;
+use super::fill::FillSource;
+use super::gradient::GradientColors;
 use crate::image::resize::ScaleMethod;
 use crate::vertical_composite_field;
 use crate::image::blend::BlendingMode;
-use crate::image::brush::BrushType;
+use crate::image::filter::ToneRange;
+use crate::image::gradient::GradientShape;
+use crate::image::brush::{BrushDynamics, BrushTip, BrushType, VelocityCurve, VelocityDynamics};
+use super::super::brush_library::BrushLibrary;
+use super::super::brush_presets::{BrushPreset, BrushPresets};
 use crate::transformable::Transformable;
 use crate::ui::form::gadget::{NumberedSliderGadget, ToggleButtonsGadget};
-use crate::ui::form::{CheckboxField, DropdownField, Form, FormBuilderIsh, NaturalField, RadioField};
+use crate::ui::form::{CheckboxField, ColorField, DropdownField, Form, FormBuilderIsh, FormField, NaturalField, RadioField};
 use crate::ui::UiState;
 use crate::shape::{ShapeType, Shape};
 
@@ -16,20 +25,267 @@ use gtk::gdk::RGBA;
 use gtk::prelude::*;
 use glib_macros::clone;
 
-type PencilSettings = (BrushType, BlendingMode, u8);
-fn mk_pencil_toolbar() -> (Form, Box<dyn Fn() -> PencilSettings>) {
+/// A button that opens a popover of the pencil's `BrushDynamics` settings,
+/// along with functions to get and set those settings
+fn mk_brush_dynamics_button() -> (gtk::MenuButton, Box<dyn Fn() -> BrushDynamics>, Box<dyn Fn(&BrushDynamics)>) {
+    let defaults = BrushDynamics::default();
+    let percent = |x: f64| (x * 100.0).round() as usize;
+
+    let mk_slider = |label, max, default| NumberedSliderGadget::new_p(
+        Some(label),
+        gtk::Orientation::Horizontal,
+        false,
+        0,
+        max,
+        1,
+        default,
+        String::from("%"),
+    );
+
+    let spacing_slider_p = NumberedSliderGadget::new_p(
+        Some("Spacing"),
+        gtk::Orientation::Horizontal,
+        false,
+        1,
+        200,
+        1,
+        percent(defaults.spacing),
+        String::from("%"),
+    );
+    let flow_slider_p = mk_slider("Flow", 100, percent(defaults.flow));
+    let opacity_slider_p = mk_slider("Opacity", 100, percent(defaults.opacity));
+    let size_jitter_slider_p = mk_slider("Size Jitter", 100, percent(defaults.size_jitter));
+    let opacity_jitter_slider_p = mk_slider("Opacity Jitter", 100, percent(defaults.opacity_jitter));
+    let position_jitter_slider_p = mk_slider("Position Jitter", 100, percent(defaults.position_jitter));
+    let scatter_slider_p = mk_slider("Scatter", 300, percent(defaults.scatter));
+    let follow_angle = CheckboxField::new(Some("Angle Follows Stroke"), defaults.follow_angle);
+
+    let velocity_defaults = defaults.velocity;
+    let slow_size_slider_p = mk_slider("Slow Size", 100, percent(velocity_defaults.slow_size));
+    let fast_size_slider_p = mk_slider("Fast Size", 100, percent(velocity_defaults.fast_size));
+    let slow_opacity_slider_p = mk_slider("Slow Opacity", 100, percent(velocity_defaults.slow_opacity));
+    let fast_opacity_slider_p = mk_slider("Fast Opacity", 100, percent(velocity_defaults.fast_opacity));
+    let full_speed_field = NaturalField::new(
+        Some("Full Speed (px/event)"), 1, 500, 1, velocity_defaults.full_speed as usize
+    );
+    let curve_dropdown = DropdownField::new(
+        Some("Curve"),
+        VelocityCurve::labeled_variants().collect::<Vec<_>>(),
+        0,
+    );
+    let fade_in_field = NaturalField::new(Some("Fade In (px)"), 0, 1000, 1, velocity_defaults.fade_in as usize);
+    let fade_out_field = NaturalField::new(Some("Fade Out (px)"), 0, 1000, 1, velocity_defaults.fade_out as usize);
+
+    let velocity_form = Form::builder()
+        .title("Velocity")
+        .with_gadget(&*slow_size_slider_p.borrow())
+        .with_gadget(&*fast_size_slider_p.borrow())
+        .with_gadget(&*slow_opacity_slider_p.borrow())
+        .with_gadget(&*fast_opacity_slider_p.borrow())
+        .with_field(&full_speed_field)
+        .with_field(&curve_dropdown)
+        .with_field(&fade_in_field)
+        .with_field(&fade_out_field)
+        .build();
+
+    let stamp_form = Form::builder()
+        .title("Stamps")
+        .with_gadget(&*spacing_slider_p.borrow())
+        .with_gadget(&*flow_slider_p.borrow())
+        .with_gadget(&*opacity_slider_p.borrow())
+        .with_gadget(&*size_jitter_slider_p.borrow())
+        .with_gadget(&*opacity_jitter_slider_p.borrow())
+        .with_gadget(&*position_jitter_slider_p.borrow())
+        .with_gadget(&*scatter_slider_p.borrow())
+        .with_field(&follow_angle)
+        .build();
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(20)
+        .with_field(stamp_form.widget())
+        .with_field(velocity_form.widget())
+        .build();
+
+    let popover = gtk::Popover::builder()
+        .child(form.widget())
+        .build();
+
+    let button = gtk::MenuButton::builder()
+        .label("Dynamics")
+        .valign(gtk::Align::Center)
+        .popover(&popover)
+        .build();
+
+    let follow_angle = Rc::new(follow_angle);
+    let full_speed_field = Rc::new(full_speed_field);
+    let curve_dropdown = Rc::new(curve_dropdown);
+    let fade_in_field = Rc::new(fade_in_field);
+    let fade_out_field = Rc::new(fade_out_field);
+
+    let set = clone!(@strong spacing_slider_p, @strong size_jitter_slider_p,
+                     @strong opacity_jitter_slider_p, @strong position_jitter_slider_p,
+                     @strong scatter_slider_p, @strong follow_angle, @strong flow_slider_p,
+                     @strong opacity_slider_p, @strong slow_size_slider_p,
+                     @strong fast_size_slider_p, @strong slow_opacity_slider_p,
+                     @strong fast_opacity_slider_p, @strong full_speed_field,
+                     @strong curve_dropdown, @strong fade_in_field,
+                     @strong fade_out_field => move |dynamics: &BrushDynamics| {
+        let set_fraction = |slider_p: &Rc<RefCell<NumberedSliderGadget>>, x: f64| {
+            slider_p.borrow().set_value(percent(x))
+        };
+
+        set_fraction(&spacing_slider_p, dynamics.spacing);
+        set_fraction(&size_jitter_slider_p, dynamics.size_jitter);
+        set_fraction(&opacity_jitter_slider_p, dynamics.opacity_jitter);
+        set_fraction(&position_jitter_slider_p, dynamics.position_jitter);
+        set_fraction(&scatter_slider_p, dynamics.scatter);
+        follow_angle.set_value(dynamics.follow_angle);
+        set_fraction(&flow_slider_p, dynamics.flow);
+        set_fraction(&opacity_slider_p, dynamics.opacity);
+
+        let velocity = &dynamics.velocity;
+        set_fraction(&slow_size_slider_p, velocity.slow_size);
+        set_fraction(&fast_size_slider_p, velocity.fast_size);
+        set_fraction(&slow_opacity_slider_p, velocity.slow_opacity);
+        set_fraction(&fast_opacity_slider_p, velocity.fast_opacity);
+        full_speed_field.set_value(velocity.full_speed as usize);
+        curve_dropdown.set_value(&velocity.curve);
+        fade_in_field.set_value(velocity.fade_in as usize);
+        fade_out_field.set_value(velocity.fade_out as usize);
+    });
+
+    let fraction = |slider_p: &Rc<RefCell<NumberedSliderGadget>>| {
+        slider_p.borrow().value() as f64 / 100.0
+    };
+
+    let get = move || {
+        BrushDynamics {
+            spacing: fraction(&spacing_slider_p),
+            size_jitter: fraction(&size_jitter_slider_p),
+            opacity_jitter: fraction(&opacity_jitter_slider_p),
+            position_jitter: fraction(&position_jitter_slider_p),
+            scatter: fraction(&scatter_slider_p),
+            follow_angle: follow_angle.value(),
+            flow: fraction(&flow_slider_p),
+            opacity: fraction(&opacity_slider_p),
+            velocity: VelocityDynamics {
+                slow_size: fraction(&slow_size_slider_p),
+                fast_size: fraction(&fast_size_slider_p),
+                slow_opacity: fraction(&slow_opacity_slider_p),
+                fast_opacity: fraction(&fast_opacity_slider_p),
+                full_speed: full_speed_field.value() as f64,
+                curve: curve_dropdown.value().clone(),
+                fade_in: fade_in_field.value() as f64,
+                fade_out: fade_out_field.value() as f64,
+            },
+        }
+    };
+
+    (button, Box::new(get), Box::new(set))
+}
+
+/// Fields for choosing a tool's `Symmetry`
+fn mk_symmetry_form() -> (Form, Box<dyn Fn() -> Symmetry>) {
+    let type_dropdown = DropdownField::new(
+        Some("Symmetry"),
+        SymmetryType::labeled_variants().collect::<Vec<_>>(),
+        0,
+    );
+    type_dropdown.set_orientation(gtk::Orientation::Vertical);
+    let radial_ways_field = NaturalField::new(Some("Radial Ways"), 2, 64, 1, 6);
+    radial_ways_field.set_orientation(gtk::Orientation::Vertical);
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .with_field(&type_dropdown)
+        .with_field(&radial_ways_field)
+        .build();
+
+    let get = move || {
+        Symmetry {
+            symmetry_type: type_dropdown.value().clone(),
+            radial_ways: radial_ways_field.value(),
+        }
+    };
+
+    (form, Box::new(get))
+}
+
+/// Fields for choosing the pencil's `Stabilizer`
+fn mk_stabilizer_form() -> (Form, Box<dyn Fn() -> Stabilizer>, Box<dyn Fn(&Stabilizer)>) {
+    let defaults = Stabilizer::default();
+
+    let type_dropdown = DropdownField::new(
+        Some("Stabilizer"),
+        StabilizerType::labeled_variants().collect::<Vec<_>>(),
+        0,
+    );
+    type_dropdown.set_orientation(gtk::Orientation::Vertical);
+    let string_length_field = NaturalField::new(
+        Some("String Length"), 1, 500, 1, defaults.string_length as usize
+    );
+    string_length_field.set_orientation(gtk::Orientation::Vertical);
+    let num_points_field = NaturalField::new(Some("Averaged Points"), 2, 64, 1, defaults.num_points);
+    num_points_field.set_orientation(gtk::Orientation::Vertical);
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .with_field(&type_dropdown)
+        .with_field(&string_length_field)
+        .with_field(&num_points_field)
+        .build();
+
+    let type_dropdown = Rc::new(type_dropdown);
+    let string_length_field = Rc::new(string_length_field);
+    let num_points_field = Rc::new(num_points_field);
+
+    let set = clone!(@strong type_dropdown, @strong string_length_field,
+                     @strong num_points_field => move |stabilizer: &Stabilizer| {
+        type_dropdown.set_value(&stabilizer.stabilizer_type);
+        string_length_field.set_value(stabilizer.string_length as usize);
+        num_points_field.set_value(stabilizer.num_points);
+    });
+
+    let get = move || {
+        Stabilizer {
+            stabilizer_type: type_dropdown.value().clone(),
+            string_length: string_length_field.value() as f64,
+            num_points: num_points_field.value(),
+        }
+    };
+
+    (form, Box::new(get), Box::new(set))
+}
+
+pub struct PencilSettings {
+    pub brush_type: BrushType,
+    pub blending_mode: BlendingMode,
+    pub radius: u8,
+    pub pixelate_block_size: u8,
+    pub tip: Option<Rc<BrushTip>>,
+    pub dynamics: BrushDynamics,
+    pub antialias: bool,
+    pub symmetry: Symmetry,
+    pub stabilizer: Stabilizer,
+    pub pixel_perfect: bool,
+}
+
+fn mk_pencil_toolbar(
+    brush_library_p: &Rc<RefCell<BrushLibrary>>,
+    brush_presets_p: &Rc<RefCell<BrushPresets>>,
+) -> (Form, Box<dyn Fn() -> PencilSettings>) {
+    const CUSTOM_BRUSH_TYPE_INDEX: usize = 5;
     let brush_types = vec![
         ("Round", BrushType::Round),
         ("Square", BrushType::Square),
         ("Caligraphy", BrushType::Caligraphy),
         ("Dither", BrushType::Dither),
+        ("Pixelate", BrushType::Pixelate),
+        ("Custom", BrushType::Custom),
     ];
 
-    let blending_modes = vec![
-        ("Overwrite", BlendingMode::Overwrite),
-        ("Paint", BlendingMode::Paint),
-        ("Average", BlendingMode::Average),
-    ];
+    let blending_modes = BlendingMode::labeled_variants().collect::<Vec<_>>();
 
     let type_dropdown = DropdownField::new(Some("Brush Type"), brush_types, 0);
     type_dropdown.set_orientation(gtk::Orientation::Vertical);
@@ -37,27 +293,455 @@ fn mk_pencil_toolbar() -> (Form, Box<dyn Fn() -> PencilSettings>) {
     blending_mode_dropdown.set_orientation(gtk::Orientation::Vertical);
     let radius_selector = NaturalField::new(Some("Brush Radius"), 1, 255, 1, 5);
     radius_selector.set_orientation(gtk::Orientation::Vertical);
+    let pixelate_block_size_selector = NaturalField::new(Some("Pixelate Block Size"), 1, 255, 1, 8);
+    pixelate_block_size_selector.set_orientation(gtk::Orientation::Vertical);
+
+    let tip_dropdown = gtk::DropDown::builder()
+        .model(brush_library_p.borrow().names())
+        .valign(gtk::Align::Center)
+        .build();
+    let tip_wrapper = gtk::Box::new(gtk::Orientation::Vertical, 4);
+    tip_wrapper.append(&gtk::Label::new(Some("Custom Brush")));
+    tip_wrapper.append(&tip_dropdown);
+
+    let preset_wrapper = gtk::Box::new(gtk::Orientation::Vertical, 4);
+    preset_wrapper.append(&gtk::Label::new(Some("Preset")));
+    preset_wrapper.append(brush_presets_p.borrow().widget());
+
+    let type_dropdown = Rc::new(type_dropdown);
+    let blending_mode_dropdown = Rc::new(blending_mode_dropdown);
+    let radius_selector = Rc::new(radius_selector);
+    let pixelate_block_size_selector = Rc::new(pixelate_block_size_selector);
+    let (dynamics_button, get_dynamics, set_dynamics) = mk_brush_dynamics_button();
+    let antialias = Rc::new(CheckboxField::new(Some("Anti-Aliased"), false));
+    let pixel_perfect = Rc::new(CheckboxField::new(Some("Pixel Perfect"), false));
+    let (symmetry_form, get_symmetry) = mk_symmetry_form();
+    let (stabilizer_form, get_stabilizer, set_stabilizer) = mk_stabilizer_form();
+
+    // switch to newly added (or replaced) brush tips
+    brush_library_p.borrow().names().connect_items_changed(
+        clone!(@strong tip_dropdown, @strong type_dropdown => move |_, position, _removed, added| {
+            if added > 0 {
+                tip_dropdown.set_selected(position);
+                type_dropdown.set_selected(CUSTOM_BRUSH_TYPE_INDEX);
+            }
+        })
+    );
+
+    brush_presets_p.borrow_mut().set_on_select(Rc::new(
+        clone!(@strong type_dropdown, @strong blending_mode_dropdown, @strong radius_selector,
+               @strong pixelate_block_size_selector, @strong tip_dropdown, @strong antialias,
+               @strong pixel_perfect, @strong brush_library_p => move |preset: &BrushPreset| {
+            type_dropdown.set_value(&preset.brush_type);
+            blending_mode_dropdown.set_value(&preset.blending_mode);
+            radius_selector.set_value(preset.radius as usize);
+            pixelate_block_size_selector.set_value(preset.pixelate_block_size as usize);
+            // a missing tip (e.g. from an imported preset) just deselects the tip
+            let tip_index = preset.tip.as_ref()
+                .and_then(|name| brush_library_p.borrow().index_of(name))
+                .unwrap_or(gtk::INVALID_LIST_POSITION);
+            tip_dropdown.set_selected(tip_index);
+            set_dynamics(&preset.dynamics);
+            antialias.set_value(preset.antialias);
+            pixel_perfect.set_value(preset.pixel_perfect);
+            set_stabilizer(&preset.stabilizer);
+        })
+    ));
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .with_field(&preset_wrapper)
+        .with_field(&*type_dropdown)
+        .with_field(&tip_wrapper)
+        .with_field(&*blending_mode_dropdown)
+        .with_field(&*radius_selector)
+        .with_field(&*pixelate_block_size_selector)
+        .with_field(&dynamics_button)
+        .with_field(&*antialias)
+        .with_field(&*pixel_perfect)
+        .with_field(symmetry_form.widget())
+        .with_field(stabilizer_form.widget())
+        .spacing(20)
+        .build();
+
+    let brush_library_p = brush_library_p.clone();
+    let get = move || {
+        PencilSettings {
+            brush_type: type_dropdown.value().clone(),
+            blending_mode: blending_mode_dropdown.value().clone(),
+            radius: radius_selector.value() as u8,
+            pixelate_block_size: pixelate_block_size_selector.value() as u8,
+            tip: brush_library_p.borrow().tip(tip_dropdown.selected()),
+            dynamics: get_dynamics(),
+            antialias: antialias.value(),
+            symmetry: get_symmetry(),
+            stabilizer: get_stabilizer(),
+            pixel_perfect: pixel_perfect.value(),
+        }
+    };
+
+    (form, Box::new(get))
+}
+
+/// The brush type dropdown and radius selector shared by the
+/// tools that stamp a plain brush shape (eraser, retouching, etc.)
+fn mk_stamp_shape_fields(radius_label: &str) -> (DropdownField<BrushType>, NaturalField) {
+    let brush_types = vec![
+        ("Round", BrushType::Round),
+        ("Square", BrushType::Square),
+        ("Caligraphy", BrushType::Caligraphy),
+        ("Dither", BrushType::Dither),
+    ];
+
+    let type_dropdown = DropdownField::new(Some("Brush Type"), brush_types, 0);
+    type_dropdown.set_orientation(gtk::Orientation::Vertical);
+    let radius_selector = NaturalField::new(Some(radius_label), 1, 255, 1, 10);
+    radius_selector.set_orientation(gtk::Orientation::Vertical);
+
+    (type_dropdown, radius_selector)
+}
+
+pub struct EraserSettings {
+    pub brush_type: BrushType,
+    pub radius: u8,
+    pub strength: f64,
+    pub erase_to_secondary: bool,
+    pub symmetry: Symmetry,
+}
+
+fn mk_eraser_toolbar() -> (Form, Box<dyn Fn() -> EraserSettings>) {
+    let (type_dropdown, radius_selector) = mk_stamp_shape_fields("Eraser Radius");
+
+    let strength_slider_gadget_p = NumberedSliderGadget::new_p(
+        Some("Strength"),
+        gtk::Orientation::Horizontal,
+        true,
+        1,
+        100,
+        1,
+        100,
+        String::from("%"),
+    );
+
+    let erase_to_secondary = CheckboxField::new(Some("Erase to Secondary (Base Layer)"), false);
+    let (symmetry_form, get_symmetry) = mk_symmetry_form();
 
     let form = Form::builder()
         .orientation(gtk::Orientation::Horizontal)
         .with_field(&type_dropdown)
-        .with_field(&blending_mode_dropdown)
         .with_field(&radius_selector)
+        .with_gadget(&*strength_slider_gadget_p.borrow())
+        .with_field(&erase_to_secondary)
+        .with_field(symmetry_form.widget())
         .spacing(20)
         .build();
 
     let get = move || {
-        (
-            type_dropdown.value().clone(),
-            blending_mode_dropdown.value().clone(),
-            radius_selector.value() as u8,
-        )
+        EraserSettings {
+            brush_type: type_dropdown.value().clone(),
+            radius: radius_selector.value() as u8,
+            strength: strength_slider_gadget_p.borrow().value() as f64 / 100.0,
+            erase_to_secondary: erase_to_secondary.value(),
+            symmetry: get_symmetry(),
+        }
     };
 
     (form, Box::new(get))
 }
 
-type MagicWandSettings = (f64, bool);
+pub struct RetouchSettings {
+    pub brush_type: BrushType,
+    pub radius: u8,
+    pub strength: f64,
+}
+
+fn mk_retouch_toolbar(default_strength: usize) -> (Form, Box<dyn Fn() -> RetouchSettings>) {
+    let (type_dropdown, radius_selector) = mk_stamp_shape_fields("Brush Radius");
+
+    let strength_slider_gadget_p = NumberedSliderGadget::new_p(
+        Some("Strength"),
+        gtk::Orientation::Horizontal,
+        true,
+        1,
+        100,
+        1,
+        default_strength,
+        String::from("%"),
+    );
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .with_field(&type_dropdown)
+        .with_field(&radius_selector)
+        .with_gadget(&*strength_slider_gadget_p.borrow())
+        .spacing(20)
+        .build();
+
+    let get = move || {
+        RetouchSettings {
+            brush_type: type_dropdown.value().clone(),
+            radius: radius_selector.value() as u8,
+            strength: strength_slider_gadget_p.borrow().value() as f64 / 100.0,
+        }
+    };
+
+    (form, Box::new(get))
+}
+
+pub struct CloneSettings {
+    pub brush_type: BrushType,
+    pub radius: u8,
+    pub aligned: bool,
+    pub sample_merged: bool,
+}
+
+fn mk_clone_toolbar() -> (Form, Box<dyn Fn() -> CloneSettings>) {
+    let (type_dropdown, radius_selector) = mk_stamp_shape_fields("Brush Radius");
+    let aligned = CheckboxField::new(Some("Aligned"), true);
+    let sample_merged = CheckboxField::new(Some("Sample Merged"), false);
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .with_field(&type_dropdown)
+        .with_field(&radius_selector)
+        .with_field(&aligned)
+        .with_field(&sample_merged)
+        .spacing(20)
+        .build();
+
+    let get = move || {
+        CloneSettings {
+            brush_type: type_dropdown.value().clone(),
+            radius: radius_selector.value() as u8,
+            aligned: aligned.value(),
+            sample_merged: sample_merged.value(),
+        }
+    };
+
+    (form, Box::new(get))
+}
+
+pub struct DodgeBurnSettings {
+    pub brush_type: BrushType,
+    pub radius: u8,
+    pub range: ToneRange,
+    pub exposure: f64,
+}
+
+fn mk_dodge_burn_toolbar() -> (Form, Box<dyn Fn() -> DodgeBurnSettings>) {
+    let (type_dropdown, radius_selector) = mk_stamp_shape_fields("Brush Radius");
+    let range_dropdown = DropdownField::new(Some("Range"), ToneRange::labeled_variants().collect::<Vec<_>>(), 1);
+    range_dropdown.set_orientation(gtk::Orientation::Vertical);
+
+    let exposure_slider_gadget_p = NumberedSliderGadget::new_p(
+        Some("Exposure"),
+        gtk::Orientation::Horizontal,
+        true,
+        1,
+        100,
+        1,
+        50,
+        String::from("%"),
+    );
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .with_field(&type_dropdown)
+        .with_field(&radius_selector)
+        .with_field(&range_dropdown)
+        .with_gadget(&*exposure_slider_gadget_p.borrow())
+        .spacing(20)
+        .build();
+
+    let get = move || {
+        DodgeBurnSettings {
+            brush_type: type_dropdown.value().clone(),
+            radius: radius_selector.value() as u8,
+            range: *range_dropdown.value(),
+            exposure: exposure_slider_gadget_p.borrow().value() as f64 / 100.0,
+        }
+    };
+
+    (form, Box::new(get))
+}
+
+pub struct AirbrushSettings {
+    pub tip: AirbrushTip,
+    pub radius: u8,
+    /// Number of dots (or stamps) sprayed per tick
+    pub density: usize,
+    pub flow: f64,
+    pub seed: usize,
+}
+
+fn mk_airbrush_toolbar() -> (Form, Box<dyn Fn() -> AirbrushSettings>) {
+    let tip_dropdown = DropdownField::new(Some("Tip"), AirbrushTip::labeled_variants().collect::<Vec<_>>(), 0);
+    tip_dropdown.set_orientation(gtk::Orientation::Vertical);
+    let radius_selector = NaturalField::new(Some("Radius"), 1, 255, 1, 15);
+    radius_selector.set_orientation(gtk::Orientation::Vertical);
+    let density_selector = NaturalField::new(Some("Density"), 1, 500, 1, 20);
+    density_selector.set_orientation(gtk::Orientation::Vertical);
+    let seed_selector = NaturalField::new(Some("Seed"), 0, 9999, 1, 0);
+    seed_selector.set_orientation(gtk::Orientation::Vertical);
+
+    let flow_slider_gadget_p = NumberedSliderGadget::new_p(
+        Some("Flow"),
+        gtk::Orientation::Horizontal,
+        true,
+        1,
+        100,
+        1,
+        30,
+        String::from("%"),
+    );
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .with_field(&tip_dropdown)
+        .with_field(&radius_selector)
+        .with_field(&density_selector)
+        .with_gadget(&*flow_slider_gadget_p.borrow())
+        .with_field(&seed_selector)
+        .spacing(20)
+        .build();
+
+    let get = move || {
+        AirbrushSettings {
+            tip: *tip_dropdown.value(),
+            radius: radius_selector.value() as u8,
+            density: density_selector.value(),
+            flow: flow_slider_gadget_p.borrow().value() as f64 / 100.0,
+            seed: seed_selector.value(),
+        }
+    };
+
+    (form, Box::new(get))
+}
+
+/// A button that opens a popover for editing the custom gradient's stops,
+/// along with a function to get them (as positions from 0 to 1, and colors)
+fn mk_gradient_stops_button() -> (gtk::MenuButton, Box<dyn Fn() -> Vec<(f64, RGBA)>>) {
+    let rows_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(4)
+        .build();
+    let stops_p: Rc<RefCell<Vec<(gtk::Box, ColorField, NaturalField)>>> = Rc::new(RefCell::new(vec![]));
+
+    let add_stop = Rc::new(clone!(@strong rows_box, @strong stops_p => move |position: usize, color: RGBA| {
+        let color_field = ColorField::new(None, color);
+        let position_field = NaturalField::new(Some("At (%)"), 0, 100, 1, position);
+        let remove_button = gtk::Button::with_label("Remove");
+
+        let row = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(4)
+            .build();
+        row.append(color_field.outer_widget());
+        row.append(position_field.outer_widget());
+        row.append(&remove_button);
+        rows_box.append(&row);
+
+        remove_button.connect_clicked(clone!(@strong rows_box, @strong stops_p, @strong row => move |_| {
+            rows_box.remove(&row);
+            stops_p.borrow_mut().retain(|(other_row, _, _)| other_row != &row);
+        }));
+
+        stops_p.borrow_mut().push((row, color_field, position_field));
+    }));
+
+    add_stop(0, RGBA::new(0.0, 0.0, 0.0, 1.0));
+    add_stop(100, RGBA::new(1.0, 1.0, 1.0, 1.0));
+
+    let add_button = gtk::Button::with_label("Add Stop");
+    add_button.connect_clicked(clone!(@strong add_stop => move |_| {
+        add_stop(100, RGBA::new(1.0, 1.0, 1.0, 0.0));
+    }));
+
+    let form = Form::builder()
+        .title("Stops")
+        .with_field(&rows_box)
+        .with_field(&add_button)
+        .build();
+
+    let popover = gtk::Popover::builder()
+        .child(form.widget())
+        .build();
+
+    let button = gtk::MenuButton::builder()
+        .label("Edit Stops")
+        .valign(gtk::Align::Center)
+        .popover(&popover)
+        .build();
+
+    let get = move || {
+        stops_p.borrow().iter()
+            .map(|(_, color_field, position_field)| (position_field.value() as f64 / 100.0, color_field.value()))
+            .collect::<Vec<_>>()
+    };
+
+    (button, Box::new(get))
+}
+
+pub struct GradientSettings {
+    pub shape: GradientShape,
+    pub colors: GradientColors,
+    /// (only used if `colors` is `GradientColors::Custom`)
+    pub custom_stops: Vec<(f64, RGBA)>,
+    pub dither: bool,
+}
+
+fn mk_gradient_toolbar() -> (Form, Box<dyn Fn() -> GradientSettings>) {
+    let shape_dropdown = DropdownField::new(Some("Shape"), GradientShape::labeled_variants().collect::<Vec<_>>(), 0);
+    shape_dropdown.set_orientation(gtk::Orientation::Vertical);
+    let colors_dropdown = DropdownField::new(Some("Colors"), GradientColors::labeled_variants().collect::<Vec<_>>(), 0);
+    colors_dropdown.set_orientation(gtk::Orientation::Vertical);
+    let (stops_button, get_stops) = mk_gradient_stops_button();
+    let dither = CheckboxField::new(Some("Dither"), true);
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .with_field(&shape_dropdown)
+        .with_field(&colors_dropdown)
+        .with_field(&stops_button)
+        .with_field(&dither)
+        .spacing(20)
+        .build();
+
+    let get = move || {
+        GradientSettings {
+            shape: *shape_dropdown.value(),
+            colors: *colors_dropdown.value(),
+            custom_stops: get_stops(),
+            dither: dither.value(),
+        }
+    };
+
+    (form, Box::new(get))
+}
+
+/// The "Relative Tolerance", "Contiguous", and "8-Way" checkboxes of the
+/// magic wand and fill toolbars (relative tolerance and 8-way connectivity
+/// only make sense for contiguous regions, so they're greyed out otherwise)
+fn mk_connectivity_fields() -> (Rc<CheckboxField>, CheckboxField, Rc<CheckboxField>) {
+    let relative_tolerance = Rc::new(CheckboxField::new(Some("Relative Tolerance"), false));
+    let contiguous = CheckboxField::new(Some("Contiguous"), true);
+    let diagonal = Rc::new(CheckboxField::new(Some("8-Way"), false));
+
+    contiguous.set_toggled_hook(clone!(@strong relative_tolerance, @strong diagonal => move |now_active| {
+        relative_tolerance.set_sensitive(now_active);
+        diagonal.set_sensitive(now_active);
+    }));
+
+    (relative_tolerance, contiguous, diagonal)
+}
+
+pub struct MagicWandSettings {
+    pub tolerance: f64,
+    pub relative_tolerance: bool,
+    pub contiguous: bool,
+    pub diagonal: bool,
+}
+
 fn mk_magic_wand_toolbar() -> (Form, Box<dyn Fn() -> MagicWandSettings>) {
     let threshold_slider_gadget_p = NumberedSliderGadget::new_p(
         Some("Tolerance"),
@@ -70,25 +754,41 @@ fn mk_magic_wand_toolbar() -> (Form, Box<dyn Fn() -> MagicWandSettings>) {
         String::from("%"),
     );
 
-    let relative_tolerance = CheckboxField::new(Some("Relative Tolerance"), false);
+    let (relative_tolerance, contiguous, diagonal) = mk_connectivity_fields();
 
     let form = Form::builder()
         .orientation(gtk::Orientation::Horizontal)
         .with_gadget(&*threshold_slider_gadget_p.borrow())
-        .with_field(&relative_tolerance)
+        .with_field(&*relative_tolerance)
+        .with_field(&contiguous)
+        .with_field(&*diagonal)
         .build();
 
     let get = move || {
-        (
-            threshold_slider_gadget_p.borrow().value() as f64 / 100.0,
-            relative_tolerance.value(),
-        )
+        MagicWandSettings {
+            tolerance: threshold_slider_gadget_p.borrow().value() as f64 / 100.0,
+            relative_tolerance: relative_tolerance.value(),
+            contiguous: contiguous.value(),
+            diagonal: diagonal.value(),
+        }
     };
 
     (form, Box::new(get))
 }
 
-type FillSettings = (f64, bool);
+pub struct FillSettings {
+    pub tolerance: f64,
+    pub relative_tolerance: bool,
+    pub contiguous: bool,
+    pub diagonal: bool,
+    pub sample_merged: bool,
+    pub whole_selection: bool,
+    pub source: FillSource,
+    pub antialias: bool,
+    pub expansion: usize,
+    pub symmetry: Symmetry,
+}
+
 fn mk_fill_toolbar() -> (Form, Box<dyn Fn() -> FillSettings>) {
     let threshold_slider_gadget_p = NumberedSliderGadget::new_p(
         Some("Tolerance"),
@@ -101,18 +801,70 @@ fn mk_fill_toolbar() -> (Form, Box<dyn Fn() -> FillSettings>) {
         String::from("%"),
     );
 
-    let relative_tolerance = CheckboxField::new(Some("Relative Tolerance"), false);
+    let (relative_tolerance, contiguous, diagonal) = mk_connectivity_fields();
+    let sample_merged = CheckboxField::new(Some("Sample Merged"), false);
+    let whole_selection = CheckboxField::new(Some("Fill Selection"), false);
+    let source_dropdown = DropdownField::new(Some("Fill With"), FillSource::labeled_variants().collect::<Vec<_>>(), 0);
+    source_dropdown.set_orientation(gtk::Orientation::Vertical);
+    let antialias = CheckboxField::new(Some("Antialias"), false);
+    let expansion_field = NaturalField::new(Some("Expand (px)"), 0, 32, 1, 0);
+    let (symmetry_form, get_symmetry) = mk_symmetry_form();
 
     let form = Form::builder()
         .orientation(gtk::Orientation::Horizontal)
         .with_gadget(&*threshold_slider_gadget_p.borrow())
-        .with_field(&relative_tolerance)
+        .with_field(&*relative_tolerance)
+        .with_field(&contiguous)
+        .with_field(&*diagonal)
+        .with_field(&sample_merged)
+        .with_field(&whole_selection)
+        .with_field(&source_dropdown)
+        .with_field(&antialias)
+        .with_field(&expansion_field)
+        .with_field(symmetry_form.widget())
+        .build();
+
+    let get = move || {
+        FillSettings {
+            tolerance: threshold_slider_gadget_p.borrow().value() as f64 / 100.0,
+            relative_tolerance: relative_tolerance.value(),
+            contiguous: contiguous.value(),
+            diagonal: diagonal.value(),
+            sample_merged: sample_merged.value(),
+            whole_selection: whole_selection.value(),
+            source: *source_dropdown.value(),
+            antialias: antialias.value(),
+            expansion: expansion_field.value(),
+            symmetry: get_symmetry(),
+        }
+    };
+
+    (form, Box::new(get))
+}
+
+type EyedropperSettings = (bool, usize);
+fn mk_eyedropper_toolbar() -> (Form, Box<dyn Fn() -> EyedropperSettings>) {
+    let sample_merged = CheckboxField::new(Some("Sample Merged"), false);
+
+    let sample_sizes = vec![
+        ("1x1", 1),
+        ("3x3", 3),
+        ("5x5", 5),
+        ("11x11", 11),
+    ];
+
+    let sample_size_dropdown = DropdownField::new(Some("Sample Size"), sample_sizes, 0);
+
+    let form = Form::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .with_field(&sample_merged)
+        .with_field(&sample_size_dropdown)
         .build();
 
     let get = move || {
         (
-            threshold_slider_gadget_p.borrow().value() as f64 / 100.0,
-            relative_tolerance.value(),
+            sample_merged.value(),
+            *sample_size_dropdown.value(),
         )
     };
 
@@ -301,20 +1053,58 @@ pub struct ModeToolbar {
     empty_form: Form,
     pencil_form: Form,
     get_pencil_settings_p: Box<dyn Fn() -> PencilSettings>,
+    eraser_form: Form,
+    get_eraser_settings_p: Box<dyn Fn() -> EraserSettings>,
+    smudge_form: Form,
+    get_smudge_settings_p: Box<dyn Fn() -> RetouchSettings>,
+    blur_form: Form,
+    get_blur_settings_p: Box<dyn Fn() -> RetouchSettings>,
+    sharpen_form: Form,
+    get_sharpen_settings_p: Box<dyn Fn() -> RetouchSettings>,
+    clone_stamp_form: Form,
+    get_clone_stamp_settings_p: Box<dyn Fn() -> CloneSettings>,
+    healing_form: Form,
+    get_healing_settings_p: Box<dyn Fn() -> CloneSettings>,
+    dodge_form: Form,
+    get_dodge_settings_p: Box<dyn Fn() -> DodgeBurnSettings>,
+    burn_form: Form,
+    get_burn_settings_p: Box<dyn Fn() -> DodgeBurnSettings>,
+    airbrush_form: Form,
+    get_airbrush_settings_p: Box<dyn Fn() -> AirbrushSettings>,
+    gradient_form: Form,
+    get_gradient_settings_p: Box<dyn Fn() -> GradientSettings>,
     magic_wand_form: Form,
     get_magic_wand_settings_p: Box<dyn Fn() -> MagicWandSettings>,
     fill_form: Form,
     get_fill_settings_p: Box<dyn Fn() -> FillSettings>,
+    eyedropper_form: Form,
+    get_eyedropper_settings_p: Box<dyn Fn() -> EyedropperSettings>,
     shape_form: Form,
     get_shape_settings_p: Box<dyn Fn() -> ShapeSettings>,
     deferred: Option<DeferredFormsAndSettings>,
 }
 
 impl ModeToolbar {
-    pub fn new(widget_wrapper: &gtk::Box, active_variant: Option<MouseModeVariant>) -> Self {
-        let (pencil_form, get_pencil_settings_p) = mk_pencil_toolbar();
+    pub fn new(
+        widget_wrapper: &gtk::Box,
+        active_variant: Option<MouseModeVariant>,
+        brush_library_p: &Rc<RefCell<BrushLibrary>>,
+        brush_presets_p: &Rc<RefCell<BrushPresets>>,
+    ) -> Self {
+        let (pencil_form, get_pencil_settings_p) = mk_pencil_toolbar(brush_library_p, brush_presets_p);
+        let (eraser_form, get_eraser_settings_p) = mk_eraser_toolbar();
+        let (smudge_form, get_smudge_settings_p) = mk_retouch_toolbar(50);
+        let (blur_form, get_blur_settings_p) = mk_retouch_toolbar(50);
+        let (sharpen_form, get_sharpen_settings_p) = mk_retouch_toolbar(20);
+        let (clone_stamp_form, get_clone_stamp_settings_p) = mk_clone_toolbar();
+        let (healing_form, get_healing_settings_p) = mk_clone_toolbar();
+        let (dodge_form, get_dodge_settings_p) = mk_dodge_burn_toolbar();
+        let (burn_form, get_burn_settings_p) = mk_dodge_burn_toolbar();
+        let (airbrush_form, get_airbrush_settings_p) = mk_airbrush_toolbar();
+        let (gradient_form, get_gradient_settings_p) = mk_gradient_toolbar();
         let (magic_wand_form, get_magic_wand_settings_p) = mk_magic_wand_toolbar();
         let (fill_form, get_fill_settings_p) = mk_fill_toolbar();
+        let (eyedropper_form, get_eyedropper_settings_p) = mk_eyedropper_toolbar();
         let (shape_form, get_shape_settings_p) = mk_shape_toolbar();
 
         let mut res = ModeToolbar {
@@ -323,10 +1113,32 @@ impl ModeToolbar {
             empty_form: Form::builder().build(),
             pencil_form,
             get_pencil_settings_p,
+            eraser_form,
+            get_eraser_settings_p,
+            smudge_form,
+            get_smudge_settings_p,
+            blur_form,
+            get_blur_settings_p,
+            sharpen_form,
+            get_sharpen_settings_p,
+            clone_stamp_form,
+            get_clone_stamp_settings_p,
+            healing_form,
+            get_healing_settings_p,
+            dodge_form,
+            get_dodge_settings_p,
+            burn_form,
+            get_burn_settings_p,
+            airbrush_form,
+            get_airbrush_settings_p,
+            gradient_form,
+            get_gradient_settings_p,
             magic_wand_form,
             get_magic_wand_settings_p,
             fill_form,
             get_fill_settings_p,
+            eyedropper_form,
+            get_eyedropper_settings_p,
             shape_form,
             get_shape_settings_p,
             deferred: None,
@@ -339,11 +1151,24 @@ impl ModeToolbar {
     fn variant_to_form(&self, variant: &MouseModeVariant) -> &Form {
         match variant {
             MouseModeVariant::Cursor => &self.empty_form,
-            MouseModeVariant::Eyedropper => &self.empty_form,
+            MouseModeVariant::Eyedropper => &self.eyedropper_form,
             MouseModeVariant::MagicWand => &self.magic_wand_form,
             MouseModeVariant::Pencil => &self.pencil_form,
+            MouseModeVariant::Eraser => &self.eraser_form,
+            MouseModeVariant::Smudge => &self.smudge_form,
+            MouseModeVariant::Blur => &self.blur_form,
+            MouseModeVariant::Sharpen => &self.sharpen_form,
+            MouseModeVariant::CloneStamp => &self.clone_stamp_form,
+            MouseModeVariant::Healing => &self.healing_form,
+            MouseModeVariant::Dodge => &self.dodge_form,
+            MouseModeVariant::Burn => &self.burn_form,
+            MouseModeVariant::Airbrush => &self.airbrush_form,
+            // lines are stroked with the pencil's brush
+            MouseModeVariant::Line => &self.pencil_form,
+            MouseModeVariant::Pen => &self.empty_form,
             MouseModeVariant::RectangleSelect => &self.empty_form,
             MouseModeVariant::Fill => &self.fill_form,
+            MouseModeVariant::Gradient => &self.gradient_form,
             MouseModeVariant::FreeTransform => &self.deferred.as_ref().unwrap().free_transform_form,
             MouseModeVariant::Shape => &self.shape_form,
             MouseModeVariant::Text => &self.empty_form,
@@ -369,6 +1194,42 @@ impl ModeToolbar {
         (self.get_pencil_settings_p)()
     }
 
+    pub fn get_eraser_settings(&self) -> EraserSettings {
+        (self.get_eraser_settings_p)()
+    }
+
+    pub fn get_retouch_settings(&self, tool: RetouchTool) -> RetouchSettings {
+        match tool {
+            RetouchTool::Smudge => (self.get_smudge_settings_p)(),
+            RetouchTool::Blur => (self.get_blur_settings_p)(),
+            RetouchTool::Sharpen => (self.get_sharpen_settings_p)(),
+        }
+    }
+
+    pub fn get_clone_settings(&self, heal: bool) -> CloneSettings {
+        if heal {
+            (self.get_healing_settings_p)()
+        } else {
+            (self.get_clone_stamp_settings_p)()
+        }
+    }
+
+    pub fn get_dodge_burn_settings(&self, burn: bool) -> DodgeBurnSettings {
+        if burn {
+            (self.get_burn_settings_p)()
+        } else {
+            (self.get_dodge_settings_p)()
+        }
+    }
+
+    pub fn get_airbrush_settings(&self) -> AirbrushSettings {
+        (self.get_airbrush_settings_p)()
+    }
+
+    pub fn get_gradient_settings(&self) -> GradientSettings {
+        (self.get_gradient_settings_p)()
+    }
+
     pub fn get_magic_wand_settings(&self) -> MagicWandSettings {
         (self.get_magic_wand_settings_p)()
     }
@@ -377,6 +1238,10 @@ impl ModeToolbar {
         (self.get_fill_settings_p)()
     }
 
+    pub fn get_eyedropper_settings(&self) -> EyedropperSettings {
+        (self.get_eyedropper_settings_p)()
+    }
+
     pub fn get_free_transform_settings(&self) -> FreeTransformSettings {
         (self.deferred.as_ref().unwrap().get_free_transform_settings_p)()
     }
@@ -0,0 +1,269 @@
+use super::{Canvas, Toolbar};
+use crate::image::vector_path::{distance, Anchor, Subpath, VectorPath};
+use crate::image::undo::action::ActionName;
+
+use gtk::gdk::ModifierType;
+use gtk::cairo::Context;
+use std::f64::consts::TAU;
+
+/// Radius (in screen pixels) of the anchor and handle markers
+const MARKER_RADIUS: f64 = 5.0;
+
+#[derive(Clone, Copy)]
+enum PenDrag {
+    None,
+    /// Pulling (symmetric) handles out of the anchor at
+    /// (subpath index, anchor index) that was just placed
+    NewAnchor(usize, usize),
+    /// Moving the anchor at (subpath index, anchor index)
+    Anchor(usize, usize),
+    /// Moving the incoming (0) or outgoing (1) handle of
+    /// the anchor at (subpath index, anchor index)
+    Handle(usize, usize, usize),
+}
+
+/// Builds and edits the canvas's active path (the paths
+/// themselves are kept with the image)
+#[derive(Clone)]
+pub struct PenState {
+    drag: PenDrag,
+    last_point: (f64, f64),
+}
+
+/// The (subpath index, anchor index) of the anchor within reach of `point`
+fn anchor_near(path: &VectorPath, point: (f64, f64), reach: f64) -> Option<(usize, usize)> {
+    path.subpaths.iter().enumerate()
+        .flat_map(|(s, subpath)| subpath.anchors.iter().enumerate().map(move |(a, anchor)| ((s, a), anchor)))
+        .find(|(_, anchor)| distance(anchor.point, point) <= reach)
+        .map(|(index, _)| index)
+}
+
+/// The (subpath index, anchor index, handle index) of the pulled-out
+/// handle within reach of `point`
+fn handle_near(path: &VectorPath, point: (f64, f64), reach: f64) -> Option<(usize, usize, usize)> {
+    path.subpaths.iter().enumerate()
+        .flat_map(|(s, subpath)| subpath.anchors.iter().enumerate().map(move |(a, anchor)| ((s, a), anchor)))
+        .flat_map(|((s, a), anchor)| [((s, a, 0), anchor.handle_in, anchor.point), ((s, a, 1), anchor.handle_out, anchor.point)])
+        .find(|(_, handle, anchor_point)| handle != anchor_point && distance(*handle, point) <= reach)
+        .map(|(index, _, _)| index)
+}
+
+impl PenState {
+    pub fn default(_canvas: &Canvas) -> PenState {
+        Self::default_no_canvas()
+    }
+
+    pub fn default_no_canvas() -> PenState {
+        PenState {
+            drag: PenDrag::None,
+            last_point: (0.0, 0.0),
+        }
+    }
+}
+
+impl super::MouseModeState for PenState {
+    fn handle_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        let point = canvas.cursor_pos_pix_f();
+        let reach = MARKER_RADIUS / *canvas.zoom();
+        self.last_point = point;
+        self.drag = PenDrag::None;
+
+        if let Some(path) = canvas.active_path_mut() {
+            if let Some((s, a, k)) = handle_near(path, point, reach) {
+                self.drag = PenDrag::Handle(s, a, k);
+            } else if let Some((s, a)) = anchor_near(path, point, reach) {
+                let is_last = s == path.subpaths.len() - 1;
+                let subpath = &mut path.subpaths[s];
+
+                // clicking the first anchor of the subpath being drawn closes it
+                if is_last && a == 0 && !subpath.closed && subpath.anchors.len() > 2 {
+                    subpath.closed = true;
+                    self.drag = PenDrag::NewAnchor(s, a);
+                } else {
+                    self.drag = PenDrag::Anchor(s, a);
+                }
+            }
+        }
+
+        if let PenDrag::None = self.drag {
+            if let Some(i) = canvas.paths().iter().position(|path| anchor_near(path, point, reach).is_some()) {
+                // switch to another path by clicking one of its anchors
+                canvas.set_active_path_index(Some(i));
+            } else if let Some(path) = canvas.active_path_mut() {
+                // extend the subpath being drawn (or start another)
+                match path.subpaths.last_mut() {
+                    Some(subpath) if !subpath.closed => subpath.anchors.push(Anchor::new(point)),
+                    _ => path.subpaths.push(Subpath::new(Anchor::new(point))),
+                }
+
+                let s = path.subpaths.len() - 1;
+                self.drag = PenDrag::NewAnchor(s, path.subpaths[s].anchors.len() - 1);
+            } else {
+                canvas.add_path(VectorPath {
+                    subpaths: vec![Subpath::new(Anchor::new(point))],
+                });
+                self.drag = PenDrag::NewAnchor(0, 0);
+            }
+        }
+
+        canvas.update();
+    }
+
+    fn handle_drag_update(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        let point = canvas.cursor_pos_pix_f();
+        let reach = MARKER_RADIUS / *canvas.zoom();
+        let (dx, dy) = (point.0 - self.last_point.0, point.1 - self.last_point.1);
+        self.last_point = point;
+
+        let Some(path) = canvas.active_path_mut() else {
+            return;
+        };
+
+        match self.drag {
+            PenDrag::None => (),
+            PenDrag::NewAnchor(s, a) => {
+                let anchor = &mut path.subpaths[s].anchors[a];
+
+                if distance(anchor.point, point) < reach {
+                    *anchor = Anchor::new(anchor.point);
+                } else {
+                    anchor.set_symmetric_handles(point);
+                }
+            },
+            PenDrag::Anchor(s, a) => path.subpaths[s].anchors[a].translate(dx, dy),
+            PenDrag::Handle(s, a, k) => {
+                let anchor = &mut path.subpaths[s].anchors[a];
+                let (handle, opposite) = if k == 0 {
+                    (&mut anchor.handle_in, &mut anchor.handle_out)
+                } else {
+                    (&mut anchor.handle_out, &mut anchor.handle_in)
+                };
+                *handle = point;
+
+                // keep smooth anchors smooth (unless Ctrl is held)
+                let opposite_length = distance(*opposite, anchor.point);
+                let length = distance(point, anchor.point);
+                if !mod_keys.intersects(ModifierType::CONTROL_MASK) && opposite_length > 0.0 && length > 0.0 {
+                    let scale = opposite_length / length;
+                    *opposite = (
+                        anchor.point.0 - (point.0 - anchor.point.0) * scale,
+                        anchor.point.1 - (point.1 - anchor.point.1) * scale,
+                    );
+                }
+            },
+        }
+
+        canvas.update();
+    }
+
+    fn handle_drag_end(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        self.drag = PenDrag::None;
+        canvas.commit_path_changes(ActionName::EditPath);
+        canvas.update();
+    }
+
+    fn handle_right_drag_start(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        let point = canvas.cursor_pos_pix_f();
+        let reach = MARKER_RADIUS / *canvas.zoom();
+
+        let Some(path) = canvas.active_path_mut() else {
+            return;
+        };
+
+        if let Some((s, a)) = anchor_near(path, point, reach) {
+            // remove the anchor (and its subpath, if that was the last of it)
+            let subpath = &mut path.subpaths[s];
+            subpath.anchors.remove(a);
+            if subpath.anchors.len() < 3 {
+                subpath.closed = false;
+            }
+            if subpath.anchors.is_empty() {
+                path.subpaths.remove(s);
+            }
+
+            if path.is_empty() {
+                canvas.delete_active_path();
+            }
+        } else {
+            // finish the path (the next click starts a new one)
+            canvas.set_active_path_index(None);
+        }
+
+        self.drag = PenDrag::None;
+        canvas.commit_path_changes(ActionName::EditPath);
+        canvas.update();
+    }
+
+    fn handle_motion(&mut self, _mod_keys: &ModifierType, canvas: &mut Canvas, _toolbar: &mut Toolbar) {
+        canvas.update();
+    }
+
+    fn draw(&self, canvas: &Canvas, cr: &Context, _toolbar: &mut Toolbar) {
+        let zoom = *canvas.zoom();
+        let active_index = canvas.active_path_index();
+
+        let _ = cr.save();
+        {
+            cr.set_line_width(1.0 / zoom);
+
+            // every path, with the active one highlighted
+            for (i, path) in canvas.paths().iter().enumerate() {
+                cr.new_path();
+                path.append_to(cr);
+
+                if Some(i) == active_index {
+                    cr.set_source_rgb(0.0, 0.6, 1.0);
+                } else {
+                    cr.set_source_rgb(0.5, 0.5, 0.5);
+                }
+                let _ = cr.stroke();
+            }
+
+            if let Some(path) = canvas.active_path() {
+                // where the next anchor would go
+                if let (PenDrag::None, Some(subpath)) = (self.drag, path.subpaths.last()) {
+                    if let (false, Some(last)) = (subpath.closed, subpath.anchors.last()) {
+                        let (x, y) = canvas.cursor_pos_pix_f();
+                        cr.set_dash(&[4.0 / zoom, 4.0 / zoom], 0.0);
+                        cr.set_source_rgb(0.0, 0.6, 1.0);
+                        cr.move_to(last.point.0, last.point.1);
+                        cr.curve_to(last.handle_out.0, last.handle_out.1, x, y, x, y);
+                        let _ = cr.stroke();
+                        cr.set_dash(&[], 0.0);
+                    }
+                }
+
+                // the handles (tied to their anchors), then the anchors
+                for anchor in path.subpaths.iter().flat_map(|subpath| subpath.anchors.iter()) {
+                    for (x, y) in [anchor.handle_in, anchor.handle_out] {
+                        if (x, y) == anchor.point {
+                            continue;
+                        }
+
+                        cr.set_source_rgb(0.0, 0.6, 1.0);
+                        cr.move_to(anchor.point.0, anchor.point.1);
+                        cr.line_to(x, y);
+                        let _ = cr.stroke();
+
+                        cr.arc(x, y, MARKER_RADIUS / zoom * 0.8, 0.0, TAU);
+                        cr.set_source_rgb(1.0, 1.0, 1.0);
+                        let _ = cr.fill_preserve();
+                        cr.set_source_rgb(0.0, 0.6, 1.0);
+                        let _ = cr.stroke();
+                    }
+                }
+
+                for anchor in path.subpaths.iter().flat_map(|subpath| subpath.anchors.iter()) {
+                    let (x, y) = anchor.point;
+                    let r = MARKER_RADIUS / zoom;
+                    cr.rectangle(x - r, y - r, 2.0 * r, 2.0 * r);
+                    cr.set_source_rgb(1.0, 1.0, 1.0);
+                    let _ = cr.fill_preserve();
+                    cr.set_source_rgb(0.0, 0.0, 0.0);
+                    let _ = cr.stroke();
+                }
+            }
+        }
+        let _ = cr.restore();
+    }
+}
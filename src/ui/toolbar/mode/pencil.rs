@@ -1,9 +1,17 @@
 mod spline;
+mod stabilizer;
+mod pixel_perfect;
 
 use super::{Canvas, Toolbar};
-use crate::image::{brush::Brush, ImageLike};
+use crate::image::{brush::{Brush, BrushDynamics, BrushType, StrokeRng}, ImageLike, LayerIndex};
+use crate::image::blend::BlendingMode;
+use crate::image::filter::ToneRange;
 use crate::image::undo::action::ActionName;
+use super::symmetry::Symmetry;
 use spline::{IncrementalSplineSnapshot, SplineSegment3, SplineSegment4, SplineSegment};
+use stabilizer::StabilizerState;
+use pixel_perfect::PixelPerfectPath;
+pub use stabilizer::{Stabilizer, StabilizerType};
 
 use gtk::gdk::ModifierType;
 use gtk::cairo::{Context, LineCap};
@@ -16,11 +24,109 @@ enum ClickType {
     Right,
 }
 
-impl Toolbar {
-    fn get_brush_from_click_type(&mut self, click_type: ClickType) -> &Brush {
-        match click_type {
-            ClickType::Left => self.get_primary_brush(),
-            ClickType::Right => self.get_secondary_brush(),
+/// Brushes that rework the pixels beneath them, rather than painting
+#[derive(Clone, Copy, PartialEq)]
+pub enum RetouchTool {
+    /// Drags the color picked up under the brush along the stroke
+    Smudge,
+    Blur,
+    Sharpen,
+}
+
+/// Whether strokes draw the pencil's brush, erase, retouch, clone, or dodge/burn
+/// (the other tools share all of the pencil's stroke logic)
+#[derive(Clone, Copy, PartialEq)]
+enum StrokeTool {
+    Pencil,
+    Eraser,
+    Retouch(RetouchTool),
+    /// Copies pixels from the canvas's clone source (`heal` matches
+    /// the copied pixels' colors to their new surroundings)
+    Clone { heal: bool },
+    /// Lightens (or, if `burn`, darkens) the pixels beneath it
+    DodgeBurn { burn: bool },
+}
+
+impl StrokeTool {
+    /// The brush to stamp (and how to blend it)
+    fn brush_mut<'a>(
+        &self,
+        canvas: &Canvas,
+        toolbar: &'a mut Toolbar,
+        click_type: ClickType,
+    ) -> (&'a mut Brush, BlendingMode) {
+        match self {
+            StrokeTool::Pencil => {
+                let blending_mode = toolbar.get_blending_mode();
+                let brush = match click_type {
+                    ClickType::Left => toolbar.get_primary_brush_mut(),
+                    ClickType::Right => toolbar.get_secondary_brush_mut(),
+                };
+                (brush, blending_mode)
+            },
+            StrokeTool::Eraser => {
+                let on_base_layer = *canvas.layered_image().active_layer_index() == LayerIndex::BaseLayer;
+                toolbar.get_eraser_brush_mut(on_base_layer)
+            },
+            // only the brush's shape is used
+            StrokeTool::Retouch(tool) => (toolbar.get_retouch_brush_mut(*tool), BlendingMode::Paint),
+            StrokeTool::Clone { heal } => (toolbar.get_clone_brush_mut(*heal), BlendingMode::Paint),
+            StrokeTool::DodgeBurn { burn } => (toolbar.get_dodge_burn_brush_mut(*burn), BlendingMode::Paint),
+        }
+    }
+
+    fn dynamics(&self, toolbar: &Toolbar) -> BrushDynamics {
+        match self {
+            StrokeTool::Pencil => toolbar.get_brush_dynamics(),
+            StrokeTool::Eraser | StrokeTool::Retouch(_) | StrokeTool::Clone { .. } |
+            StrokeTool::DodgeBurn { .. } => BrushDynamics::default(),
+        }
+    }
+
+    fn antialias(&self, toolbar: &Toolbar) -> bool {
+        match self {
+            StrokeTool::Pencil => toolbar.get_pencil_antialias() && !toolbar.get_pencil_pixel_perfect(),
+            StrokeTool::Eraser | StrokeTool::Retouch(_) | StrokeTool::Clone { .. } |
+            StrokeTool::DodgeBurn { .. } => false,
+        }
+    }
+
+    fn pixel_perfect(&self, toolbar: &Toolbar) -> bool {
+        match self {
+            StrokeTool::Pencil => toolbar.get_pencil_pixel_perfect(),
+            _ => false,
+        }
+    }
+
+    fn stabilizer(&self, toolbar: &Toolbar) -> Stabilizer {
+        match self {
+            StrokeTool::Pencil => toolbar.get_pencil_stabilizer(),
+            StrokeTool::Eraser | StrokeTool::Retouch(_) | StrokeTool::Clone { .. } |
+            StrokeTool::DodgeBurn { .. } => Stabilizer::default(),
+        }
+    }
+
+    fn symmetry(&self, toolbar: &Toolbar) -> Symmetry {
+        match self {
+            StrokeTool::Pencil => toolbar.get_pencil_symmetry(),
+            StrokeTool::Eraser => toolbar.get_eraser_symmetry(),
+            // a smudge can't carry color to several places at once
+            StrokeTool::Retouch(_) | StrokeTool::Clone { .. } | StrokeTool::DodgeBurn { .. } => Symmetry::default(),
+        }
+    }
+
+    fn action_name(&self, brush: &Brush) -> ActionName {
+        match (self, brush.brush_type()) {
+            (StrokeTool::Eraser, _) => ActionName::Eraser,
+            (StrokeTool::Retouch(RetouchTool::Smudge), _) => ActionName::Smudge,
+            (StrokeTool::Retouch(RetouchTool::Blur), _) => ActionName::Blur,
+            (StrokeTool::Retouch(RetouchTool::Sharpen), _) => ActionName::Sharpen,
+            (StrokeTool::Clone { heal: false }, _) => ActionName::CloneStamp,
+            (StrokeTool::Clone { heal: true }, _) => ActionName::Healing,
+            (StrokeTool::DodgeBurn { burn: false }, _) => ActionName::Dodge,
+            (StrokeTool::DodgeBurn { burn: true }, _) => ActionName::Burn,
+            (StrokeTool::Pencil, BrushType::Pixelate) => ActionName::Pixelate,
+            (StrokeTool::Pencil, _) => ActionName::Pencil,
         }
     }
 }
@@ -29,10 +135,30 @@ impl Toolbar {
 enum PencilMode {
     PencilUp,
     PencilDown,
+    /// Dragging the symmetry center (instead of drawing)
+    MovingSymmetryCenter,
+    /// Dragging the clone source (instead of drawing)
+    SettingCloneSource,
+    /// Ignoring a clone stroke made before any source was set
+    NoCloneSource,
 }
 
+/// A point along a stroke that the brush will be stamped at
 #[derive(Clone, Copy)]
+struct Stamp {
+    /// The pixel to center the brush on
+    pixel: (i32, i32),
+    /// The exact point to center (anti-aliased) brushes on
+    point: (f64, f64),
+    /// The cursor's (smoothed) speed at this point
+    speed: f64,
+    /// Distance (in pixels) from the start of the stroke
+    distance: f64,
+}
+
+#[derive(Clone)]
 pub struct PencilState {
+    tool: StrokeTool,
     /// The (real-number) pixel coordinates of the
     /// last-drawn-to position
     last_cursor_pos_pix: (f64, f64),
@@ -46,40 +172,145 @@ pub struct PencilState {
     /// Serves as a queue of control points before the spline
     /// segment can be eagerly drawn
     spline_snapshot: IncrementalSplineSnapshot,
+    /// Source of the brush dynamics' jitter (reseeded each stroke)
+    rng: StrokeRng,
+    /// Where the brush was last stamped during this stroke
+    last_stamp_point: Option<(f64, f64)>,
+    /// Direction (in radians) the stroke was last heading
+    stroke_angle: f64,
+    /// The cursor's speed (pixels per input event), smoothed
+    /// over the last few events
+    speed: f64,
+    /// Length of the stroke so far (through the last queued stamp)
+    stroke_length: f64,
+    /// Stamps that are too close to the end of the stroke to be
+    /// drawn yet (they might need to be faded out)
+    pending_stamps: Vec<Stamp>,
+    /// Whether the current stroke follows the cursor's exact (sub-pixel)
+    /// position, drawing round brushes with anti-aliased edges
+    antialias: bool,
+    /// How the current stroke is mirrored
+    symmetry: Symmetry,
+    /// Smooths the current stroke's path
+    stabilizer: StabilizerState,
+    /// Whether the current stroke removes the corners of its
+    /// diagonal steps (when drawing single pixels)
+    pixel_perfect: bool,
+    /// The pixels the current (pixel-perfect) stroke has passed through
+    pixel_path: PixelPerfectPath,
 }
 
 impl PencilState {
     pub fn default(canvas: &Canvas) -> PencilState {
         let last_cursor_pos_pix = canvas.last_cursor_pos_pix();
         PencilState {
+            tool: StrokeTool::Pencil,
             last_cursor_pos_pix,
             mode: PencilMode::PencilUp,
             dist_till_resample: 0.0,
             spline_snapshot: IncrementalSplineSnapshot::NoPoints,
+            rng: StrokeRng::new(0),
+            last_stamp_point: None,
+            stroke_angle: 0.0,
+            speed: 0.0,
+            stroke_length: 0.0,
+            pending_stamps: vec![],
+            antialias: false,
+            symmetry: Symmetry::default(),
+            stabilizer: StabilizerState::new(Stabilizer::default()),
+            pixel_perfect: false,
+            pixel_path: PixelPerfectPath::new(),
         }
     }
 
     pub fn default_no_canvas() -> PencilState {
         PencilState {
+            tool: StrokeTool::Pencil,
             last_cursor_pos_pix: (0.0, 0.0),
             mode: PencilMode::PencilUp,
             dist_till_resample: 0.0,
             spline_snapshot: IncrementalSplineSnapshot::NoPoints,
+            rng: StrokeRng::new(0),
+            last_stamp_point: None,
+            stroke_angle: 0.0,
+            speed: 0.0,
+            stroke_length: 0.0,
+            pending_stamps: vec![],
+            antialias: false,
+            symmetry: Symmetry::default(),
+            stabilizer: StabilizerState::new(Stabilizer::default()),
+            pixel_perfect: false,
+            pixel_path: PixelPerfectPath::new(),
+        }
+    }
+
+    pub fn eraser(canvas: &Canvas) -> PencilState {
+        PencilState {
+            tool: StrokeTool::Eraser,
+            ..Self::default(canvas)
+        }
+    }
+
+    pub fn eraser_no_canvas() -> PencilState {
+        PencilState {
+            tool: StrokeTool::Eraser,
+            ..Self::default_no_canvas()
+        }
+    }
+
+    pub fn retouch(canvas: &Canvas, tool: RetouchTool) -> PencilState {
+        PencilState {
+            tool: StrokeTool::Retouch(tool),
+            ..Self::default(canvas)
+        }
+    }
+
+    pub fn retouch_no_canvas(tool: RetouchTool) -> PencilState {
+        PencilState {
+            tool: StrokeTool::Retouch(tool),
+            ..Self::default_no_canvas()
+        }
+    }
+
+    pub fn clone_stamp(canvas: &Canvas, heal: bool) -> PencilState {
+        PencilState {
+            tool: StrokeTool::Clone { heal },
+            ..Self::default(canvas)
+        }
+    }
+
+    pub fn clone_stamp_no_canvas(heal: bool) -> PencilState {
+        PencilState {
+            tool: StrokeTool::Clone { heal },
+            ..Self::default_no_canvas()
+        }
+    }
+
+    pub fn dodge_burn(canvas: &Canvas, burn: bool) -> PencilState {
+        PencilState {
+            tool: StrokeTool::DodgeBurn { burn },
+            ..Self::default(canvas)
+        }
+    }
+
+    pub fn dodge_burn_no_canvas(burn: bool) -> PencilState {
+        PencilState {
+            tool: StrokeTool::DodgeBurn { burn },
+            ..Self::default_no_canvas()
         }
     }
 
     /// Claims `distance` of the draw-length
     /// (adjusts `self.dist_till_resample`), returning
     /// the number of sample points that lie along that distance
-    fn get_and_claim_num_points_to_sample(&mut self, distance: f64, brush: &Brush) -> usize {
+    fn get_and_claim_num_points_to_sample(&mut self, distance: f64, brush: &Brush, dynamics: &BrushDynamics) -> usize {
         self.dist_till_resample -= distance;
         if self.dist_till_resample > 0.0 {
             return 0; // no points to draw
         }
 
-        const SAMPLE_DIST_FACTOR: f64 = 0.1;
         // distance (in pixels) between two samples
-        let sample_distance = (brush.radius() as f64).powf(1.05) * SAMPLE_DIST_FACTOR;
+        let sample_distance = dynamics.sample_distance(brush.radius());
         let res = (-self.dist_till_resample / sample_distance).floor() as usize + 1;
         self.dist_till_resample = self.dist_till_resample % sample_distance + sample_distance;
 
@@ -99,27 +330,23 @@ impl PencilState {
         let dy = line_pt0.1 - line_pt1.1;
         let d = (dx.powi(2) + dy.powi(2)).sqrt();
 
-        let blending_mode = toolbar.get_blending_mode();
-        let brush = toolbar.get_brush_from_click_type(click_type);
+        let dynamics = self.tool.dynamics(toolbar);
+        let (brush, _) = self.tool.brush_mut(canvas, toolbar, click_type);
 
-        let num_points = self.get_and_claim_num_points_to_sample(d, brush);
-        let target_pixels = pixels_along_segment(line_pt0, line_pt1, num_points);
+        let num_points = self.get_and_claim_num_points_to_sample(d, brush, &dynamics);
+        let target_points = points_along_segment(line_pt0, line_pt1, num_points);
 
-        target_pixels.iter().for_each(|&(x, y)| {
-            let x_offset = (brush.brush_image.width() as i32 - 1) / 2;
-            let y_offset = (brush.brush_image.height() as i32 - 1) / 2;
-            canvas.sample_image_respecting_pencil_mask(
-                &brush.brush_image,
-                &blending_mode,
-                x - x_offset,
-                y - y_offset
-            );
-        });
+        // straight lines are drawn all at once, so have no speed of their own
+        self.queue_stamps(&target_points, |x| x as i32, self.speed, self.speed, canvas, toolbar, click_type);
     }
 
     fn draw_straight_line_to_cursor(&mut self, canvas: &mut Canvas, toolbar: &mut Toolbar, click_type: ClickType) {
         let line_pt0 = self.last_cursor_pos_pix;
-        let line_pt1 = canvas.cursor_pos_pix_f_rounded();
+        let line_pt1 = if self.antialias {
+            canvas.cursor_pos_pix_f()
+        } else {
+            canvas.cursor_pos_pix_f_rounded()
+        };
         self.last_cursor_pos_pix = line_pt1;
 
         self.draw_line_between(line_pt0, line_pt1, canvas, toolbar, click_type);
@@ -137,49 +364,33 @@ impl PencilState {
         self.last_cursor_pos_pix = segment.endpoint();
         let d = segment.rough_length();
 
-        let blending_mode = toolbar.get_blending_mode();
-        let brush = toolbar.get_brush_from_click_type(click_type);
+        let dynamics = self.tool.dynamics(toolbar);
+        let (brush, _) = self.tool.brush_mut(canvas, toolbar, click_type);
 
-        let num_points = self.get_and_claim_num_points_to_sample(d, brush);
-        let target_pixels = segment.sample_n_pixels(num_points);
+        let num_points = self.get_and_claim_num_points_to_sample(d, brush, &dynamics);
+        let target_points = segment.sample_n_points(num_points);
 
-        target_pixels.iter().for_each(|&(x, y)| {
-            let x_offset = (brush.brush_image.width() as i32 - 1) / 2;
-            let y_offset = (brush.brush_image.height() as i32 - 1) / 2;
-            canvas.sample_image_respecting_pencil_mask(
-                &brush.brush_image,
-                &blending_mode,
-                x as i32 - x_offset,
-                y as i32 - y_offset
-            );
-        });
+        // each segment spans (roughly) one input event
+        const SPEED_SMOOTHING: f64 = 0.3;
+        let last_speed = self.speed;
+        self.speed += (d - self.speed) * SPEED_SMOOTHING;
+
+        self.queue_stamps(&target_points, |x| x.round() as i32, last_speed, self.speed, canvas, toolbar, click_type);
     }
 
     fn draw_to_cursor(&mut self, canvas: &mut Canvas, toolbar: &mut Toolbar, click_type: ClickType) {
-        // We're starting a new stroke: draw a single brush sample
-        // at the cursor to not leave the user hanging
+        let last_brush_point = self.stabilizer.brush_point();
+        let new_point = self.stabilized_stroke_point(canvas);
+
         if let IncrementalSplineSnapshot::NoPoints = self.spline_snapshot {
-            let target_pixels = vec![
-                    canvas.cursor_pos_pix_i()
-                ].into_iter()
-                .collect::<Vec<_>>();
-
-            let blending_mode = toolbar.get_blending_mode();
-            let brush = toolbar.get_brush_from_click_type(click_type);
-
-            target_pixels.iter().for_each(|&(x, y)| {
-                let x_offset = (brush.brush_image.width() as i32 - 1) / 2;
-                let y_offset = (brush.brush_image.height() as i32 - 1) / 2;
-                canvas.sample_image_respecting_pencil_mask(
-                    &brush.brush_image,
-                    &blending_mode,
-                    x as i32 - x_offset,
-                    y as i32 - y_offset
-                );
-            });
-        }
+            // We're starting a new stroke: draw a single brush sample
+            // at the cursor to not leave the user hanging
+            let target_points = vec![new_point];
 
-        let new_point = canvas.cursor_pos_pix_i();
+            self.queue_stamps(&target_points, |x| x as i32, self.speed, self.speed, canvas, toolbar, click_type);
+        } else if !self.stabilizer.is_off() && self.stabilizer.brush_point() == last_brush_point {
+            return; // the brush hasn't moved
+        }
 
         if let Some(segment) = self.spline_snapshot.append_point(new_point) {
             self.draw_spline_segment(&segment, canvas, toolbar, click_type);
@@ -190,16 +401,16 @@ impl PencilState {
         match self.spline_snapshot {
             IncrementalSplineSnapshot::NoPoints => (),
             IncrementalSplineSnapshot::One(pt) => {
-                self.last_cursor_pos_pix = (pt.0 as f64, pt.1 as f64);
+                self.last_cursor_pos_pix = pt;
                 self.draw_straight_line_to_cursor(canvas, toolbar, click_type)
             },
             IncrementalSplineSnapshot::Two(last_last, last) => {
-                let cursor_pos = canvas.cursor_pos_pix_i();
+                let cursor_pos = self.stroke_point(canvas);
                 let segment = SplineSegment4::from_grouped(last_last, last, cursor_pos, cursor_pos);
                 self.draw_spline_segment(&segment, canvas, toolbar, click_type);
             },
             IncrementalSplineSnapshot::Three(last_last_last, last_last, last) => {
-                let cursor_pos = canvas.cursor_pos_pix_i();
+                let cursor_pos = self.stroke_point(canvas);
                 let segment1 = SplineSegment4::from_grouped(last_last_last, last_last, last, cursor_pos);
                 let segment2 = SplineSegment4::from_grouped(last_last, last, cursor_pos, cursor_pos);
                 self.draw_spline_segment(&segment1, canvas, toolbar, click_type);
@@ -212,27 +423,32 @@ impl PencilState {
     fn straight_line_visual_cue_fn(&mut self, canvas: &Canvas) -> Box<dyn Fn(&Context)> {
         let zoom = *canvas.zoom();
 
-        let (x0, y0) = self.last_cursor_pos_pix;
-        let (x1, y1) = canvas.cursor_pos_pix_f();
+        let pt0 = self.last_cursor_pos_pix;
+        let pt1 = canvas.cursor_pos_pix_f();
 
-        Box::new(move |cr| {
-            const LINE_WIDTH: f64 = 3.0;
-            const LINE_BORDER_FACTOR: f64 = 0.4;
+        Box::new(move |cr| draw_guide_line(cr, zoom, pt0, pt1))
+    }
 
-            cr.set_line_cap(LineCap::Round);
-            cr.set_line_width(LINE_WIDTH / zoom);
+    /// The cursor's position, rounded down to
+    /// the pixel unless the stroke is anti-aliased
+    fn stroke_point(&self, canvas: &Canvas) -> (f64, f64) {
+        if self.antialias {
+            canvas.cursor_pos_pix_f()
+        } else {
+            canvas.cursor_pos_pix_f_rounded()
+        }
+    }
 
-            cr.set_source_rgba(0.25, 0.25, 0.25, 0.75);
-            cr.move_to(x0, y0);
-            cr.line_to(x1, y1);
-            let _ = cr.stroke();
+    /// The stabilized cursor's position (where the brush is drawn to),
+    /// rounded down to the pixel unless the stroke is anti-aliased
+    fn stabilized_stroke_point(&mut self, canvas: &Canvas) -> (f64, f64) {
+        let (x, y) = self.stabilizer.follow(canvas.cursor_pos_pix_f());
 
-            cr.set_line_width(LINE_WIDTH / zoom * LINE_BORDER_FACTOR);
-            cr.set_source_rgba(1.0, 1.0, 1.0, 0.75);
-            cr.move_to(x0, y0);
-            cr.line_to(x1, y1);
-            let _ = cr.stroke();
-        })
+        if self.antialias {
+            (x, y)
+        } else {
+            (x.floor(), y.floor())
+        }
     }
 
     pub fn set_last_cursor_pos_pix(&mut self, value: (f64, f64)) {
@@ -240,33 +456,334 @@ impl PencilState {
     }
 }
 
-/// Given a continuous line segment, return the given number
-/// of discrete points (pixels) that "intersect" it
-fn pixels_along_segment(
+impl PencilState {
+    /// Adds stamps at `target_points` (with speeds interpolated from
+    /// `start_speed` to `end_speed`), then draws those that are far
+    /// enough from the end of the stroke. Unless the stroke is
+    /// anti-aliased, `to_pixel` rounds the points' coordinates.
+    fn queue_stamps(
+        &mut self,
+        target_points: &[(f64, f64)],
+        to_pixel: fn(f64) -> i32,
+        start_speed: f64,
+        end_speed: f64,
+        canvas: &mut Canvas,
+        toolbar: &mut Toolbar,
+        click_type: ClickType,
+    ) {
+        let n = target_points.len();
+        let to_pixel = if self.antialias { |x: f64| x.floor() as i32 } else { to_pixel };
+
+        for (i, &point) in target_points.iter().enumerate() {
+            if let Some(last) = self.pending_stamps.last().map(|s| s.point).or(self.last_stamp_point) {
+                let (dx, dy) = (point.0 - last.0, point.1 - last.1);
+                self.stroke_length += (dx * dx + dy * dy).sqrt();
+            }
+
+            let t = (i as f64 + 0.5) / n as f64;
+
+            self.pending_stamps.push(Stamp {
+                pixel: (to_pixel(point.0), to_pixel(point.1)),
+                point,
+                speed: start_speed + (end_speed - start_speed) * t,
+                distance: self.stroke_length,
+            });
+        }
+
+        self.release_stamps(false, canvas, toolbar, click_type);
+    }
+
+    /// Draws (and removes) the pending stamps that can't be affected by the
+    /// stroke's fade-out (or all of them, if the stroke has `ended`)
+    fn release_stamps(&mut self, ended: bool, canvas: &mut Canvas, toolbar: &mut Toolbar, click_type: ClickType) {
+        let fade_out = self.tool.dynamics(toolbar).velocity.fade_out;
+        let num_ready = if ended {
+            self.pending_stamps.len()
+        } else {
+            self.pending_stamps.iter()
+                .position(|stamp| stamp.distance > self.stroke_length - fade_out)
+                .unwrap_or(self.pending_stamps.len())
+        };
+
+        let ready = self.pending_stamps.drain(..num_ready).collect::<Vec<_>>();
+        let stroke_end = Some(self.stroke_length).filter(|_| ended);
+
+        self.stamp_brush_at(&ready, stroke_end, canvas, toolbar, click_type);
+    }
+
+    /// Draws the brush (centered) at each of the stamps (and their symmetric copies),
+    /// varied by the tool's dynamics (`stroke_end` is the length of the stroke, if it's finished).
+    /// Pixelating brushes (and retouch, clone, and dodge/burn tools) rework what's beneath them instead of drawing their own pixels.
+    fn stamp_brush_at(
+        &mut self,
+        stamps: &[Stamp],
+        stroke_end: Option<f64>,
+        canvas: &mut Canvas,
+        toolbar: &mut Toolbar,
+        click_type: ClickType,
+    ) {
+        let block_size = toolbar.get_pixelate_block_size();
+        let retouch_strength = match self.tool {
+            StrokeTool::Retouch(tool) => toolbar.get_retouch_strength(tool),
+            _ => 0.0,
+        };
+        let (tone_range, exposure) = match self.tool {
+            StrokeTool::DodgeBurn { burn } => (toolbar.get_dodge_burn_range(burn), toolbar.get_dodge_burn_exposure(burn)),
+            _ => (ToneRange::Midtones, 0.0),
+        };
+        let dynamics = self.tool.dynamics(toolbar);
+        let (brush, blending_mode) = self.tool.brush_mut(canvas, toolbar, click_type);
+        let base_radius = brush.radius() as f64;
+        // mirrored copies go through the same pencil mask, so they're committed with the stroke
+        let copies = self.symmetry.copies();
+        let symmetry_center = canvas.symmetry_center();
+
+        for stamp in stamps.iter() {
+            let taper = dynamics.velocity.taper(stamp.distance, stroke_end.map(|end| end - stamp.distance));
+
+            if let Some((last_x, last_y)) = self.last_stamp_point {
+                if stamp.point != (last_x, last_y) {
+                    self.stroke_angle = (stamp.point.1 - last_y).atan2(stamp.point.0 - last_x);
+                }
+            }
+            self.last_stamp_point = Some(stamp.point);
+
+            let mut scale = dynamics.velocity.size_at(stamp.speed) * taper;
+            if dynamics.size_jitter > 0.0 {
+                scale *= 1.0 - dynamics.size_jitter * self.rng.next_f64();
+            }
+
+            let (mut dx, mut dy) = (0.0, 0.0);
+
+            if dynamics.position_jitter > 0.0 {
+                let theta = self.rng.next_f64() * std::f64::consts::TAU;
+                let dist = self.rng.next_f64() * dynamics.position_jitter * base_radius;
+                dx += theta.cos() * dist;
+                dy += theta.sin() * dist;
+            }
+
+            if dynamics.scatter > 0.0 {
+                // perpendicular to the stroke
+                let dist = self.rng.next_signed() * dynamics.scatter * base_radius;
+                dx -= self.stroke_angle.sin() * dist;
+                dy += self.stroke_angle.cos() * dist;
+            }
+
+            let flow = dynamics.flow * (1.0 - dynamics.opacity_jitter * self.rng.next_f64());
+            let opacity = dynamics.opacity * dynamics.velocity.opacity_at(stamp.speed) * taper;
+
+            if self.antialias {
+                let point = (stamp.point.0 + dx, stamp.point.1 + dy);
+                let images = copies.iter()
+                    .filter_map(|copy| {
+                        brush.antialiased_image(base_radius * scale, copy.point(symmetry_center, point))
+                    })
+                    .collect::<Vec<_>>();
+
+                if !images.is_empty() {
+                    for (image, x, y) in images.iter() {
+                        canvas.stamp_image_in_stroke(image, &blending_mode, flow, opacity, *x, *y);
+                    }
+                    continue;
+                }
+            }
+
+            let radius = (base_radius * scale).round().clamp(1.0, 255.0) as u8;
+            let resized;
+            let stamped_brush = if radius as usize != brush.radius() {
+                resized = brush.resized(radius);
+                &resized
+            } else {
+                &*brush
+            };
+
+            let rotated;
+            let image = if dynamics.follow_angle {
+                rotated = stamped_brush.brush_image.rotated(self.stroke_angle);
+                &rotated
+            } else {
+                &stamped_brush.brush_image
+            };
+
+            let x = stamp.pixel.0 + dx.round() as i32 - (image.width() as i32 - 1) / 2;
+            let y = stamp.pixel.1 + dy.round() as i32 - (image.height() as i32 - 1) / 2;
+
+            // one-pixel strokes step from pixel to pixel, dropping the corners of diagonal steps
+            if self.pixel_perfect && image.width() == 1 && image.height() == 1 &&
+               stamped_brush.brush_type() != BrushType::Pixelate {
+                for step in self.pixel_path.steps_to((x, y)) {
+                    let mut drawn = vec![];
+
+                    for copy in copies.iter() {
+                        let (x, y) = copy.pixel(symmetry_center, step);
+                        if !canvas.stroke_drew_at(x, y) {
+                            drawn.push((x, y));
+                        }
+                        canvas.stamp_image_in_stroke(image, &blending_mode, flow, opacity, x, y);
+                    }
+
+                    for (x, y) in self.pixel_path.push(step, drawn) {
+                        canvas.undraw_stroke_pixel(x, y);
+                    }
+                }
+                continue;
+            }
+
+            for copy in copies.iter() {
+                let copied;
+                let (image, x, y) = match copy.brush_image(symmetry_center, image, x, y) {
+                    Some(copy) => {
+                        copied = copy;
+                        (&copied.0, copied.1, copied.2)
+                    },
+                    None => (image, x, y),
+                };
+
+                match (self.tool, stamped_brush.brush_type()) {
+                    (StrokeTool::Retouch(RetouchTool::Smudge), _) => canvas.smudge_beneath(
+                        image,
+                        retouch_strength,
+                        x,
+                        y
+                    ),
+                    (StrokeTool::Retouch(tool), _) => canvas.filter_beneath(
+                        image,
+                        tool == RetouchTool::Sharpen,
+                        retouch_strength,
+                        x,
+                        y
+                    ),
+                    (StrokeTool::Clone { heal }, _) => canvas.clone_beneath(
+                        image,
+                        heal,
+                        x,
+                        y
+                    ),
+                    (StrokeTool::DodgeBurn { burn }, _) => canvas.dodge_burn_respecting_pencil_mask(
+                        image,
+                        tone_range,
+                        exposure,
+                        burn,
+                        x,
+                        y
+                    ),
+                    (_, BrushType::Pixelate) => canvas.pixelate_respecting_pencil_mask(
+                        image,
+                        &blending_mode,
+                        block_size,
+                        x,
+                        y
+                    ),
+                    (_, _) => canvas.stamp_image_in_stroke(
+                        image,
+                        &blending_mode,
+                        flow,
+                        opacity,
+                        x,
+                        y
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// Draws a (two-toned) line from `(x0, y0)` to `(x1, y1)`
+fn draw_guide_line(cr: &Context, zoom: f64, (x0, y0): (f64, f64), (x1, y1): (f64, f64)) {
+    const LINE_WIDTH: f64 = 3.0;
+    const LINE_BORDER_FACTOR: f64 = 0.4;
+
+    cr.set_line_cap(LineCap::Round);
+    cr.set_line_width(LINE_WIDTH / zoom);
+
+    cr.set_source_rgba(0.25, 0.25, 0.25, 0.75);
+    cr.move_to(x0, y0);
+    cr.line_to(x1, y1);
+    let _ = cr.stroke();
+
+    cr.set_line_width(LINE_WIDTH / zoom * LINE_BORDER_FACTOR);
+    cr.set_source_rgba(1.0, 1.0, 1.0, 0.75);
+    cr.move_to(x0, y0);
+    cr.line_to(x1, y1);
+    let _ = cr.stroke();
+}
+
+/// Draws a (two-toned) crosshair centered on `(x, y)`
+fn draw_crosshair(cr: &Context, zoom: f64, (x, y): (f64, f64)) {
+    const ARM_LENGTH: f64 = 8.0;
+
+    let arm = ARM_LENGTH / zoom;
+
+    draw_guide_line(cr, zoom, (x - arm, y), (x + arm, y));
+    draw_guide_line(cr, zoom, (x, y - arm), (x, y + arm));
+}
+
+/// Given a continuous line segment, return the given
+/// number of evenly-spaced points along it
+fn points_along_segment(
     (x0, y0): (f64, f64),
     (x1, y1): (f64, f64),
-    num_pix: usize,
-) -> Vec<(i32, i32)> {
+    num_pts: usize,
+) -> Vec<(f64, f64)> {
     let total_dx = x1 - x0;
     let total_dy = y1 - y0;
 
-    let dx = total_dx / (num_pix as f64);
-    let dy = total_dy / (num_pix as f64);
+    let dx = total_dx / (num_pts as f64);
+    let dy = total_dy / (num_pts as f64);
 
-    (0..num_pix).map(|i| {
+    (0..num_pts).map(|i| {
         let i = i as f64 + 0.5;
         let x = x0 + dx * i;
         let y = y0 + dy * i;
         (x, y)
     })
-        .map(|(x, y)| (x as i32, y as i32))
         .collect::<Vec<_>>()
 }
 
 // Generic handlers for both left and right click variants
 impl PencilState {
     fn drag_start(&mut self, mod_keys: &ModifierType, canvas: &mut Canvas, toolbar: &mut Toolbar, click_type: ClickType) {
+        self.symmetry = self.tool.symmetry(toolbar);
+
+        if mod_keys.intersects(ModifierType::CONTROL_MASK) && !self.symmetry.is_off() {
+            canvas.move_symmetry_center_to_cursor();
+            canvas.update();
+            self.mode = PencilMode::MovingSymmetryCenter;
+            return;
+        }
+
+        if let StrokeTool::Clone { heal } = self.tool {
+            if mod_keys.intersects(ModifierType::CONTROL_MASK) {
+                canvas.set_clone_source_to_cursor();
+                canvas.update();
+                self.mode = PencilMode::SettingCloneSource;
+                return;
+            }
+
+            let aligned = toolbar.get_clone_aligned(heal);
+            let sample_merged = toolbar.get_clone_sample_merged(heal);
+
+            if !canvas.begin_clone_stroke(aligned, sample_merged) {
+                self.mode = PencilMode::NoCloneSource;
+                return;
+            }
+        }
+
         self.dist_till_resample = 0.0;
+        self.antialias = self.tool.antialias(toolbar);
+        self.last_stamp_point = None;
+        self.stroke_angle = 0.0;
+        self.speed = 0.0;
+        self.stroke_length = 0.0;
+        self.pending_stamps.clear();
+        self.stabilizer = StabilizerState::new(self.tool.stabilizer(toolbar));
+        self.pixel_perfect = self.tool.pixel_perfect(toolbar);
+        self.pixel_path = PixelPerfectPath::new();
+
+        // seed with the stroke's start, so identical strokes jitter identically
+        let (x, y) = canvas.cursor_pos_pix_i();
+        self.rng = StrokeRng::new(((x as u32 as u64) << 32) | y as u32 as u64);
+
         if mod_keys.intersects(ModifierType::SHIFT_MASK) {
             self.draw_straight_line_to_cursor(canvas, toolbar, click_type);
             self.mode = PencilMode::PencilUp;
@@ -281,6 +798,15 @@ impl PencilState {
         match self.mode {
             PencilMode::PencilDown => self.draw_to_cursor(canvas, toolbar, click_type),
             PencilMode::PencilUp => (), // line already drawn
+            PencilMode::MovingSymmetryCenter => {
+                canvas.move_symmetry_center_to_cursor();
+                canvas.update();
+            },
+            PencilMode::SettingCloneSource => {
+                canvas.set_clone_source_to_cursor();
+                canvas.update();
+            },
+            PencilMode::NoCloneSource => (),
         }
     }
 
@@ -291,9 +817,19 @@ impl PencilState {
                 self.mode = PencilMode::PencilUp;
             },
             PencilMode::PencilUp => (),
+            PencilMode::MovingSymmetryCenter | PencilMode::SettingCloneSource | PencilMode::NoCloneSource => {
+                self.mode = PencilMode::PencilUp;
+                return;
+            },
         }
 
-        canvas.commit_changes(ActionName::Pencil);
+        self.release_stamps(true, canvas, toolbar, click_type);
+        canvas.update();
+
+        let (brush, _) = self.tool.brush_mut(canvas, toolbar, click_type);
+        let culprit = self.tool.action_name(brush);
+
+        canvas.commit_changes(culprit);
         canvas.clear_pencil_mask();
     }
 }
@@ -339,7 +875,29 @@ impl super::MouseModeState for PencilState {
         let cursor_pos = canvas.cursor_pos_pix_f();
         let cursor_pos = (cursor_pos.0.floor(), cursor_pos.1.floor());
 
-        let brush = toolbar.get_primary_brush_mut();
+        // the stabilized brush trails the cursor on this line
+        if let (PencilMode::PencilDown, Some(brush_point)) = (self.mode, self.stabilizer.brush_point()) {
+            if !self.stabilizer.is_off() {
+                draw_guide_line(cr, *canvas.zoom(), brush_point, canvas.cursor_pos_pix_f());
+            }
+        }
+
+        // mark where the clone stamp is copying from (or would start copying from)
+        if let StrokeTool::Clone { heal } = self.tool {
+            let following = matches!(self.mode, PencilMode::PencilDown) || toolbar.get_clone_aligned(heal);
+
+            if let Some((x, y)) = canvas.clone_sample_point(following) {
+                draw_crosshair(cr, *canvas.zoom(), (x as f64 + 0.5, y as f64 + 0.5));
+            }
+        }
+
+        let symmetry = self.tool.symmetry(toolbar);
+        if !symmetry.is_off() {
+            let (width, height) = (canvas.image_width() as f64, canvas.image_height() as f64);
+            symmetry.draw_guides(cr, canvas.symmetry_center(), width, height, *canvas.zoom());
+        }
+
+        let (brush, _) = self.tool.brush_mut(canvas, toolbar, ClickType::Left);
         let x_offset = (brush.brush_image.width() as i32 - 1) / 2;
         let y_offset = (brush.brush_image.height() as i32 - 1) / 2;
         let path = brush.outline_path(cr);
@@ -0,0 +1,70 @@
+/// A pixel that a one-pixel stroke passed through
+#[derive(Clone)]
+struct PathPixel {
+    pixel: (i32, i32),
+    /// The pixels drawn for it (it and its symmetric copies) that
+    /// the stroke hadn't already drawn, so they can be undrawn
+    drawn: Vec<(i32, i32)>,
+}
+
+/// The tail end of a one-pixel stroke's path, used to remove the redundant
+/// corner pixel of each diagonal step (the "L-shapes" that make lines look
+/// doubled up), the way Aseprite's pixel-perfect mode does
+#[derive(Clone)]
+pub struct PixelPerfectPath {
+    recent: Vec<PathPixel>,
+}
+
+impl PixelPerfectPath {
+    pub fn new() -> Self {
+        PixelPerfectPath {
+            recent: vec![],
+        }
+    }
+
+    /// The (8-connected) pixels to step through, from the last
+    /// pixel in the path, to reach `pixel`
+    pub fn steps_to(&self, pixel: (i32, i32)) -> Vec<(i32, i32)> {
+        let Some(last) = self.recent.last().map(|p| p.pixel) else {
+            return vec![pixel];
+        };
+
+        let (dx, dy) = (pixel.0 - last.0, pixel.1 - last.1);
+        let n = dx.abs().max(dy.abs());
+
+        (1..=n).map(|i| {
+            let t = i as f64 / n as f64;
+            (
+                last.0 + (dx as f64 * t).round() as i32,
+                last.1 + (dy as f64 * t).round() as i32,
+            )
+        }).collect()
+    }
+
+    /// Adds `pixel` (drawn as `drawn`) to the path. If this makes the previous
+    /// pixel a redundant corner, it's dropped from the path, and the pixels
+    /// drawn for it are returned (to be undrawn).
+    pub fn push(&mut self, pixel: (i32, i32), drawn: Vec<(i32, i32)>) -> Vec<(i32, i32)> {
+        self.recent.push(PathPixel { pixel, drawn });
+
+        let mut undrawn = vec![];
+
+        if let [.., a, b, c] = &self.recent[..] {
+            let (a, b, c) = (a.pixel, b.pixel, c.pixel);
+            let orthogonal = |p: (i32, i32), q: (i32, i32)| (p.0 == q.0) != (p.1 == q.1);
+            let diagonal = (a.0 - c.0).abs() == 1 && (a.1 - c.1).abs() == 1;
+
+            if diagonal && orthogonal(a, b) && orthogonal(b, c) {
+                let corner = self.recent.remove(self.recent.len() - 2);
+                undrawn = corner.drawn;
+            }
+        }
+
+        // only the last two pixels can form a corner with the next one
+        if self.recent.len() > 2 {
+            self.recent.drain(..self.recent.len() - 2);
+        }
+
+        undrawn
+    }
+}
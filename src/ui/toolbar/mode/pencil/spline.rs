@@ -1,24 +1,24 @@
 pub trait SplineSegment {
     /// Approximate of the curve's length
     fn rough_length(&self) -> f64;
-    fn sample_n_pixels(&self, num_pix: usize) -> Vec<(i32, i32)>;
+    fn sample_n_points(&self, num_pts: usize) -> Vec<(f64, f64)>;
     fn endpoint(&self) -> (f64, f64);
 }
 
 pub struct SplineSegment3 {
-    x0: i32,
-    y0: i32,
-    x1: i32,
-    y1: i32,
-    x2: i32,
-    y2: i32,
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64,
 }
 
 impl SplineSegment3 {
     pub fn from_grouped(
-        (x0, y0): (i32, i32),
-        (x1, y1): (i32, i32),
-        (x2, y2): (i32, i32),
+        (x0, y0): (f64, f64),
+        (x1, y1): (f64, f64),
+        (x2, y2): (f64, f64),
     ) -> Self {
         Self { x0, y0, x1, y1, x2, y2 }
     }
@@ -26,12 +26,12 @@ impl SplineSegment3 {
 
 impl SplineSegment for SplineSegment3 {
     fn rough_length(&self) -> f64 {
-        let x0 = self.x0 as f64;
-        let x1 = self.x1 as f64;
-        let x2 = self.x2 as f64;
-        let y0 = self.y0 as f64;
-        let y1 = self.y1 as f64;
-        let y2 = self.y2 as f64;
+        let x0 = self.x0;
+        let x1 = self.x1;
+        let x2 = self.x2;
+        let y0 = self.y0;
+        let y1 = self.y1;
+        let y2 = self.y2;
 
         // average the legs' sum and the hypotenuse
         let a = ((x0 - x1).powi(2) + (y0 - y1).powi(2)).sqrt();
@@ -41,48 +41,47 @@ impl SplineSegment for SplineSegment3 {
         a + b + c / 2.0
     }
 
-    fn sample_n_pixels(&self, num_pix: usize) -> Vec<(i32, i32)> {
-        (0..num_pix).map(|i| {
-            let t = (i as f64 + 0.5) / num_pix as f64;
+    fn sample_n_points(&self, num_pts: usize) -> Vec<(f64, f64)> {
+        (0..num_pts).map(|i| {
+            let t = (i as f64 + 0.5) / num_pts as f64;
             let tn = 1.0 - t;
-            let x0 = self.x0 as f64;
-            let x1 = self.x1 as f64;
-            let x2 = self.x2 as f64;
-            let y0 = self.y0 as f64;
-            let y1 = self.y1 as f64;
-            let y2 = self.y2 as f64;
+            let x0 = self.x0;
+            let x1 = self.x1;
+            let x2 = self.x2;
+            let y0 = self.y0;
+            let y1 = self.y1;
+            let y2 = self.y2;
 
             // quadratic bezier
             let x = tn * (tn * x0 + t * x1) + t * (tn * x1 + t * x2);
             let y = tn * (tn * y0 + t * y1) + t * (tn * y1 + t * y2);
             (x, y)
         })
-            .map(|(x, y)| (x.round() as i32, y.round() as i32))
             .collect::<Vec<_>>()
     }
 
     fn endpoint(&self) -> (f64, f64) {
-        (self.x2 as f64, self.y2 as f64)
+        (self.x2, self.y2)
     }
 }
 
 pub struct SplineSegment4 {
-    x0: i32,
-    y0: i32,
-    x1: i32,
-    y1: i32,
-    x2: i32,
-    y2: i32,
-    x3: i32,
-    y3: i32,
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64,
+    x3: f64,
+    y3: f64,
 }
 
 impl SplineSegment4 {
     pub fn from_grouped(
-        (x0, y0): (i32, i32),
-        (x1, y1): (i32, i32),
-        (x2, y2): (i32, i32),
-        (x3, y3): (i32, i32),
+        (x0, y0): (f64, f64),
+        (x1, y1): (f64, f64),
+        (x2, y2): (f64, f64),
+        (x3, y3): (f64, f64),
     ) -> Self {
         Self { x0, y0, x1, y1, x2, y2, x3, y3 }
     }
@@ -90,14 +89,14 @@ impl SplineSegment4 {
 
 impl SplineSegment for SplineSegment4 {
     fn rough_length(&self) -> f64 {
-        let x0 = self.x0 as f64;
-        let x1 = self.x1 as f64;
-        let x2 = self.x2 as f64;
-        let x3 = self.x3 as f64;
-        let y0 = self.y0 as f64;
-        let y1 = self.y1 as f64;
-        let y2 = self.y2 as f64;
-        let y3 = self.y3 as f64;
+        let x0 = self.x0;
+        let x1 = self.x1;
+        let x2 = self.x2;
+        let x3 = self.x3;
+        let y0 = self.y0;
+        let y1 = self.y1;
+        let y2 = self.y2;
+        let y3 = self.y3;
 
         let a = ((x0 - x1).powi(2) + (y0 - y1).powi(2)).sqrt();
         let b = ((x1 - x2).powi(2) + (y1 - y2).powi(2)).sqrt();
@@ -107,19 +106,19 @@ impl SplineSegment for SplineSegment4 {
         a + b + c + d / 2.0
     }
 
-    fn sample_n_pixels(&self, num_pix: usize) -> Vec<(i32, i32)> {
-        (0..num_pix).map(|i| {
-            let t = (i as f64 + 0.5) / num_pix as f64;
+    fn sample_n_points(&self, num_pts: usize) -> Vec<(f64, f64)> {
+        (0..num_pts).map(|i| {
+            let t = (i as f64 + 0.5) / num_pts as f64;
             let t2 = t.powi(2);
             let t3 = t.powi(3);
-            let x0 = self.x0 as f64;
-            let x1 = self.x1 as f64;
-            let x2 = self.x2 as f64;
-            let x3 = self.x3 as f64;
-            let y0 = self.y0 as f64;
-            let y1 = self.y1 as f64;
-            let y2 = self.y2 as f64;
-            let y3 = self.y3 as f64;
+            let x0 = self.x0;
+            let x1 = self.x1;
+            let x2 = self.x2;
+            let x3 = self.x3;
+            let y0 = self.y0;
+            let y1 = self.y1;
+            let y2 = self.y2;
+            let y3 = self.y3;
 
             // cubic b-spline
             let x = 1.0/6.0 * ((-x0 + 3.0 * x1 - 3.0 * x2 + x3) * t3 +
@@ -132,26 +131,25 @@ impl SplineSegment for SplineSegment4 {
                                     (y0 + 4.0 * y1 + y2));
             (x, y)
         })
-            .map(|(x, y)| (x.round() as i32, y.round() as i32))
             .collect::<Vec<_>>()
     }
 
     fn endpoint(&self) -> (f64, f64) {
-        (self.x3 as f64, self.y3 as f64)
+        (self.x3, self.y3)
     }
 }
 
 #[derive(Clone, Copy)]
 pub enum IncrementalSplineSnapshot {
     NoPoints,
-    One((i32, i32)),
-    Two((i32, i32), (i32, i32)),
-    Three((i32, i32), (i32, i32), (i32, i32)),
+    One((f64, f64)),
+    Two((f64, f64), (f64, f64)),
+    Three((f64, f64), (f64, f64), (f64, f64)),
 }
 
 impl IncrementalSplineSnapshot {
     pub fn append_point(
-        &mut self, pt: (i32, i32)
+        &mut self, pt: (f64, f64)
     ) -> Option<SplineSegment4> {
         match self {
             Self::NoPoints => {
@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+use serde_derive::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum StabilizerType {
+    #[default]
+    Off,
+    /// The brush trails the cursor on a virtual string, only
+    /// moving once the string is pulled taut
+    String,
+    /// The brush follows the average of the cursor's recent positions
+    Average,
+}
+
+impl StabilizerType {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Off", StabilizerType::Off),
+            ("String", StabilizerType::String),
+            ("Average", StabilizerType::Average),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+/// Smoothing applied to the cursor's position before it's drawn to
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Stabilizer {
+    pub stabilizer_type: StabilizerType,
+    /// Length (in pixels) of the `String` stabilizer's string
+    pub string_length: f64,
+    /// Number of positions averaged by the `Average` stabilizer
+    pub num_points: usize,
+}
+
+impl Default for Stabilizer {
+    fn default() -> Self {
+        Stabilizer {
+            stabilizer_type: StabilizerType::default(),
+            string_length: 20.0,
+            num_points: 8,
+        }
+    }
+}
+
+impl Stabilizer {
+    pub fn is_off(&self) -> bool {
+        self.stabilizer_type == StabilizerType::Off
+    }
+}
+
+/// A stabilizer's progress through a stroke
+#[derive(Clone)]
+pub struct StabilizerState {
+    stabilizer: Stabilizer,
+    /// Where the brush is (lagging behind the cursor)
+    brush_point: Option<(f64, f64)>,
+    /// The cursor's most recent positions (for `StabilizerType::Average`)
+    recent_points: VecDeque<(f64, f64)>,
+}
+
+impl StabilizerState {
+    pub fn new(stabilizer: Stabilizer) -> Self {
+        StabilizerState {
+            stabilizer,
+            brush_point: None,
+            recent_points: VecDeque::new(),
+        }
+    }
+
+    pub fn is_off(&self) -> bool {
+        self.stabilizer.is_off()
+    }
+
+    /// Where the brush is (if the stroke has started)
+    pub fn brush_point(&self) -> Option<(f64, f64)> {
+        self.brush_point
+    }
+
+    /// Moves the brush toward the cursor (at `cursor`), returning its new position
+    pub fn follow(&mut self, cursor: (f64, f64)) -> (f64, f64) {
+        let point = match (self.stabilizer.stabilizer_type, self.brush_point) {
+            (StabilizerType::Off, _) | (_, None) => cursor,
+            (StabilizerType::String, Some((x, y))) => {
+                let (dx, dy) = (cursor.0 - x, cursor.1 - y);
+                let d = (dx * dx + dy * dy).sqrt();
+                let slack = self.stabilizer.string_length;
+
+                if d > slack {
+                    // pulled along the string, until it's just taut
+                    let t = (d - slack) / d;
+                    (x + dx * t, y + dy * t)
+                } else {
+                    (x, y)
+                }
+            },
+            (StabilizerType::Average, Some(_)) => {
+                self.recent_points.push_back(cursor);
+                while self.recent_points.len() > self.stabilizer.num_points.max(1) {
+                    self.recent_points.pop_front();
+                }
+
+                let n = self.recent_points.len() as f64;
+                let (sum_x, sum_y) = self.recent_points.iter()
+                    .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+                (sum_x / n, sum_y / n)
+            },
+        };
+
+        if self.brush_point.is_none() {
+            self.recent_points.push_back(cursor);
+        }
+
+        self.brush_point = Some(point);
+        point
+    }
+}
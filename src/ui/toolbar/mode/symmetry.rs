@@ -0,0 +1,191 @@
+use crate::image::brush::BrushImage;
+use crate::image::ImageLike;
+
+use gtk::cairo::Context;
+use std::f64::consts::TAU;
+
+/// How strokes (and fills) are repeated around the canvas's symmetry center
+#[derive(Clone, Copy, PartialEq)]
+pub enum SymmetryType {
+    Off,
+    /// Mirrored left-to-right (across a vertical axis)
+    Horizontal,
+    /// Mirrored top-to-bottom (across a horizontal axis)
+    Vertical,
+    /// Mirrored across both axes
+    Both,
+    /// Rotated evenly about the center
+    Radial,
+}
+
+impl SymmetryType {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("Off", SymmetryType::Off),
+            ("Horizontal", SymmetryType::Horizontal),
+            ("Vertical", SymmetryType::Vertical),
+            ("Both", SymmetryType::Both),
+            ("Radial", SymmetryType::Radial),
+        ].iter().map(|x| x.clone())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Symmetry {
+    pub symmetry_type: SymmetryType,
+    /// Number of copies made by `SymmetryType::Radial`
+    pub radial_ways: usize,
+}
+
+/// One of the copies made by a `Symmetry`: a mirroring,
+/// followed by a rotation about the center
+#[derive(Clone, Copy)]
+pub struct SymmetryCopy {
+    flip_x: bool,
+    flip_y: bool,
+    /// Radians, clockwise on-screen
+    angle: f64,
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Symmetry {
+            symmetry_type: SymmetryType::Off,
+            radial_ways: 6,
+        }
+    }
+}
+
+impl Symmetry {
+    pub fn is_off(&self) -> bool {
+        self.symmetry_type == SymmetryType::Off
+    }
+
+    /// Every copy to draw, starting with the original
+    pub fn copies(&self) -> Vec<SymmetryCopy> {
+        let flip = |flip_x, flip_y| SymmetryCopy { flip_x, flip_y, angle: 0.0 };
+
+        match self.symmetry_type {
+            SymmetryType::Off => vec![flip(false, false)],
+            SymmetryType::Horizontal => vec![flip(false, false), flip(true, false)],
+            SymmetryType::Vertical => vec![flip(false, false), flip(false, true)],
+            SymmetryType::Both => vec![
+                flip(false, false),
+                flip(true, false),
+                flip(false, true),
+                flip(true, true),
+            ],
+            SymmetryType::Radial => {
+                let n = self.radial_ways.max(1);
+                (0..n).map(|i| SymmetryCopy {
+                    flip_x: false,
+                    flip_y: false,
+                    angle: TAU * i as f64 / n as f64,
+                }).collect()
+            },
+        }
+    }
+
+    /// Draws the axes (or spokes) through `center` across an image of
+    /// the given size (`cr` should be in the image's coordinates)
+    pub fn draw_guides(&self, cr: &Context, (cx, cy): (f64, f64), width: f64, height: f64, zoom: f64) {
+        // long enough to cross the image from any center
+        let reach = (width + height) * 2.0;
+
+        let mut lines = vec![];
+
+        match self.symmetry_type {
+            SymmetryType::Off => return,
+            SymmetryType::Horizontal => lines.push(((cx, 0.0), (cx, height))),
+            SymmetryType::Vertical => lines.push(((0.0, cy), (width, cy))),
+            SymmetryType::Both => {
+                lines.push(((cx, 0.0), (cx, height)));
+                lines.push(((0.0, cy), (width, cy)));
+            },
+            SymmetryType::Radial => {
+                for copy in self.copies() {
+                    // spokes point up, like the first copy's
+                    let (sin, cos) = copy.angle.sin_cos();
+                    lines.push(((cx, cy), (cx + sin * reach, cy - cos * reach)));
+                }
+            },
+        }
+
+        const LINE_WIDTH: f64 = 2.0;
+        const CENTER_RADIUS: f64 = 5.0;
+
+        let _ = cr.save();
+        {
+            cr.rectangle(0.0, 0.0, width, height);
+            cr.clip();
+            cr.set_line_width(LINE_WIDTH / zoom);
+            cr.set_dash(&[6.0 / zoom, 4.0 / zoom], 0.0);
+            cr.set_source_rgba(0.0, 0.6, 1.0, 0.75);
+
+            for ((x0, y0), (x1, y1)) in lines.into_iter() {
+                cr.move_to(x0, y0);
+                cr.line_to(x1, y1);
+                let _ = cr.stroke();
+            }
+
+            cr.set_dash(&[], 0.0);
+            cr.arc(cx, cy, CENTER_RADIUS / zoom, 0.0, TAU);
+            let _ = cr.stroke();
+        }
+        let _ = cr.restore();
+    }
+}
+
+impl SymmetryCopy {
+    fn is_identity(&self) -> bool {
+        !self.flip_x && !self.flip_y && self.angle == 0.0
+    }
+
+    /// Where the (real-number) `point` is copied to
+    pub fn point(&self, (cx, cy): (f64, f64), (x, y): (f64, f64)) -> (f64, f64) {
+        let dx = if self.flip_x { cx - x } else { x - cx };
+        let dy = if self.flip_y { cy - y } else { y - cy };
+        let (sin, cos) = self.angle.sin_cos();
+
+        (cx + cos * dx - sin * dy, cy + sin * dx + cos * dy)
+    }
+
+    /// Which pixel the pixel at (`x`, `y`) is copied to
+    pub fn pixel(&self, center: (f64, f64), (x, y): (i32, i32)) -> (i32, i32) {
+        let (x, y) = self.point(center, (x as f64 + 0.5, y as f64 + 0.5));
+        (x.floor() as i32, y.floor() as i32)
+    }
+
+    /// The copy of `image`, when drawn with its top-left corner at (`x`, `y`),
+    /// along with its new top-left corner (`None` for the identity)
+    pub fn brush_image(
+        &self,
+        center: (f64, f64),
+        image: &BrushImage,
+        x: i32,
+        y: i32,
+    ) -> Option<(BrushImage, i32, i32)> {
+        if self.is_identity() {
+            return None;
+        }
+
+        let (w, h) = (image.width() as i32, image.height() as i32);
+        let mut image = image.flipped(self.flip_x, self.flip_y);
+
+        // mirror the image's bounds, so even-sized images line up exactly
+        let x = if self.flip_x { (2.0 * center.0).round() as i32 - x - w } else { x };
+        let y = if self.flip_y { (2.0 * center.1).round() as i32 - y - h } else { y };
+
+        if self.angle == 0.0 {
+            return Some((image, x, y));
+        }
+
+        // rotate the image's center pixel (which `BrushImage::rotated` preserves)
+        let rotation = SymmetryCopy { flip_x: false, flip_y: false, angle: self.angle };
+        let (px, py) = rotation.pixel(center, (x + (w - 1) / 2, y + (h - 1) / 2));
+        image = image.rotated(self.angle);
+        let (w, h) = (image.width() as i32, image.height() as i32);
+
+        Some((image, px - (w - 1) / 2, py - (h - 1) / 2))
+    }
+}
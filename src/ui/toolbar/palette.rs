@@ -1,7 +1,8 @@
 mod io;
+mod picker;
 
 use crate::image::{DrawableImage, mk_transparent_checkerboard};
-use crate::ui::{dialog::choose_color_dialog, get_parent_window};
+use picker::ColorPicker;
 
 use gtk::prelude::*;
 use std::rc::Rc;
@@ -17,7 +18,7 @@ struct PaletteColorButton {
 }
 
 impl PaletteColorButton {
-    fn new_p(color: Option<RGBA>) -> Rc<RefCell<Self>> {
+    fn new_p(color: Option<RGBA>, picker_p: &Rc<RefCell<ColorPicker>>) -> Rc<RefCell<Self>> {
         const SIZE: i32 = 30;
 
         let widget = gtk::Button::builder()
@@ -82,13 +83,15 @@ impl PaletteColorButton {
             controller.reset();
         });
 
-        click_controller.connect_stopped(clone!(@strong cb_p => move |controller| {
+        click_controller.connect_stopped(clone!(@strong cb_p, @strong picker_p => move |controller| {
             if controller.current_button() == RIGHT_CLICK_BUTTON {
                 if controller.current_event_state().contains(gtk::gdk::ModifierType::CONTROL_MASK) {
                     cb_p.borrow_mut().color = None;
                     cb_p.borrow().drawing_area.queue_draw();
                 } else {
-                    Self::select_new_color(cb_p.clone());
+                    // store the picker's color in this slot
+                    let color = picker_p.borrow().color();
+                    Self::set_color(&cb_p, Some(color));
                 }
             }
         }));
@@ -98,21 +101,6 @@ impl PaletteColorButton {
         cb_p
     }
 
-    fn select_new_color(self_p: Rc<RefCell<Self>>) {
-        let parent = get_parent_window(&self_p.borrow().widget);
-        let parent_ref = if let Some(ref w) = parent {
-            Some(w)
-        } else {
-            None
-        };
-
-        choose_color_dialog(parent_ref, move |res_color| {
-            if let Ok(rgba) = res_color {
-                Self::set_color(&self_p, Some(rgba));
-            }
-        });
-    }
-
     fn set_color(self_p: &Rc<RefCell<Self>>, new_rgba_option: Option<RGBA>) {
         self_p.borrow_mut().color = new_rgba_option;
         self_p.borrow_mut().drawing_area.queue_draw();
@@ -176,45 +164,8 @@ impl PrimarySecondaryButton {
             let _ = cr.fill();
         }));
 
-        let click_controller = gtk::GestureClick::builder()
-            .button(0)
-            .build();
-
-        const RIGHT_CLICK_BUTTON: u32 = 3;
-
-        // Scuffed use of click_controller's handlers: I don't know
-        // why this works, and why the alternatives don't.
-
-        click_controller.connect_end(|controller, _| {
-            controller.reset();
-        });
-
-        click_controller.connect_stopped(clone!(@strong cb_p => move |controller| {
-            if controller.current_button() == RIGHT_CLICK_BUTTON {
-                Self::select_new_color(cb_p.clone());
-            }
-        }));
-
-        cb_p.borrow().widget.add_controller(click_controller);
-
         cb_p
     }
-
-    fn select_new_color(self_p: Rc<RefCell<Self>>) {
-        let parent = get_parent_window(&self_p.borrow().widget);
-        let parent_ref = if let Some(ref w) = parent {
-            Some(w)
-        } else {
-            None
-        };
-
-        choose_color_dialog(parent_ref, move |res_color| {
-            if let Ok(rgba) = res_color {
-                self_p.borrow_mut().color = rgba;
-                self_p.borrow_mut().drawing_area.queue_draw();
-            }
-        });
-    }
 }
 
 pub struct Palette {
@@ -223,6 +174,10 @@ pub struct Palette {
     active: PrimaryOrSecondary,
     primary_button_p: Rc<RefCell<PrimarySecondaryButton>>,
     secondary_button_p: Rc<RefCell<PrimarySecondaryButton>>,
+    /// Docked picker that edits the active (primary or secondary) color
+    picker_p: Rc<RefCell<ColorPicker>>,
+    /// Set when imported from a format that stores a palette name
+    name: Option<String>,
 }
 
 #[derive(PartialEq)]
@@ -238,10 +193,12 @@ impl Palette {
             .spacing(10)
             .build();
 
+        let picker_p = ColorPicker::new_p(default_primary);
+
         let color_buttons = colors.iter()
             .map(|inner_vec| {
                 inner_vec.iter()
-                    .map(|color| PaletteColorButton::new_p(*color))
+                    .map(|color| PaletteColorButton::new_p(*color, &picker_p))
                     .collect::<Vec<_>>()
             })
             .collect::<Vec<_>>();
@@ -255,6 +212,12 @@ impl Palette {
             active: PrimaryOrSecondary::Primary,
             primary_button_p,
             secondary_button_p,
+            picker_p,
+            name: None,
+        }));
+
+        palette_p.borrow().picker_p.borrow_mut().set_on_change(clone!(@strong palette_p => move |color| {
+            palette_p.borrow().set_active_button_color(color);
         }));
 
         let color_array_wrapper_widget = gtk::Box::builder()
@@ -292,12 +255,16 @@ impl Palette {
 
         palette_p.borrow().widget.prepend(&primary_secondary_wrapper);
 
+        palette_p.borrow().widget.append(&gtk::Separator::new(gtk::Orientation::Vertical));
+        palette_p.borrow().widget.append(palette_p.borrow().picker_p.borrow().widget());
+
         palette_p.borrow()
             .primary_button_p.borrow().widget
             .connect_clicked(clone!(@strong palette_p => move |button| {
                 button.set_active(true);
                 palette_p.borrow().secondary_button_p.borrow().widget.set_active(false);
                 palette_p.borrow_mut().active = PrimaryOrSecondary::Primary;
+                palette_p.borrow().sync_picker();
             }));
 
         palette_p.borrow()
@@ -306,6 +273,7 @@ impl Palette {
                 button.set_active(true);
                 palette_p.borrow().primary_button_p.borrow().widget.set_active(false);
                 palette_p.borrow_mut().active = PrimaryOrSecondary::Secondary;
+                palette_p.borrow().sync_picker();
             }));
 
         palette_p
@@ -373,25 +341,58 @@ impl Palette {
     }
 
     pub fn set_primary_color(&self, color: RGBA) {
-        self.primary_button_p.borrow_mut().color = color;
-        self.primary_button_p.borrow().drawing_area.queue_draw();
+        Self::set_button_color(&self.primary_button_p, color);
+        ColorPicker::add_recent_color(&self.picker_p, color);
+        self.sync_picker();
     }
 
     pub fn set_secondary_color(&self, color: RGBA) {
-        self.secondary_button_p.borrow_mut().color = color;
-        self.secondary_button_p.borrow().drawing_area.queue_draw();
+        Self::set_button_color(&self.secondary_button_p, color);
+        ColorPicker::add_recent_color(&self.picker_p, color);
+        self.sync_picker();
+    }
+
+    fn set_button_color(button_p: &Rc<RefCell<PrimarySecondaryButton>>, color: RGBA) {
+        button_p.borrow_mut().color = color;
+        button_p.borrow().drawing_area.queue_draw();
+    }
+
+    fn active_button_p(&self) -> &Rc<RefCell<PrimarySecondaryButton>> {
+        match self.active {
+            PrimaryOrSecondary::Primary => &self.primary_button_p,
+            PrimaryOrSecondary::Secondary => &self.secondary_button_p,
+        }
     }
 
     /// Sets the primary or secondary color (whichever
     /// is currently active) to the argument
     fn set_active_color(&mut self, color: RGBA) {
-        let active_button_p = match self.active {
-            PrimaryOrSecondary::Primary => &self.primary_button_p,
-            PrimaryOrSecondary::Secondary => &self.secondary_button_p,
-        };
+        Self::set_button_color(self.active_button_p(), color);
+        ColorPicker::add_recent_color(&self.picker_p, color);
+        self.sync_picker();
+    }
+
+    /// Like `set_active_color`, but doesn't update the
+    /// picker (for changes that come from the picker)
+    fn set_active_button_color(&self, color: RGBA) {
+        Self::set_button_color(self.active_button_p(), color);
+    }
+
+    /// Makes the picker show the active color
+    fn sync_picker(&self) {
+        let color = self.active_button_p().borrow().color;
+        ColorPicker::set_color(&self.picker_p, color);
+    }
+
+    /// The colors of the (non-empty) palette slots, in row-major order
+    pub fn colors(&self) -> Vec<RGBA> {
+        self.color_buttons.iter().flatten()
+            .filter_map(|cb_p| cb_p.borrow().color)
+            .collect::<Vec<_>>()
+    }
 
-        active_button_p.borrow_mut().color = color;
-        active_button_p.borrow().drawing_area.queue_draw();
+    pub fn num_slots(&self) -> usize {
+        self.color_buttons.iter().map(|row| row.len()).sum()
     }
 
     /// Looks for an empty color slot in the palette,
@@ -413,8 +414,9 @@ impl Palette {
     fn swap_primary_and_secondary(&mut self) {
         let primary = self.primary_color();
         let secondary = self.secondary_color();
-        self.set_primary_color(secondary);
-        self.set_secondary_color(primary);
+        Self::set_button_color(&self.primary_button_p, secondary);
+        Self::set_button_color(&self.secondary_button_p, primary);
+        self.sync_picker();
     }
 
     /// Overwrites the palette colors (not the primary/secondary)
@@ -1,7 +1,11 @@
-use crate::image::Pixel;
+mod formats;
+
+use crate::image::{ImageLikeUnchecked, Pixel};
+use crate::image::quantize::quantize_ordered;
 
 use crate::ui::UiState;
-use crate::ui::dialog::{choose_file_dialog, ok_dialog_str_};
+use crate::ui::selection::Selection;
+use crate::ui::dialog::{choose_file_dialog, extract_palette_dialog, ok_dialog_str_};
 
 use std::rc::Rc;
 use std::cell::RefCell;
@@ -9,6 +13,8 @@ use gtk::prelude::FileExt;
 use regex::Regex;
 use glib_macros::clone;
 use std::fs::File;
+use std::path::Path;
+use formats::{read_palette, write_palette, NamedPalette, PaletteFormat};
 
 // Use dead-simple encoding: one color per line,
 // each line matches one of the following:
@@ -103,7 +109,8 @@ fn parse_rgba(s: &str) -> Option<Pixel> {
     }
 }
 
-fn string_to_colors(string: &str) -> Result<Vec<Pixel>, String> {
+/// Parses a single color in any of the formats above
+pub(super) fn parse_color(s: &str) -> Option<Pixel> {
     let parsers = [
         parse_hex3,
         parse_hex4,
@@ -113,6 +120,13 @@ fn string_to_colors(string: &str) -> Result<Vec<Pixel>, String> {
         parse_rgba,
     ];
 
+    parsers.iter()
+        .map(|p| p(s))
+        .filter_map(|opt| opt)
+        .next()
+}
+
+pub(super) fn string_to_colors(string: &str) -> Result<Vec<Pixel>, String> {
     let mut res = Vec::new();
 
     for (i, line) in string.split("\n").enumerate() {
@@ -120,11 +134,7 @@ fn string_to_colors(string: &str) -> Result<Vec<Pixel>, String> {
             continue;
         }
 
-        if let Some(color) = parsers.iter()
-            .map(|p| p(line.trim()))
-            .filter_map(|opt| opt)
-            .next()
-        {
+        if let Some(color) = parse_color(line.trim()) {
             res.push(color);
         } else {
             return Err(format!("Couldn't parse line {} (`{line}`)", i + 1));
@@ -134,7 +144,7 @@ fn string_to_colors(string: &str) -> Result<Vec<Pixel>, String> {
     Ok(res)
 }
 
-fn colors_to_string(colors: Vec<Pixel>) -> String {
+pub(super) fn colors_to_string(colors: Vec<Pixel>) -> String {
     colors.iter().map(|pix| {
         // rgb:
         // format!("{},{},{},{}\n", pix.red(), pix.green(), pix.blue(), pix.alpha())
@@ -151,8 +161,25 @@ fn colors_to_string(colors: Vec<Pixel>) -> String {
     .collect::<String>()
 }
 
-fn all_files() -> gtk::gio::ListStore {
+fn palette_files() -> gtk::gio::ListStore {
     let valid_filetypes = gtk::gio::ListStore::new::<gtk::FileFilter>();
+
+    let all_palettes = gtk::FileFilter::new();
+    all_palettes.set_name(Some("All Palettes"));
+    valid_filetypes.append(&all_palettes);
+
+    for (name, format) in PaletteFormat::labeled_variants() {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some(name));
+
+        for ext in format.extensions() {
+            filter.add_suffix(ext);
+            all_palettes.add_suffix(ext);
+        }
+
+        valid_filetypes.append(&filter);
+    }
+
     let all = gtk::FileFilter::new();
     all.set_name(Some("All Files"));
     all.add_pattern("*");
@@ -160,25 +187,32 @@ fn all_files() -> gtk::gio::ListStore {
     valid_filetypes
 }
 
+fn path_extension(path: &Path) -> Option<&str> {
+    path.extension().and_then(|ext| ext.to_str())
+}
+
 impl UiState {
     pub fn import_palette(ui_p: Rc<RefCell<UiState>>) {
 
-        fn gfile_to_colors(gfile: gtk::gio::File) -> Result<Vec<Pixel>, String> {
+        fn gfile_to_palette(gfile: gtk::gio::File) -> Result<NamedPalette, String> {
             let path = gfile.path().unwrap();
-            let mut file = File::open(path).map_err(|e| e.to_string())?;
-            let mut contents = String::new();
-            std::io::Read::read_to_string(&mut file, &mut contents).map_err(|e| e.to_string())?;
-            string_to_colors(&contents)
+            let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+            let format = PaletteFormat::detect(path_extension(&path), &bytes);
+            read_palette(&bytes, format)
         }
 
         choose_file_dialog(&ui_p.borrow().window, "Choose an palette to import",
-            "Import", &all_files(), false,
+            "Import", &palette_files(), false,
             clone!(@strong ui_p => move |res| {
                 if let Ok(gfile) = res {
-                    match gfile_to_colors(gfile) {
-                        Ok(colors) => {
-                            let colors = colors.iter().map(|pix| Pixel::to_rgba_struct(&pix)).collect::<Vec<_>>();
-                            ui_p.borrow().toolbar_p.borrow().palette_p.borrow_mut().overwrite_colors(colors);
+                    match gfile_to_palette(gfile) {
+                        Ok(palette) => {
+                            let colors = palette.colors.iter().map(|pix| Pixel::to_rgba_struct(&pix)).collect::<Vec<_>>();
+                            let ui = ui_p.borrow();
+                            let toolbar = ui.toolbar_p.borrow();
+                            let mut palette_widget = toolbar.palette_p.borrow_mut();
+                            palette_widget.overwrite_colors(colors);
+                            palette_widget.name = palette.name;
                         },
                         Err(mesg) => {
                             ok_dialog_str_(
@@ -194,27 +228,40 @@ impl UiState {
     }
 
     pub fn export_palette(ui_p: Rc<RefCell<UiState>>) {
-        let colors = ui_p.borrow().toolbar_p.borrow().palette_p.borrow()
-            .color_buttons.iter().flatten()
-            .map(|cb_p| cb_p.borrow().color)
-            .filter_map(|opt| opt)
-            .map(|rgba| Pixel::from_rgba_struct(rgba))
-            .collect::<Vec<_>>();
+        let (colors, name) = {
+            let ui = ui_p.borrow();
+            let toolbar = ui.toolbar_p.borrow();
+            let palette = toolbar.palette_p.borrow();
 
-        let colors_str = colors_to_string(colors);
+            let colors = palette.colors().into_iter()
+                .map(|rgba| Pixel::from_rgba_struct(rgba))
+                .collect::<Vec<_>>();
 
-        fn write_string_to_gfile(s: &str, gfile: gtk::gio::File) -> Result<(), String> {
+            (colors, palette.name.clone())
+        };
+
+        fn write_palette_to_gfile(colors: Vec<Pixel>, name: Option<String>, gfile: gtk::gio::File) -> Result<(), String> {
             let path = gfile.path().unwrap();
+            let format = path_extension(&path)
+                .and_then(PaletteFormat::from_extension)
+                .unwrap_or(PaletteFormat::Simple);
+
+            // name the palette after the file, if it doesn't have one already
+            let name = name.or_else(|| {
+                path.file_stem().and_then(|stem| stem.to_str()).map(String::from)
+            });
+
+            let bytes = write_palette(&NamedPalette { name, colors }, format);
             let mut file = File::create(path).map_err(|e| e.to_string())?;
-            std::io::Write::write_all(&mut file, s.as_bytes()).map_err(|e| e.to_string())?;
+            std::io::Write::write_all(&mut file, &bytes).map_err(|e| e.to_string())?;
             Ok(())
         }
 
         choose_file_dialog(&ui_p.borrow().window, "Export Palette",
-            "Export", &all_files(), true,
+            "Export", &palette_files(), true,
             clone!(@strong ui_p => move |res| {
                 if let Ok(gfile) = res {
-                    match write_string_to_gfile(&colors_str, gfile) {
+                    match write_palette_to_gfile(colors, name, gfile) {
                         Ok(()) => (),
                         Err(mesg) => {
                             ok_dialog_str_(
@@ -228,4 +275,37 @@ impl UiState {
             })
         );
     }
+
+    /// Fills the palette with the most representative colors
+    /// of the selection (or the entire active layer, if there's
+    /// no selection)
+    pub fn extract_palette(ui_p: Rc<RefCell<UiState>>) {
+        if ui_p.borrow().active_canvas_p().is_none() {
+            return;
+        }
+
+        let num_slots = ui_p.borrow().toolbar_p.borrow().palette_p.borrow().num_slots();
+
+        extract_palette_dialog(&ui_p.borrow().window, num_slots, clone!(@strong ui_p => move |method, num_colors, order| {
+            let colors = if let Some(canvas_p) = ui_p.borrow().active_canvas_p() {
+                let canvas = canvas_p.borrow();
+                let image = canvas.active_image();
+
+                match canvas.selection() {
+                    Selection::NoSelection => quantize_ordered(image.pixels().iter(), num_colors, method, order),
+                    selection => quantize_ordered(
+                        selection.iter().map(|(r, c)| image.pix_at(r, c)),
+                        num_colors,
+                        method,
+                        order,
+                    ),
+                }
+            } else {
+                return;
+            };
+
+            let colors = colors.iter().map(|pix| Pixel::to_rgba_struct(&pix)).collect::<Vec<_>>();
+            ui_p.borrow().toolbar_p.borrow().palette_p.borrow_mut().overwrite_colors(colors);
+        }));
+    }
 }
@@ -0,0 +1,482 @@
+use crate::image::Pixel;
+use super::{colors_to_string, string_to_colors};
+
+use regex::Regex;
+
+/// The palette file formats that can be read and written
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaletteFormat {
+    /// One `#hex`/`r,g,b[,a]` color per line (the original format)
+    Simple,
+    /// GIMP (and Aseprite, Inkscape, Krita) `.gpl`
+    Gpl,
+    /// JASC (Paint Shop Pro) `.pal`
+    JascPal,
+    /// Adobe Swatch Exchange `.ase`
+    Ase,
+    /// Paint.NET `.txt` (`AARRGGBB` per line, `;` comments)
+    PaintNet,
+    /// Lospec `.hex` (`RRGGBB` per line)
+    Hex,
+}
+
+impl PaletteFormat {
+    pub fn labeled_variants() -> impl Iterator<Item = (&'static str, Self)> {
+        [
+            ("GIMP Palette", PaletteFormat::Gpl),
+            ("JASC Palette", PaletteFormat::JascPal),
+            ("Adobe Swatch Exchange", PaletteFormat::Ase),
+            ("Paint.NET Palette", PaletteFormat::PaintNet),
+            ("Hex Palette", PaletteFormat::Hex),
+            ("Plain Text Palette", PaletteFormat::Simple),
+        ].iter().map(|x| x.clone())
+    }
+
+    /// File extensions associated with the format (without the '.')
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Simple => &["palette"],
+            Self::Gpl => &["gpl"],
+            Self::JascPal => &["pal"],
+            Self::Ase => &["ase"],
+            Self::PaintNet => &["txt"],
+            Self::Hex => &["hex"],
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "gpl" => Some(Self::Gpl),
+            "pal" => Some(Self::JascPal),
+            "ase" => Some(Self::Ase),
+            "hex" => Some(Self::Hex),
+            "txt" => Some(Self::PaintNet),
+            "palette" => Some(Self::Simple),
+            _ => None,
+        }
+    }
+
+    /// Sniffs the format from the file's contents
+    pub fn from_contents(bytes: &[u8]) -> Self {
+        if bytes.starts_with(b"ASEF") {
+            return Self::Ase;
+        }
+
+        let text = String::from_utf8_lossy(bytes);
+        let mut lines = text.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty());
+
+        match lines.next() {
+            Some(first) if first.starts_with("GIMP Palette") => Self::Gpl,
+            Some(first) if first.starts_with("JASC-PAL") => Self::JascPal,
+            Some(first) if first.starts_with(';') => Self::PaintNet,
+            Some(first) if is_hex_digits(first, 8) => Self::PaintNet,
+            Some(first) if is_hex_digits(first, 6) => Self::Hex,
+            _ => Self::Simple,
+        }
+    }
+
+    /// Prefer the extension, but fall back on the contents (`.txt` is
+    /// ambiguous, so its contents always have the final say)
+    pub fn detect(extension: Option<&str>, bytes: &[u8]) -> Self {
+        let from_contents = Self::from_contents(bytes);
+
+        match extension.and_then(Self::from_extension) {
+            Some(Self::PaintNet) => from_contents,
+            Some(format) => format,
+            None => from_contents,
+        }
+    }
+}
+
+/// A list of colors, with the palette's name
+/// (if the format stores one)
+pub struct NamedPalette {
+    pub name: Option<String>,
+    pub colors: Vec<Pixel>,
+}
+
+fn is_hex_digits(s: &str, n: usize) -> bool {
+    s.len() == n && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub fn read_palette(bytes: &[u8], format: PaletteFormat) -> Result<NamedPalette, String> {
+    if let PaletteFormat::Ase = format {
+        return read_ase(bytes);
+    }
+
+    let text = String::from_utf8_lossy(bytes);
+
+    match format {
+        PaletteFormat::Simple => Ok(NamedPalette { name: None, colors: string_to_colors(&text)? }),
+        PaletteFormat::Gpl => read_gpl(&text),
+        PaletteFormat::JascPal => read_jasc_pal(&text),
+        PaletteFormat::PaintNet => read_paint_net(&text),
+        PaletteFormat::Hex => read_hex(&text),
+        PaletteFormat::Ase => unreachable!(),
+    }
+}
+
+pub fn write_palette(palette: &NamedPalette, format: PaletteFormat) -> Vec<u8> {
+    match format {
+        PaletteFormat::Simple => colors_to_string(palette.colors.clone()).into_bytes(),
+        PaletteFormat::Gpl => write_gpl(palette).into_bytes(),
+        PaletteFormat::JascPal => write_jasc_pal(palette).into_bytes(),
+        PaletteFormat::Ase => write_ase(palette),
+        PaletteFormat::PaintNet => write_paint_net(palette).into_bytes(),
+        PaletteFormat::Hex => write_hex(palette).into_bytes(),
+    }
+}
+
+fn parse_channel(s: &str, line_number: usize) -> Result<u8, String> {
+    s.parse::<u8>().map_err(|_| format!("Invalid color value on line {line_number} (`{s}`)"))
+}
+
+// GIMP: "GIMP Palette" header, optional "Name:"/"Columns:"
+// lines, '#' comments, then "R G B [color name]" lines
+
+fn read_gpl(text: &str) -> Result<NamedPalette, String> {
+    let mut lines = text.lines().enumerate();
+
+    match lines.next() {
+        Some((_, header)) if header.trim().starts_with("GIMP Palette") => (),
+        _ => return Err(String::from("Missing `GIMP Palette` header")),
+    }
+
+    let re = Regex::new(r"^\s*(\d+)\s+(\d+)\s+(\d+)").unwrap();
+    let mut name = None;
+    let mut colors = Vec::new();
+
+    for (i, line) in lines {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("Columns:") {
+            continue;
+        } else if let Some(palette_name) = trimmed.strip_prefix("Name:") {
+            name = Some(palette_name.trim().to_string());
+        } else if let Some((_match_str, [r, g, b])) = re.captures(line).map(|c| c.extract()) {
+            colors.push(Pixel::from_rgb(
+                parse_channel(r, i + 1)?,
+                parse_channel(g, i + 1)?,
+                parse_channel(b, i + 1)?,
+            ));
+        } else {
+            return Err(format!("Couldn't parse line {} (`{line}`)", i + 1));
+        }
+    }
+
+    Ok(NamedPalette { name, colors })
+}
+
+fn write_gpl(palette: &NamedPalette) -> String {
+    let mut res = String::from("GIMP Palette\n");
+    res.push_str(&format!("Name: {}\n", palette.name.as_deref().unwrap_or("Untitled")));
+    res.push_str("#\n");
+
+    for pix in palette.colors.iter() {
+        res.push_str(&format!(
+            "{:3} {:3} {:3}\t#{:02x}{:02x}{:02x}\n",
+            pix.red(), pix.green(), pix.blue(),
+            pix.red(), pix.green(), pix.blue(),
+        ));
+    }
+
+    res
+}
+
+// JASC: "JASC-PAL", "0100", the number of colors,
+// then "R G B" lines (some writers append alpha)
+
+fn read_jasc_pal(text: &str) -> Result<NamedPalette, String> {
+    let mut lines = text.lines()
+        .enumerate()
+        .filter(|(_i, line)| !line.trim().is_empty());
+
+    match lines.next() {
+        Some((_, header)) if header.trim() == "JASC-PAL" => (),
+        _ => return Err(String::from("Missing `JASC-PAL` header")),
+    }
+
+    let _version = lines.next();
+    let count = lines.next()
+        .and_then(|(_i, line)| line.trim().parse::<usize>().ok())
+        .ok_or(String::from("Missing color count"))?;
+
+    // the count comes from the file, so don't trust it with an allocation
+    let mut colors = Vec::new();
+
+    for (i, line) in lines.take(count) {
+        let channels = line.split_whitespace()
+            .map(|s| parse_channel(s, i + 1))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        match channels[..] {
+            [r, g, b] => colors.push(Pixel::from_rgb(r, g, b)),
+            [r, g, b, a] => colors.push(Pixel::from_rgba(r, g, b, a)),
+            _ => return Err(format!("Couldn't parse line {} (`{line}`)", i + 1)),
+        }
+    }
+
+    if colors.len() < count {
+        return Err(format!("Expected {count} colors, but found {}", colors.len()));
+    }
+
+    Ok(NamedPalette { name: None, colors })
+}
+
+fn write_jasc_pal(palette: &NamedPalette) -> String {
+    let mut res = format!("JASC-PAL\r\n0100\r\n{}\r\n", palette.colors.len());
+
+    for pix in palette.colors.iter() {
+        res.push_str(&format!("{} {} {}\r\n", pix.red(), pix.green(), pix.blue()));
+    }
+
+    res
+}
+
+// Paint.NET: ';' comments, then "AARRGGBB" lines
+// (Lospec stores the name in a "; Palette Name: " comment)
+
+fn read_paint_net(text: &str) -> Result<NamedPalette, String> {
+    let mut name = None;
+    let mut colors = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            continue;
+        } else if let Some(comment) = trimmed.strip_prefix(';') {
+            if let Some(palette_name) = comment.trim().strip_prefix("Palette Name:") {
+                name = Some(palette_name.trim().to_string());
+            }
+        } else if is_hex_digits(trimmed, 8) {
+            let argb = u32::from_str_radix(trimmed, 16).unwrap();
+            let [a, r, g, b] = argb.to_be_bytes();
+            colors.push(Pixel::from_rgba(r, g, b, a));
+        } else {
+            return Err(format!("Couldn't parse line {} (`{line}`)", i + 1));
+        }
+    }
+
+    Ok(NamedPalette { name, colors })
+}
+
+fn write_paint_net(palette: &NamedPalette) -> String {
+    let mut res = String::from("; paint.net Palette File\n");
+    if let Some(ref name) = palette.name {
+        res.push_str(&format!("; Palette Name: {name}\n"));
+    }
+    res.push_str(&format!("; Colors: {}\n", palette.colors.len()));
+
+    for pix in palette.colors.iter() {
+        res.push_str(&format!(
+            "{:02X}{:02X}{:02X}{:02X}\n",
+            pix.alpha(), pix.red(), pix.green(), pix.blue(),
+        ));
+    }
+
+    res
+}
+
+// Lospec hex: "RRGGBB" lines
+
+fn read_hex(text: &str) -> Result<NamedPalette, String> {
+    let mut colors = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim().trim_start_matches('#');
+
+        if trimmed.is_empty() {
+            continue;
+        } else if is_hex_digits(trimmed, 6) {
+            let rgb = u32::from_str_radix(trimmed, 16).unwrap();
+            let [_, r, g, b] = rgb.to_be_bytes();
+            colors.push(Pixel::from_rgb(r, g, b));
+        } else {
+            return Err(format!("Couldn't parse line {} (`{line}`)", i + 1));
+        }
+    }
+
+    Ok(NamedPalette { name: None, colors })
+}
+
+fn write_hex(palette: &NamedPalette) -> String {
+    palette.colors.iter()
+        .map(|pix| format!("{:02x}{:02x}{:02x}\n", pix.red(), pix.green(), pix.blue()))
+        .collect::<String>()
+}
+
+// Adobe Swatch Exchange: big-endian binary. "ASEF", version (u16, u16),
+// block count (u32), then blocks of: type (u16), length (u32), data.
+// Color entries hold a UTF-16 name, a color model, f32 channels and a
+// color type; group-start blocks hold just a name.
+
+const ASE_GROUP_START: u16 = 0xC001;
+const ASE_GROUP_END: u16 = 0xC002;
+const ASE_COLOR_ENTRY: u16 = 0x0001;
+
+struct AseReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> AseReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.pos + n > self.bytes.len() {
+            return Err(String::from("Unexpected end of file"));
+        }
+
+        let res = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(res)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let len = self.u16()? as usize;
+        let units = (0..len)
+            .map(|_| self.u16())
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(String::from_utf16_lossy(&units).trim_end_matches('\0').to_string())
+    }
+}
+
+/// CIE L*a*b* (D50, as used by Adobe) to sRGB
+fn lab_to_rgb(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let fy = (l + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+
+    let finv = |t: f32| if t.powi(3) > 0.008856 { t.powi(3) } else { (t - 16.0 / 116.0) / 7.787 };
+    let (x, y, z) = (finv(fx) * 0.9642, finv(fy), finv(fz) * 0.8251);
+
+    // Bradford-adapted D50 XYZ -> linear sRGB
+    let r = 3.1338561 * x - 1.6168667 * y - 0.4906146 * z;
+    let g = -0.9787684 * x + 1.9161415 * y + 0.0334540 * z;
+    let bl = 0.0719453 * x - 0.2289914 * y + 1.4052427 * z;
+
+    let gamma = |c: f32| if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (gamma(r), gamma(g), gamma(bl))
+}
+
+fn read_ase(bytes: &[u8]) -> Result<NamedPalette, String> {
+    let mut reader = AseReader { bytes, pos: 0 };
+
+    if reader.take(4)? != b"ASEF" {
+        return Err(String::from("Missing `ASEF` signature"));
+    }
+
+    let _version = (reader.u16()?, reader.u16()?);
+    let num_blocks = reader.u32()?;
+
+    let mut name = None;
+    let mut colors = Vec::new();
+
+    for _ in 0..num_blocks {
+        let block_type = reader.u16()?;
+        let block_len = reader.u32()? as usize;
+        let block_end = reader.pos + block_len;
+
+        match block_type {
+            ASE_GROUP_START => {
+                // the first group's name is the closest thing to a palette name
+                let group_name = reader.name()?;
+                if name.is_none() && !group_name.is_empty() {
+                    name = Some(group_name);
+                }
+            },
+            ASE_COLOR_ENTRY => {
+                let _color_name = reader.name()?;
+                let model = reader.take(4)?;
+
+                let (r, g, b) = match model {
+                    b"RGB " => (reader.f32()?, reader.f32()?, reader.f32()?),
+                    b"CMYK" => {
+                        let (c, m, y, k) = (reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+                        ((1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k))
+                    },
+                    b"LAB " => {
+                        let (l, a, b) = (reader.f32()?, reader.f32()?, reader.f32()?);
+                        lab_to_rgb(l * 100.0, a, b)
+                    },
+                    b"Gray" => {
+                        let v = reader.f32()?;
+                        (v, v, v)
+                    },
+                    _ => return Err(format!("Unknown color model `{}`", String::from_utf8_lossy(model))),
+                };
+
+                let ch = |x: f32| (x.max(0.0).min(1.0) * 255.0).round() as u8;
+                colors.push(Pixel::from_rgb(ch(r), ch(g), ch(b)));
+            },
+            ASE_GROUP_END => (),
+            _ => (), // unknown block: skip it
+        }
+
+        reader.pos = block_end;
+    }
+
+    Ok(NamedPalette { name, colors })
+}
+
+fn write_ase(palette: &NamedPalette) -> Vec<u8> {
+    fn name_bytes(name: &str) -> Vec<u8> {
+        let units = name.encode_utf16().chain(std::iter::once(0)).collect::<Vec<_>>();
+        let mut res = (units.len() as u16).to_be_bytes().to_vec();
+        units.iter().for_each(|u| res.extend_from_slice(&u.to_be_bytes()));
+        res
+    }
+
+    fn push_block(res: &mut Vec<u8>, block_type: u16, data: Vec<u8>) {
+        res.extend_from_slice(&block_type.to_be_bytes());
+        res.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        res.extend(data);
+    }
+
+    let mut blocks = Vec::new();
+    let mut num_blocks: u32 = 0;
+
+    if let Some(ref name) = palette.name {
+        push_block(&mut blocks, ASE_GROUP_START, name_bytes(name));
+        num_blocks += 1;
+    }
+
+    for pix in palette.colors.iter() {
+        let hex = format!("#{:02x}{:02x}{:02x}", pix.red(), pix.green(), pix.blue());
+        let mut data = name_bytes(&hex);
+        data.extend_from_slice(b"RGB ");
+        for channel in [pix.red(), pix.green(), pix.blue()] {
+            data.extend_from_slice(&(channel as f32 / 255.0).to_be_bytes());
+        }
+        data.extend_from_slice(&2u16.to_be_bytes()); // "normal" (non-spot) color
+
+        push_block(&mut blocks, ASE_COLOR_ENTRY, data);
+        num_blocks += 1;
+    }
+
+    if palette.name.is_some() {
+        push_block(&mut blocks, ASE_GROUP_END, vec![]);
+        num_blocks += 1;
+    }
+
+    let mut res = b"ASEF".to_vec();
+    res.extend_from_slice(&1u16.to_be_bytes());
+    res.extend_from_slice(&0u16.to_be_bytes());
+    res.extend_from_slice(&num_blocks.to_be_bytes());
+    res.extend(blocks);
+    res
+}
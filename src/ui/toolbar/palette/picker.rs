@@ -0,0 +1,524 @@
+use super::io::{colors_to_string, parse_color, string_to_colors};
+use crate::image::{mk_transparent_checkerboard, Pixel};
+use crate::image::color::{hsv_to_rgb, pixel_to_hex, rgb_to_hsv};
+use crate::config::{read_config_file, write_config_file};
+
+use gtk::prelude::*;
+use gtk::gdk::RGBA;
+use gtk::cairo;
+use std::rc::Rc;
+use std::cell::RefCell;
+use glib_macros::clone;
+
+const RECENT_COLORS_FILE: &str = "recent-colors";
+const MAX_RECENT_COLORS: usize = 12;
+const RECENT_COLORS_PER_ROW: usize = 6;
+/// How long the color has to stay unchanged before
+/// it's recorded as a recent color
+const RECENT_COLOR_DELAY_MS: u64 = 750;
+
+fn same_color(a: &Pixel, b: &Pixel) -> bool {
+    (a.red(), a.green(), a.blue(), a.alpha()) == (b.red(), b.green(), b.blue(), b.alpha())
+}
+
+fn to_hex(color: RGBA) -> String {
+    pixel_to_hex(&Pixel::from_rgba_struct(color))
+}
+
+/// The sliders of the picker, in the order they're laid out
+#[derive(Clone, Copy)]
+enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    Hue,
+    Saturation,
+    Value,
+}
+
+impl Channel {
+    const ALL: [Channel; 7] = [
+        Channel::Red,
+        Channel::Green,
+        Channel::Blue,
+        Channel::Alpha,
+        Channel::Hue,
+        Channel::Saturation,
+        Channel::Value,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Channel::Red => "R",
+            Channel::Green => "G",
+            Channel::Blue => "B",
+            Channel::Alpha => "A",
+            Channel::Hue => "H",
+            Channel::Saturation => "S",
+            Channel::Value => "V",
+        }
+    }
+
+    fn max(&self) -> f64 {
+        match self {
+            Channel::Red | Channel::Green | Channel::Blue | Channel::Alpha => 255.0,
+            Channel::Hue => 359.0,
+            Channel::Saturation | Channel::Value => 100.0,
+        }
+    }
+}
+
+/// A docked color picker: a saturation/value square, a hue strip,
+/// RGB/HSV/alpha sliders, a hex entry, and a strip of recently
+/// used colors (which is saved across sessions)
+pub struct ColorPicker {
+    widget: gtk::Box,
+    sv_area: gtk::DrawingArea,
+    hue_area: gtk::DrawingArea,
+    scales: Vec<gtk::Scale>,
+    hex_entry: gtk::Entry,
+    recent_grid: gtk::Grid,
+    /// (hue, saturation, value, alpha): kept in hsv (rather than
+    /// rgb) so the hue isn't lost when the color is desaturated
+    hsva: (f64, f64, f64, f64),
+    recent_colors: Vec<Pixel>,
+    /// Set while the widgets are being updated to match `hsva`,
+    /// so their signal handlers don't feed back into it
+    syncing: bool,
+    /// Incremented on every change, so a delayed recent-color
+    /// update can tell whether the color has settled
+    generation: u64,
+    on_change: Rc<dyn Fn(RGBA)>,
+}
+
+impl ColorPicker {
+    pub fn new_p(initial_color: RGBA) -> Rc<RefCell<Self>> {
+        const AREA_SIZE: i32 = 72;
+        const HUE_WIDTH: i32 = 14;
+
+        let widget = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .valign(gtk::Align::Center)
+            .build();
+
+        let sv_area = gtk::DrawingArea::builder()
+            .content_height(AREA_SIZE)
+            .content_width(AREA_SIZE)
+            .build();
+
+        let hue_area = gtk::DrawingArea::builder()
+            .content_height(AREA_SIZE)
+            .content_width(HUE_WIDTH)
+            .build();
+
+        let slider_grid = gtk::Grid::builder()
+            .column_spacing(4)
+            .build();
+
+        let scales = Channel::ALL.iter()
+            .enumerate()
+            .map(|(i, channel)| {
+                let scale = gtk::Scale::builder()
+                    .orientation(gtk::Orientation::Horizontal)
+                    .adjustment(&gtk::Adjustment::new(0.0, 0.0, channel.max(), 1.0, 10.0, 0.0))
+                    .digits(0)
+                    .draw_value(true)
+                    .value_pos(gtk::PositionType::Right)
+                    .width_request(110)
+                    .build();
+
+                let label = gtk::Label::new(Some(channel.label()));
+
+                // rgba in the first pair of columns, hsv in the second
+                let (row, col) = (i as i32 % 4, i as i32 / 4 * 2);
+                slider_grid.attach(&label, col, row, 1, 1);
+                slider_grid.attach(&scale, col + 1, row, 1, 1);
+
+                scale
+            })
+            .collect::<Vec<_>>();
+
+        let hex_entry = gtk::Entry::builder()
+            .width_chars(9)
+            .max_width_chars(9)
+            .tooltip_text("Hex Color (#rrggbb or #rrggbbaa)")
+            .build();
+
+        let recent_grid = gtk::Grid::builder()
+            .row_spacing(2)
+            .column_spacing(2)
+            .build();
+
+        let hex_recent_wrapper = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(4)
+            .valign(gtk::Align::Center)
+            .build();
+
+        hex_recent_wrapper.append(&hex_entry);
+        hex_recent_wrapper.append(&recent_grid);
+
+        widget.append(&sv_area);
+        widget.append(&hue_area);
+        widget.append(&slider_grid);
+        widget.append(&hex_recent_wrapper);
+
+        let recent_colors = read_config_file(RECENT_COLORS_FILE)
+            .and_then(|contents| string_to_colors(&contents).ok())
+            .unwrap_or(vec![]);
+
+        let picker_p = Rc::new(RefCell::new(ColorPicker {
+            widget,
+            sv_area,
+            hue_area,
+            scales,
+            hex_entry,
+            recent_grid,
+            hsva: (0.0, 0.0, 0.0, 1.0),
+            recent_colors,
+            syncing: false,
+            generation: 0,
+            on_change: Rc::new(|_| ()),
+        }));
+
+        Self::set_up_areas(&picker_p);
+        Self::set_up_scales(&picker_p);
+
+        picker_p.borrow().hex_entry.connect_activate(clone!(@strong picker_p => move |entry| {
+            let text = entry.text();
+            let text = text.trim();
+            let text = if text.starts_with('#') {
+                text.to_string()
+            } else {
+                format!("#{text}")
+            };
+
+            if let Some(pix) = parse_color(&text) {
+                Self::user_set_color(&picker_p, pix.to_rgba_struct());
+            } else {
+                // revert to the current color
+                Self::sync_widgets(&picker_p);
+            }
+        }));
+
+        Self::update_recent_grid(&picker_p);
+        picker_p.borrow_mut().hsva = Self::rgb_to_hsva_keeping_hue(
+            (0.0, 0.0, 0.0, 1.0),
+            initial_color.red() as f64,
+            initial_color.green() as f64,
+            initial_color.blue() as f64,
+            initial_color.alpha() as f64,
+        );
+        Self::sync_widgets(&picker_p);
+
+        picker_p
+    }
+
+    fn set_up_areas(picker_p: &Rc<RefCell<Self>>) {
+        picker_p.borrow().sv_area.set_draw_func(clone!(@strong picker_p => move |_, cr, width, height| {
+            let (h, s, v, _a) = picker_p.borrow().hsva;
+            let (width, height) = (width as f64, height as f64);
+
+            let (r, g, b) = hsv_to_rgb(h, 1.0, 1.0);
+            cr.set_source_rgb(r, g, b);
+            let _ = cr.paint();
+
+            let white_gradient = cairo::LinearGradient::new(0.0, 0.0, width, 0.0);
+            white_gradient.add_color_stop_rgba(0.0, 1.0, 1.0, 1.0, 1.0);
+            white_gradient.add_color_stop_rgba(1.0, 1.0, 1.0, 1.0, 0.0);
+            let _ = cr.set_source(&white_gradient);
+            let _ = cr.paint();
+
+            let black_gradient = cairo::LinearGradient::new(0.0, 0.0, 0.0, height);
+            black_gradient.add_color_stop_rgba(0.0, 0.0, 0.0, 0.0, 0.0);
+            black_gradient.add_color_stop_rgba(1.0, 0.0, 0.0, 0.0, 1.0);
+            let _ = cr.set_source(&black_gradient);
+            let _ = cr.paint();
+
+            cr.arc(s * width, (1.0 - v) * height, 4.0, 0.0, 2.0 * std::f64::consts::PI);
+            cr.set_line_width(2.5);
+            cr.set_source_rgb(0.0, 0.0, 0.0);
+            let _ = cr.stroke_preserve();
+            cr.set_line_width(1.0);
+            cr.set_source_rgb(1.0, 1.0, 1.0);
+            let _ = cr.stroke();
+        }));
+
+        picker_p.borrow().hue_area.set_draw_func(clone!(@strong picker_p => move |_, cr, width, height| {
+            let h = picker_p.borrow().hsva.0;
+            let (width, height) = (width as f64, height as f64);
+
+            let gradient = cairo::LinearGradient::new(0.0, 0.0, 0.0, height);
+            for i in 0..=6 {
+                let (r, g, b) = hsv_to_rgb(i as f64 * 60.0, 1.0, 1.0);
+                gradient.add_color_stop_rgb(i as f64 / 6.0, r, g, b);
+            }
+            let _ = cr.set_source(&gradient);
+            let _ = cr.paint();
+
+            let y = h / 360.0 * height;
+            cr.rectangle(0.5, y - 1.5, width - 1.0, 3.0);
+            cr.set_line_width(1.0);
+            cr.set_source_rgb(0.0, 0.0, 0.0);
+            let _ = cr.stroke();
+        }));
+
+        fn set_sv(picker_p: &Rc<RefCell<ColorPicker>>, x: f64, y: f64) {
+            let width = picker_p.borrow().sv_area.width() as f64;
+            let height = picker_p.borrow().sv_area.height() as f64;
+            let (h, _s, _v, a) = picker_p.borrow().hsva;
+            let s = (x / width).clamp(0.0, 1.0);
+            let v = (1.0 - y / height).clamp(0.0, 1.0);
+            ColorPicker::user_set_hsva(picker_p, (h, s, v, a));
+        }
+
+        fn set_hue(picker_p: &Rc<RefCell<ColorPicker>>, y: f64) {
+            let height = picker_p.borrow().hue_area.height() as f64;
+            let (_h, s, v, a) = picker_p.borrow().hsva;
+            let h = (y / height).clamp(0.0, 1.0) * 359.0;
+            ColorPicker::user_set_hsva(picker_p, (h, s, v, a));
+        }
+
+        let sv_drag = gtk::GestureDrag::new();
+
+        sv_drag.connect_drag_begin(clone!(@strong picker_p => move |_, x, y| {
+            set_sv(&picker_p, x, y);
+        }));
+
+        sv_drag.connect_drag_update(clone!(@strong picker_p => move |gesture, dx, dy| {
+            if let Some((x, y)) = gesture.start_point() {
+                set_sv(&picker_p, x + dx, y + dy);
+            }
+        }));
+
+        picker_p.borrow().sv_area.add_controller(sv_drag);
+
+        let hue_drag = gtk::GestureDrag::new();
+
+        hue_drag.connect_drag_begin(clone!(@strong picker_p => move |_, _x, y| {
+            set_hue(&picker_p, y);
+        }));
+
+        hue_drag.connect_drag_update(clone!(@strong picker_p => move |gesture, _dx, dy| {
+            if let Some((_x, y)) = gesture.start_point() {
+                set_hue(&picker_p, y + dy);
+            }
+        }));
+
+        picker_p.borrow().hue_area.add_controller(hue_drag);
+    }
+
+    fn set_up_scales(picker_p: &Rc<RefCell<Self>>) {
+        for (scale, channel) in picker_p.borrow().scales.iter().zip(Channel::ALL) {
+            scale.connect_value_changed(clone!(@strong picker_p => move |scale| {
+                if picker_p.borrow().syncing {
+                    return;
+                }
+
+                let value = scale.value();
+                let (h, s, v, a) = picker_p.borrow().hsva;
+
+                let new_hsva = match channel {
+                    Channel::Hue => (value, s, v, a),
+                    Channel::Saturation => (h, value / 100.0, v, a),
+                    Channel::Value => (h, s, value / 100.0, a),
+                    Channel::Alpha => (h, s, v, value / 255.0),
+                    Channel::Red | Channel::Green | Channel::Blue => {
+                        let rgb_values = picker_p.borrow().scales.iter()
+                            .take(3)
+                            .map(|scale| scale.value() / 255.0)
+                            .collect::<Vec<_>>();
+                        Self::rgb_to_hsva_keeping_hue((h, s, v, a), rgb_values[0], rgb_values[1], rgb_values[2], a)
+                    },
+                };
+
+                Self::user_set_hsva(&picker_p, new_hsva);
+            }));
+        }
+    }
+
+    /// Converts the rgba to hsva, falling back to `old_hsva`'s hue
+    /// and saturation when they're undefined
+    fn rgb_to_hsva_keeping_hue(old_hsva: (f64, f64, f64, f64), r: f64, g: f64, b: f64, a: f64) -> (f64, f64, f64, f64) {
+        let (old_h, old_s, _old_v, _old_a) = old_hsva;
+        let (h, s, v) = rgb_to_hsv(r, g, b);
+        (h.unwrap_or(old_h), s.unwrap_or(old_s), v, a)
+    }
+
+    pub fn widget(&self) -> &gtk::Box {
+        &self.widget
+    }
+
+    pub fn color(&self) -> RGBA {
+        let (h, s, v, a) = self.hsva;
+        let (r, g, b) = hsv_to_rgb(h, s, v);
+        RGBA::new(r as f32, g as f32, b as f32, a as f32)
+    }
+
+    /// Set the function that's called whenever the user changes the color
+    pub fn set_on_change(&mut self, on_change: impl Fn(RGBA) + 'static) {
+        self.on_change = Rc::new(on_change);
+    }
+
+    /// Sets the picker's color (without calling `on_change`)
+    pub fn set_color(picker_p: &Rc<RefCell<Self>>, color: RGBA) {
+        let old_hsva = picker_p.borrow().hsva;
+        let current = Pixel::from_rgba_struct(picker_p.borrow().color());
+        if same_color(&current, &Pixel::from_rgba_struct(color)) {
+            return;
+        }
+
+        picker_p.borrow_mut().hsva = Self::rgb_to_hsva_keeping_hue(
+            old_hsva,
+            color.red() as f64,
+            color.green() as f64,
+            color.blue() as f64,
+            color.alpha() as f64,
+        );
+        Self::sync_widgets(picker_p);
+    }
+
+    fn user_set_color(picker_p: &Rc<RefCell<Self>>, color: RGBA) {
+        let old_hsva = picker_p.borrow().hsva;
+        let new_hsva = Self::rgb_to_hsva_keeping_hue(
+            old_hsva,
+            color.red() as f64,
+            color.green() as f64,
+            color.blue() as f64,
+            color.alpha() as f64,
+        );
+        Self::user_set_hsva(picker_p, new_hsva);
+    }
+
+    /// Sets the color in response to user input: updates the
+    /// widgets, calls `on_change`, and schedules the color to be
+    /// added to the recent colors (once it settles)
+    fn user_set_hsva(picker_p: &Rc<RefCell<Self>>, hsva: (f64, f64, f64, f64)) {
+        picker_p.borrow_mut().hsva = hsva;
+        picker_p.borrow_mut().generation += 1;
+        Self::sync_widgets(picker_p);
+
+        let color = picker_p.borrow().color();
+        let on_change = picker_p.borrow().on_change.clone();
+        on_change(color);
+
+        let generation = picker_p.borrow().generation;
+        gtk::glib::timeout_add_local_once(
+            std::time::Duration::from_millis(RECENT_COLOR_DELAY_MS),
+            clone!(@strong picker_p => move || {
+                if picker_p.borrow().generation == generation {
+                    let color = picker_p.borrow().color();
+                    Self::add_recent_color(&picker_p, color);
+                }
+            }),
+        );
+    }
+
+    /// Updates the sliders, entry, and drawing areas to match `hsva`
+    fn sync_widgets(picker_p: &Rc<RefCell<Self>>) {
+        picker_p.borrow_mut().syncing = true;
+
+        let (h, s, v, a) = picker_p.borrow().hsva;
+        let color = picker_p.borrow().color();
+        let pix = Pixel::from_rgba_struct(color);
+        let scales = picker_p.borrow().scales.clone();
+
+        for (scale, channel) in scales.iter().zip(Channel::ALL) {
+            let value = match channel {
+                Channel::Red => pix.red() as f64,
+                Channel::Green => pix.green() as f64,
+                Channel::Blue => pix.blue() as f64,
+                Channel::Alpha => (a * 255.0).round(),
+                Channel::Hue => h.round(),
+                Channel::Saturation => (s * 100.0).round(),
+                Channel::Value => (v * 100.0).round(),
+            };
+
+            scale.set_value(value);
+        }
+
+        let hex_entry = picker_p.borrow().hex_entry.clone();
+        hex_entry.set_text(&to_hex(color));
+
+        picker_p.borrow().sv_area.queue_draw();
+        picker_p.borrow().hue_area.queue_draw();
+
+        picker_p.borrow_mut().syncing = false;
+    }
+
+    /// Moves the color to the front of the recent colors,
+    /// saving them to the config file
+    pub fn add_recent_color(picker_p: &Rc<RefCell<Self>>, color: RGBA) {
+        let pix = Pixel::from_rgba_struct(color);
+
+        {
+            let recent_colors = &mut picker_p.borrow_mut().recent_colors;
+
+            if recent_colors.first().map(|first| same_color(first, &pix)).unwrap_or(false) {
+                return;
+            }
+
+            recent_colors.retain(|other| !same_color(other, &pix));
+            recent_colors.insert(0, pix);
+            recent_colors.truncate(MAX_RECENT_COLORS);
+        }
+
+        Self::update_recent_grid(picker_p);
+
+        // recent colors are a convenience (and this runs on every color
+        // change), so failing to save them isn't worth interrupting the user
+        let contents = colors_to_string(picker_p.borrow().recent_colors.clone());
+        let _ = write_config_file(RECENT_COLORS_FILE, &contents);
+    }
+
+    fn update_recent_grid(picker_p: &Rc<RefCell<Self>>) {
+        const SIZE: i32 = 14;
+
+        let recent_grid = picker_p.borrow().recent_grid.clone();
+
+        while let Some(child) = recent_grid.first_child() {
+            recent_grid.remove(&child);
+        }
+
+        let recent_colors = picker_p.borrow().recent_colors.clone();
+
+        for (i, pix) in recent_colors.into_iter().enumerate() {
+            let color = pix.to_rgba_struct();
+
+            let drawing_area = gtk::DrawingArea::builder()
+                .content_height(SIZE)
+                .content_width(SIZE)
+                .build();
+
+            let checkerboard = RefCell::new(mk_transparent_checkerboard());
+
+            drawing_area.set_draw_func(move |_, cr, width, height| {
+                cr.scale((width / 2).into(), (height / 2).into());
+
+                let transparent_pattern = checkerboard.borrow_mut().to_repeated_surface_pattern();
+                let _ = cr.set_source(&transparent_pattern);
+                cr.rectangle(0.0, 0.0, 2.1, 2.1);
+                let _ = cr.fill();
+
+                cr.set_source_rgba(color.red().into(), color.green().into(), color.blue().into(), color.alpha().into());
+                cr.rectangle(0.0, 0.0, 2.1, 2.1);
+                let _ = cr.fill();
+            });
+
+            let button = gtk::Button::builder()
+                .child(&drawing_area)
+                .css_classes(["no-padding", "palette-button"])
+                .tooltip_text(to_hex(color))
+                .build();
+
+            button.connect_clicked(clone!(@strong picker_p => move |_| {
+                Self::user_set_color(&picker_p, color);
+            }));
+
+            let (row, col) = (i / RECENT_COLORS_PER_ROW, i % RECENT_COLORS_PER_ROW);
+            recent_grid.attach(&button, col as i32, row as i32, 1, 1);
+        }
+    }
+}